@@ -0,0 +1,40 @@
+//! Ray and bounding box intersection, the innermost test of BVH traversal.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::{Rng, SeedableRng};
+use rtiow::aabb::Aabb;
+use rtiow::ray::Ray;
+use rtiow::vec3::{Point3, Vec3};
+
+fn aabb_hit(c: &mut Criterion) {
+    // The same boxes and rays every run, roughly half of which hit
+    let mut rng = rtiow::rng::RenderRng::seed_from_u64(1);
+    let boxes: Vec<Aabb> = (0..256)
+        .map(|_| {
+            let min = Vec3::random_range(&mut rng, -10.0, 10.0);
+            Aabb::new(&min, &(min + Vec3::random_range(&mut rng, 0.5, 5.0)))
+        })
+        .collect();
+    let rays: Vec<Ray> = (0..256)
+        .map(|_| {
+            let origin = Point3::new(0.0, 0.0, -30.0);
+            let target = Vec3::random_range(&mut rng, -10.0, 10.0);
+            Ray::new(origin, target - origin, rng.gen())
+        })
+        .collect();
+
+    c.bench_function("aabb_hit", |b| {
+        b.iter(|| {
+            let mut hits = 0;
+            for r in &rays {
+                for bbox in &boxes {
+                    hits += usize::from(bbox.hit(criterion::black_box(r), 0.001, f64::INFINITY));
+                }
+            }
+            hits
+        })
+    });
+}
+
+criterion_group!(benches, aabb_hit);
+criterion_main!(benches);
@@ -0,0 +1,35 @@
+//! Paths traced through the Cornell box, the per-sample work of a render.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::{Rng, SeedableRng};
+use rtiow::render::{RenderConfig, RenderContext};
+use rtiow::scene::Scene;
+
+fn ray_color(c: &mut Criterion) {
+    let config = RenderConfig::new(64, 64);
+    let mut rng = rtiow::rng::scene_rng(1);
+    let scene = rtiow::scene::find("cornell_box").unwrap();
+    let (cam, world) = scene.build(&mut rng, config.img_w, config.img_h).unwrap();
+    let ctx = RenderContext::new(&config, &world);
+
+    // The same camera rays every run, and the same random numbers along each
+    let mut rng = rtiow::rng::RenderRng::seed_from_u64(1);
+    let rays: Vec<_> = (0..256)
+        .map(|_| {
+            let (s, t) = (rng.gen(), rng.gen());
+            cam.get_ray(&mut rng, s, t)
+        })
+        .collect();
+
+    c.bench_function("ray_color", |b| {
+        b.iter(|| {
+            let mut rng = rtiow::rng::RenderRng::seed_from_u64(2);
+            rays.iter()
+                .map(|r| rtiow::ray::ray_color(&mut rng, criterion::black_box(r), &ctx))
+                .sum::<rtiow::vec3::Color>()
+        })
+    });
+}
+
+criterion_group!(benches, ray_color);
+criterion_main!(benches);
@@ -0,0 +1,30 @@
+//! Render times of small versions of a few scenes, see `rtiow::bench`.
+//!
+//! Run with `cargo bench`, and compare against a baseline saved from before a
+//! change with `cargo bench -- --save-baseline before` then
+//! `cargo bench -- --baseline before`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+/// Seed for building and rendering every scene, so each run traces the same
+/// rays.
+const SEED: u64 = 1;
+
+fn scenes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scenes");
+    group.sample_size(10);
+    group.measurement_time(std::time::Duration::from_secs(10));
+    for &name in rtiow::bench::SCENES {
+        group.bench_function(name, |b| {
+            b.iter_custom(|iters| {
+                (0..iters)
+                    .map(|_| rtiow::bench::bench_scene(name, 96, 54, 8, SEED).unwrap())
+                    .sum()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scenes);
+criterion_main!(benches);
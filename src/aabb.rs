@@ -1,7 +1,12 @@
 //! Axis-alligned bounding box used for Bounding Volume Hierarchy calculation.
 
+use crate::bvh::Axis;
 use crate::ray::Ray;
-use crate::vec3::Point3;
+use crate::vec3::{Point3, Vec3};
+
+/// Default padding given to each side of flat bounding boxes by
+/// [`Aabb::padded`].
+pub const PADDING: f64 = 0.0001;
 
 /// Axis-aligned bounding box.
 #[derive(Clone, Copy, Default, Debug)]
@@ -18,6 +23,29 @@ impl Aabb {
         Self { min: *a, max: *b }
     }
 
+    /// Create a bounding box from `min` to `max`, widening any dimension
+    /// thinner than `2.0 * pad` to reach `pad` either side of its middle.
+    ///
+    /// Flat objects like rectangles and triangles have no thickness along
+    /// their normal, and the slab test can miss a box with none. [`PADDING`]
+    /// suits scenes measured in units to hundreds of units. Scale it with
+    /// scenes much smaller or larger than that.
+    pub fn padded(min: &Point3, max: &Point3, pad: f64) -> Self {
+        let widen = |min: f64, max: f64| {
+            if max - min < 2.0 * pad {
+                let middle = 0.5 * (min + max);
+                (middle - pad, middle + pad)
+            } else {
+                (min, max)
+            }
+        };
+        let (x0, x1) = widen(min.x(), max.x());
+        let (y0, y1) = widen(min.y(), max.y());
+        let (z0, z1) = widen(min.z(), max.z());
+
+        Self::new(&Point3::new(x0, y0, z0), &Point3::new(x1, y1, z1))
+    }
+
     /// Return the min.
     pub fn min(&self) -> Point3 {
         self.min
@@ -28,8 +56,42 @@ impl Aabb {
         self.max
     }
 
+    /// Return the vector from the min corner to the max corner.
+    pub fn diagonal(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// Return the surface area of the box.
+    pub fn surface_area(&self) -> f64 {
+        let d = self.diagonal();
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    /// Return the center point of the box.
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.min + self.max)
+    }
+
+    /// Return the axis along which the box is widest.
+    pub fn longest_axis(&self) -> Axis {
+        let d = self.diagonal();
+        if d.x() > d.y() && d.x() > d.z() {
+            Axis::X
+        } else if d.y() > d.z() {
+            Axis::Y
+        } else {
+            Axis::Z
+        }
+    }
+
     /// Check whether a ray strikes the bounding box.
     pub fn hit(&self, r: &Ray, tmin: f64, tmax: f64) -> bool {
+        self.hit_t(r, tmin, tmax).is_some()
+    }
+
+    /// Return the interval `(t_enter, t_exit)` of a ray inside the bounding
+    /// box, clipped to `tmin..tmax`, or `None` if it misses.
+    pub fn hit_t(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<(f64, f64)> {
         let inv_d = r.direction().x().recip();
         let mut t0 = (self.min().x() - r.origin().x()) * inv_d;
         let mut t1 = (self.max().x() - r.origin().x()) * inv_d;
@@ -39,7 +101,7 @@ impl Aabb {
         let tmin = if t0 > tmin { t0 } else { tmin };
         let tmax = if t1 < tmax { t1 } else { tmax };
         if tmax <= tmin {
-            return false;
+            return None;
         }
         let inv_d = r.direction().y().recip();
         let mut t0 = (self.min().y() - r.origin().y()) * inv_d;
@@ -50,7 +112,7 @@ impl Aabb {
         let tmin = if t0 > tmin { t0 } else { tmin };
         let tmax = if t1 < tmax { t1 } else { tmax };
         if tmax <= tmin {
-            return false;
+            return None;
         }
         let inv_d = r.direction().z().recip();
         let mut t0 = (self.min().z() - r.origin().z()) * inv_d;
@@ -61,10 +123,10 @@ impl Aabb {
         let tmin = if t0 > tmin { t0 } else { tmin };
         let tmax = if t1 < tmax { t1 } else { tmax };
         if tmax <= tmin {
-            return false;
+            return None;
         }
 
-        true
+        Some((tmin, tmax))
     }
 
     /// Compute the bounding box of two boxes.
@@ -83,3 +145,60 @@ impl Aabb {
         Aabb::new(&a, &b)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb {
+        Aabb::new(&Point3::new(0.0, 0.0, 0.0), &Point3::new(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn hit_t_gives_entry_and_exit() {
+        let r = Ray::new(Point3::new(-1.0, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert_eq!(unit_box().hit_t(&r, 0.0, f64::INFINITY), Some((1.0, 2.0)));
+
+        // Backwards along a diagonal, at half speed
+        let r = Ray::new(Point3::new(3.0, 3.0, 3.0), Vec3::new(-0.5, -0.5, -0.5), 0.0);
+        assert_eq!(unit_box().hit_t(&r, 0.0, f64::INFINITY), Some((4.0, 6.0)));
+
+        // Clipped to the range, and missed outside of it
+        assert_eq!(unit_box().hit_t(&r, 5.0, 5.5), Some((5.0, 5.5)));
+        assert_eq!(unit_box().hit_t(&r, 0.0, 3.0), None);
+    }
+
+    #[test]
+    fn helpers_describe_a_known_box() {
+        let b = Aabb::new(&Point3::new(-1.0, 0.0, 2.0), &Point3::new(3.0, 1.0, 4.0));
+        let d = b.diagonal();
+        assert_eq!((d.x(), d.y(), d.z()), (4.0, 1.0, 2.0));
+        // 2 * (4*1 + 1*2 + 2*4)
+        assert_eq!(b.surface_area(), 28.0);
+        let c = b.centroid();
+        assert_eq!((c.x(), c.y(), c.z()), (1.0, 0.5, 3.0));
+        assert_eq!(b.longest_axis(), Axis::X);
+
+        let tall = Aabb::new(&Point3::new(0.0, 0.0, 0.0), &Point3::new(1.0, 5.0, 2.0));
+        assert_eq!(tall.longest_axis(), Axis::Y);
+        let deep = Aabb::new(&Point3::new(0.0, 0.0, 0.0), &Point3::new(1.0, 1.0, 2.0));
+        assert_eq!(deep.longest_axis(), Axis::Z);
+    }
+
+    #[test]
+    fn flat_boxes_are_padded_to_a_thickness() {
+        let flat = Aabb::padded(
+            &Point3::new(0.0, 2.0, 0.0),
+            &Point3::new(1.0, 2.0, 1.0),
+            0.01,
+        );
+        let d = flat.diagonal();
+        assert_eq!((d.x(), d.z()), (1.0, 1.0));
+        assert!((d.y() - 0.02).abs() < 1e-12, "{}", d.y());
+        assert!(flat.min().y() < 2.0 && flat.max().y() > 2.0);
+
+        // A ray grazing along the plane still finds the box
+        let r = Ray::new(Point3::new(-1.0, 2.0, 0.5), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(flat.hit(&r, 0.0, f64::INFINITY));
+    }
+}
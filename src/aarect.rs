@@ -6,8 +6,6 @@ use crate::hittable::Hittable;
 use crate::material::Material;
 use crate::vec3::{Point3, Vec3};
 
-const PADDING: f64 = 0.0001;
-
 /// Axis-aligned rectangle in the XY plane.
 #[derive(Clone)]
 pub struct AaRect {
@@ -25,6 +23,8 @@ pub struct AaRect {
     pub mp: std::sync::Arc<Material>,
     /// Plane type of axis-aligned rectangle.
     pub plane: Plane,
+    /// Padding either side of the plane for the bounding box.
+    pub padding: f64,
 }
 
 /// Plane types for axis-aligned rectangles.
@@ -57,8 +57,79 @@ impl AaRect {
             k,
             mp,
             plane,
+            padding: crate::aabb::PADDING,
         }
     }
+
+    /// Set the padding either side of the plane for the bounding box, see
+    /// [`Aabb::padded`](crate::aabb::Aabb::padded).
+    pub fn with_padding(mut self, padding: f64) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Area of the rectangle.
+    pub fn area(&self) -> f64 {
+        (self.a1 - self.a0) * (self.b1 - self.b0)
+    }
+}
+
+impl AaRect {
+    /// Where `r` crosses the rectangle between `t_min` and `t_max`, as `t`, the
+    /// texture coordinates and the point, unless the material cuts a hole
+    /// there.
+    #[inline]
+    fn crossing(
+        &self,
+        r: &crate::ray::Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<(f64, f64, f64, Point3)> {
+        let (origin, direction) = (r.origin(), r.direction());
+        // Distance along the plane's axis, then along the rectangle's sides
+        let (k, a, b) = match self.plane {
+            Plane::Xy => (
+                (origin.z(), direction.z()),
+                (origin.x(), direction.x()),
+                (origin.y(), direction.y()),
+            ),
+            Plane::Xz => (
+                (origin.y(), direction.y()),
+                (origin.x(), direction.x()),
+                (origin.z(), direction.z()),
+            ),
+            Plane::Yz => (
+                (origin.x(), direction.x()),
+                (origin.y(), direction.y()),
+                (origin.z(), direction.z()),
+            ),
+        };
+
+        // Rays parallel to the plane never cross it, and would otherwise give
+        // an infinite or NaN `t` which slips past the range checks
+        if k.1.abs() < f64::EPSILON {
+            return None;
+        }
+        let t = (self.k - k.0) * k.1.recip();
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let a = a.0 + t * a.1;
+        let b = b.0 + t * b.1;
+        if a < self.a0 || a > self.a1 || b < self.b0 || b > self.b1 {
+            return None;
+        }
+
+        let u = (a - self.a0) * (self.a1 - self.a0).recip();
+        let v = (b - self.b0) * (self.b1 - self.b0).recip();
+        let p = r.at(t);
+        if self.mp.is_cut_out(u, v, &p) {
+            return None;
+        }
+
+        Some((t, u, v, p))
+    }
 }
 
 impl Hittable for AaRect {
@@ -69,122 +140,76 @@ impl Hittable for AaRect {
         t_max: f64,
         rec: &mut crate::hittable::HitRecord,
     ) -> bool {
-        match self.plane {
-            Plane::Xy => {
-                let t = (self.k - r.origin().z()) * r.direction().z().recip();
-                if t < t_min || t > t_max {
-                    return false;
-                }
-
-                let x = r.origin().x() + t * r.direction().x();
-                let y = r.origin().y() + t * r.direction().y();
-                if x < self.a0 || x > self.a1 || y < self.b0 || y > self.b1 {
-                    return false;
-                }
-
-                rec.u = (x - self.a0) * (self.a1 - self.a0).recip();
-                rec.v = (y - self.b0) * (self.b1 - self.b0).recip();
-                rec.t = t;
-                let outward_normal = Vec3::new(0.0, 0.0, 1.0);
-                rec.set_face_normal(r, &outward_normal);
-                rec.material = self.mp.clone();
-                rec.p = r.at(t);
-
-                true
-            }
-            Plane::Xz => {
-                let t = (self.k - r.origin().y()) * r.direction().y().recip();
-                if t < t_min || t > t_max {
-                    return false;
-                }
-
-                let x = r.origin().x() + t * r.direction().x();
-                let z = r.origin().z() + t * r.direction().z();
-                if x < self.a0 || x > self.a1 || z < self.b0 || z > self.b1 {
-                    return false;
-                }
-
-                rec.u = (x - self.a0) * (self.a1 - self.a0).recip();
-                rec.v = (z - self.b0) * (self.b1 - self.b0).recip();
-                rec.t = t;
-                let outward_normal = Vec3::new(0.0, 1.0, 0.0);
-                rec.set_face_normal(r, &outward_normal);
-                rec.material = self.mp.clone();
-                rec.p = r.at(t);
-
-                true
-            }
-            Plane::Yz => {
-                let t = (self.k - r.origin().x()) * r.direction().x().recip();
-                if t < t_min || t > t_max {
-                    return false;
-                }
-
-                let y = r.origin().y() + t * r.direction().y();
-                let z = r.origin().z() + t * r.direction().z();
-                if y < self.a0 || y > self.a1 || z < self.b0 || z > self.b1 {
-                    return false;
-                }
-
-                rec.u = (y - self.a0) * (self.a1 - self.a0).recip();
-                rec.v = (z - self.b0) * (self.b1 - self.b0).recip();
-                rec.t = t;
-                let outward_normal = Vec3::new(1.0, 0.0, 0.0);
-                rec.set_face_normal(r, &outward_normal);
-                rec.material = self.mp.clone();
-                rec.p = r.at(t);
-
-                true
-            }
-        }
+        let (t, u, v, p) = match self.crossing(r, t_min, t_max) {
+            Some(crossing) => crossing,
+            None => return false,
+        };
+        let (outward_normal, tangent) = match self.plane {
+            Plane::Xy => (Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0)),
+            Plane::Xz => (Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)),
+            Plane::Yz => (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        };
+
+        rec.u = u;
+        rec.v = v;
+        rec.t = t;
+        rec.set_face_normal(r, &outward_normal);
+        rec.tangent = tangent;
+        rec.material = self.mp.clone();
+        rec.set_object(self);
+        rec.p = p;
+
+        true
+    }
+
+    fn occluded(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64) -> bool {
+        self.crossing(r, t_min, t_max).is_some()
     }
 
     fn bounding_box(&self, _t0: f64, _t1: f64, output_box: &mut crate::aabb::Aabb) -> bool {
-        match self.plane {
-            Plane::Xy => {
-                // Pad the bounding box so it doesn't have a non-zero width in any dimension
-                *output_box = crate::aabb::Aabb::new(
-                    &Point3::new(self.a0, self.b0, self.k - PADDING),
-                    &Point3::new(self.a1, self.b1, self.k + PADDING),
-                );
-                true
-            }
-            Plane::Xz => {
-                *output_box = crate::aabb::Aabb::new(
-                    &Point3::new(self.a0, self.k - PADDING, self.b0),
-                    &Point3::new(self.a1, self.k + PADDING, self.b1),
-                );
-                true
-            }
-            Plane::Yz => {
-                *output_box = crate::aabb::Aabb::new(
-                    &Point3::new(self.k - PADDING, self.a0, self.b0),
-                    &Point3::new(self.k + PADDING, self.a1, self.b1),
-                );
-                true
-            }
-        }
+        let (min, max) = match self.plane {
+            Plane::Xy => (
+                Point3::new(self.a0, self.b0, self.k),
+                Point3::new(self.a1, self.b1, self.k),
+            ),
+            Plane::Xz => (
+                Point3::new(self.a0, self.k, self.b0),
+                Point3::new(self.a1, self.k, self.b1),
+            ),
+            Plane::Yz => (
+                Point3::new(self.k, self.a0, self.b0),
+                Point3::new(self.k, self.a1, self.b1),
+            ),
+        };
+        *output_box = crate::aabb::Aabb::padded(&min, &max, self.padding);
+        true
+    }
+
+    fn power(&self) -> Option<f64> {
+        let (a, b) = (0.5 * (self.a0 + self.a1), 0.5 * (self.b0 + self.b1));
+        let center = match self.plane {
+            Plane::Xy => Point3::new(a, b, self.k),
+            Plane::Xz => Point3::new(a, self.k, b),
+            Plane::Yz => Point3::new(self.k, a, b),
+        };
+        let emission = self.mp.emission(0.5, 0.5, &center);
+        Some(self.area() * (emission.x() + emission.y() + emission.z()) / 3.0)
     }
 
     fn pdf_value(&self, o: &Point3, v: &Vec3) -> f64 {
         let mut rec = crate::hittable::HitRecord::default();
-        if !self.hit(
-            &crate::ray::Ray::new(*o, *v, 0.0),
-            0.001,
-            f64::INFINITY,
-            &mut rec,
-        ) {
+        let r = crate::ray::Ray::new(*o, *v, 0.0);
+        if !self.hit(&r, r.step_past(0.0), f64::INFINITY, &mut rec) {
             return 0.0;
         }
 
-        let area = (self.a1 - self.a0) * (self.b1 - self.b0);
         let distance_squared = rec.t * rec.t * v.length_squared();
         let cosine = (v.dot(&rec.normal) * v.length().recip()).abs();
 
-        distance_squared * (cosine * area).recip()
+        distance_squared * (cosine * self.area()).recip()
     }
 
-    fn random(&self, rng: &mut rand::rngs::ThreadRng, origin: &Vec3) -> Vec3 {
+    fn random(&self, rng: &mut crate::rng::RenderRng, origin: &Vec3) -> Vec3 {
         match self.plane {
             Plane::Xy => {
                 let random_point = Point3::new(
@@ -213,3 +238,54 @@ impl Hittable for AaRect {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::HitRecord;
+    use crate::ray::Ray;
+
+    #[test]
+    fn rays_parallel_to_an_xy_rect_miss_it() {
+        let rect = AaRect::new(
+            -1.0,
+            1.0,
+            -1.0,
+            1.0,
+            0.0,
+            std::sync::Arc::new(Material::default()),
+            Plane::Xy,
+        );
+        // Running along the rectangle's own plane, and beside it
+        for &z in &[0.0, 0.5] {
+            let r = Ray::new(Point3::new(-2.0, 0.0, z), Vec3::new(1.0, 0.0, 0.0), 0.0);
+            let mut rec = HitRecord::default();
+            assert!(
+                !rect.hit(&r, 0.001, f64::INFINITY, &mut rec),
+                "hit at z = {}",
+                z
+            );
+            assert!(!rect.occluded(&r, 0.001, f64::INFINITY));
+        }
+    }
+
+    #[test]
+    fn pdf_is_the_same_at_every_scale() {
+        // Looking straight at the middle of the rect along a unit direction,
+        // as when weighing a direction drawn from a material. The rect's area
+        // grows with the square of the distance, so the pdf stays the same.
+        for &scale in &[1e-6, 1e-3, 1.0, 555.0, 1e9] {
+            let rect = AaRect::new(
+                -scale,
+                scale,
+                -scale,
+                scale,
+                scale,
+                std::sync::Arc::new(Material::default()),
+                Plane::Xy,
+            );
+            let pdf = rect.pdf_value(&Point3::new_with(0.0), &Vec3::new(0.0, 0.0, 1.0));
+            assert!((pdf - 0.25).abs() < 1e-12, "pdf {} at scale {}", pdf, scale);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Timing renders of a few fixed scenes, so performance changes can be
+//! compared against the same baseline.
+
+use std::time::{Duration, Instant};
+
+use crate::render::{Background, RenderConfig};
+
+/// Names of the scenes timed as a baseline by [`bench_scene`].
+#[cfg(feature = "images")]
+pub const SCENES: &[&str] = &["cornell_box", "bouncing_spheres", "final_scene"];
+/// Names of the scenes timed as a baseline by [`bench_scene`].
+#[cfg(not(feature = "images"))]
+pub const SCENES: &[&str] = &["cornell_box", "bouncing_spheres"];
+
+/// Time rendering the scene `name` at `img_w` by `img_h` pixels with `samples`
+/// samples per pixel.
+///
+/// `name` is any scene of [`crate::scene::SCENES`], and [`SCENES`] lists the
+/// usual baseline: the Cornell box of the third book, and the bouncing
+/// spheres and final scene of the second. The scene and the render are both
+/// seeded from `seed`, so every call traces the same rays. Rendering runs on
+/// a single thread so timings don't depend on the number of cores, and only
+/// rendering is timed, not building the scene.
+///
+/// Fails if `name` isn't a known scene or the scene can't be built.
+pub fn bench_scene(
+    name: &str,
+    img_w: u32,
+    img_h: u32,
+    samples: u32,
+    seed: u64,
+) -> Result<Duration, Box<dyn std::error::Error>> {
+    let mut rng = crate::rng::scene_rng(seed);
+    let scene =
+        crate::scene::find(name).ok_or_else(|| format!("unknown benchmark scene `{}`", name))?;
+    let (cam, world) = crate::scene::Scene::build(scene, &mut rng, img_w, img_h)?;
+
+    let config = RenderConfig {
+        samples,
+        background: Background::Solid(scene.background),
+        threads: false,
+        seed,
+        ..RenderConfig::new(img_w, img_h)
+    };
+
+    let now = Instant::now();
+    let colors = crate::render::render(&config, &world, &cam);
+    let elapsed = now.elapsed();
+    // Keep the render from being optimized away
+    std::hint::black_box(colors);
+
+    Ok(elapsed)
+}
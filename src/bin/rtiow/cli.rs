@@ -0,0 +1,414 @@
+//! Command line parsing for the `rtiow` binary.
+
+/// Output image formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Plain text PPM.
+    Ppm,
+    /// PNG, with an alpha channel for transparent backgrounds.
+    #[cfg(feature = "images")]
+    Png,
+    /// OpenEXR with linear radiance and normal, depth and albedo layers.
+    #[cfg(feature = "exr")]
+    Exr,
+}
+
+impl Format {
+    /// File extension for the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Ppm => "ppm",
+            #[cfg(feature = "images")]
+            Format::Png => "png",
+            #[cfg(feature = "exr")]
+            Format::Exr => "exr",
+        }
+    }
+}
+
+impl core::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ppm" => Ok(Format::Ppm),
+            #[cfg(feature = "images")]
+            "png" => Ok(Format::Png),
+            #[cfg(feature = "exr")]
+            "exr" => Ok(Format::Exr),
+            #[cfg(not(feature = "exr"))]
+            "exr" => Err("`exr` output requires building with the `exr` feature".to_owned()),
+            _ => Err(format!(
+                "unsupported format `{}`, expected one of: ppm, png, exr",
+                s
+            )),
+        }
+    }
+}
+
+/// Settings for a render parsed from the command line.
+pub struct Config {
+    /// Scene to render.
+    pub scene: &'static rtiow::scene::SceneEntry,
+    /// Number of samples per pixel.
+    pub samples: u32,
+    /// Width of the output image.
+    pub img_w: u32,
+    /// Height of the output image.
+    pub img_h: u32,
+    /// Seed for the random numbers used to build and render the scene.
+    pub seed: u64,
+    /// Path of the output image.
+    pub out: std::path::PathBuf,
+    /// Format of the output image.
+    pub format: Format,
+    /// Rectangle of pixels to render, `(x0, y0, x1, y1)` from the top left.
+    pub region: Option<(u32, u32, u32, u32)>,
+    /// How samples are placed over each pixel and the lens.
+    pub sampler: rtiow::sampler::Sampler,
+    /// Distribution of the samples within each pixel.
+    pub jitter: rtiow::sampler::Jitter,
+    /// Step size for ray-marching fog with single scattering, if enabled.
+    pub volumetric: Option<f64>,
+    /// Whether to dither the 8-bit output.
+    pub dither: bool,
+    /// Stops of exposure applied before tone mapping.
+    pub exposure: f64,
+    /// White point of Reinhard tone mapping, if enabled.
+    pub white: Option<f64>,
+    /// Whether to print a hash of the 8-bit output.
+    pub hash: bool,
+    /// Whether rays that miss everything leave the pixel transparent.
+    pub transparent: bool,
+    /// Whether rays that miss everything see a daylight sky.
+    #[cfg_attr(not(feature = "sky"), allow(dead_code))]
+    pub sky: bool,
+    /// Equirectangular image seen by rays that miss everything, if any.
+    pub environment: Option<std::path::PathBuf>,
+    /// Whether to show the render in a window as it converges.
+    #[cfg_attr(not(feature = "preview"), allow(dead_code))]
+    pub preview: bool,
+}
+
+impl Config {
+    /// Warning for an image whose aspect ratio is off from the one its scene is
+    /// framed for.
+    ///
+    /// Scenes build their cameras for the image's own aspect ratio, so nothing
+    /// is stretched, but the vertical field of view stays the same and the
+    /// sides show more or less of the scene than it was framed for.
+    pub fn aspect_warning(&self) -> Option<String> {
+        let aspect_ratio = self.scene.aspect_ratio;
+        if self
+            .img_h
+            .abs_diff(default_height(self.img_w, aspect_ratio))
+            <= 1
+        {
+            return None;
+        }
+        Some(format!(
+            "{}x{} isn't the {}:1 that `{}` is framed for, so it'll show {} at the sides",
+            self.img_w,
+            self.img_h,
+            (aspect_ratio * 100.0).round() / 100.0,
+            self.scene.name,
+            if f64::from(self.img_w) > aspect_ratio * f64::from(self.img_h) {
+                "more"
+            } else {
+                "less"
+            }
+        ))
+    }
+}
+
+/// Height of an image `img_w` wide at `aspect_ratio`.
+fn default_height(img_w: u32, aspect_ratio: f64) -> u32 {
+    (f64::from(img_w) * aspect_ratio.recip()).max(2.0) as u32
+}
+
+/// Reasons parsing can stop without producing a `Config`.
+#[derive(Debug)]
+pub enum CliError {
+    /// `--help` was passed.
+    Help,
+    /// `--list` was passed.
+    List,
+    /// An argument was missing or malformed.
+    Invalid(String),
+}
+
+impl core::fmt::Display for CliError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CliError::Help => write!(f, "help requested"),
+            CliError::List => write!(f, "scene list requested"),
+            CliError::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Build the usage message, including the list of scenes.
+pub fn usage() -> String {
+    let mut msg = String::from(
+        "Usage: rtiow [OPTIONS]
+
+Options:
+    --scene <name>     Scene to render [default: cornell_box_sphere]
+    --samples <n>      Samples per pixel [default: 100]
+    --width <n>        Image width in pixels [default: 384]
+    --height <n>       Image height in pixels [default: width at the scene's
+                       aspect ratio]
+    --seed <n>         Seed for the scene and render [default: current time]
+    --out <path>       Output file [default: image0.<format>]
+    --format <fmt>     Output format: ppm, png, exr [default: ppm]
+                       (exr requires the `exr` feature)
+    --region <x0,y0,x1,y1>
+                       Only render pixels from (x0, y0) up to, but not
+                       including, (x1, y1), measured from the top left
+    --sampler <name>   Sample placement: random, cmj [default: random]
+    --jitter <name>    Sample spread within a pixel: box, tent, gaussian
+                       [default: box]
+    --volumetric <step>
+                       Ray-march fog with single scattering, sampling every
+                       <step> world units, to bring out shafts of light
+    --dither           Dither the output to hide banding in smooth gradients
+    --exposure <stops> Brighten, or darken if negative, by powers of two
+                       [default: 0]
+    --white <lum>      Tone map with Reinhard's curve, showing luminance <lum>
+                       and above as white, or `inf` for the basic curve
+    --hash             Print a hash of the image's 8-bit colors, to check
+                       whether a change altered the render
+    --transparent      Leave the background transparent, png and exr only
+    --sky              Light the scene with a midday sun and sky
+                       (requires the `sky` feature)
+    --environment <path>
+                       Surround the scene with an equirectangular image,
+                       blurred in rough reflections
+                       (requires the `images` feature)
+    --preview          Show the render in a window as it converges
+                       (requires the `preview` feature)
+    --list             Print the names of the scenes
+    -h, --help         Print this message
+
+Scenes:
+",
+    );
+    for scene in rtiow::scene::SCENES {
+        msg.push_str(&format!("    {:<20} {}\n", scene.name, scene.description));
+    }
+    msg
+}
+
+/// Parse the command line arguments, not including the program name.
+pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Config, CliError> {
+    let mut scene_name = "cornell_box_sphere".to_owned();
+    let mut samples = 100;
+    let mut img_w = 384;
+    let mut img_h = None;
+    let mut seed = None;
+    let mut out = None;
+    let mut format = Format::Ppm;
+    let mut region = None;
+    let mut sampler = rtiow::sampler::Sampler::Random;
+    let mut jitter = rtiow::sampler::Jitter::Box;
+    let mut volumetric = None;
+    let mut dither = false;
+    let mut exposure: f64 = 0.0;
+    let mut white = None;
+    let mut hash = false;
+    let mut transparent = false;
+    let mut sky = false;
+    let mut environment = None;
+    let mut preview = false;
+
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Err(CliError::Help);
+        }
+        if arg == "--list" {
+            return Err(CliError::List);
+        }
+        if arg == "--dither" {
+            dither = true;
+            continue;
+        }
+        if arg == "--hash" {
+            hash = true;
+            continue;
+        }
+        if arg == "--transparent" {
+            transparent = true;
+            continue;
+        }
+        if arg == "--sky" {
+            if !cfg!(feature = "sky") {
+                return Err(CliError::Invalid(
+                    "`--sky` requires building with the `sky` feature".to_owned(),
+                ));
+            }
+            sky = true;
+            continue;
+        }
+        if arg == "--preview" {
+            if !cfg!(feature = "preview") {
+                return Err(CliError::Invalid(
+                    "`--preview` requires building with the `preview` feature".to_owned(),
+                ));
+            }
+            preview = true;
+            continue;
+        }
+
+        // Accept both `--flag value` and `--flag=value`
+        let (flag, inline_value) = match arg.find('=') {
+            Some(idx) if arg.starts_with("--") => (arg[..idx].to_owned(), Some(&arg[idx + 1..])),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || match inline_value {
+            Some(v) => Ok(v.to_owned()),
+            None => args
+                .next()
+                .ok_or_else(|| CliError::Invalid(format!("missing value for `{}`", flag))),
+        };
+
+        match flag.as_str() {
+            "--scene" => scene_name = value()?,
+            "--samples" => samples = parse_number(&flag, &value()?)?,
+            "--width" => img_w = parse_number(&flag, &value()?)?,
+            "--height" => img_h = Some(parse_number(&flag, &value()?)?),
+            "--seed" => seed = Some(parse_number(&flag, &value()?)?),
+            "--out" => out = Some(value()?.into()),
+            "--region" => region = Some(parse_region(&flag, &value()?)?),
+            "--sampler" => sampler = parse_sampler(&value()?)?,
+            "--jitter" => jitter = parse_jitter(&value()?)?,
+            "--volumetric" => volumetric = Some(parse_number(&flag, &value()?)?),
+            "--exposure" => exposure = parse_number(&flag, &value()?)?,
+            "--white" => white = Some(parse_number(&flag, &value()?)?),
+            "--format" => format = value()?.parse().map_err(CliError::Invalid)?,
+            "--environment" => {
+                if !cfg!(feature = "images") {
+                    return Err(CliError::Invalid(
+                        "`--environment` requires building with the `images` feature".to_owned(),
+                    ));
+                }
+                environment = Some(value()?.into());
+            }
+            _ => return Err(CliError::Invalid(format!("unknown argument `{}`", arg))),
+        }
+    }
+
+    let scene = rtiow::scene::find(&scene_name)
+        .ok_or_else(|| CliError::Invalid(format!("unknown scene `{}`", scene_name)))?;
+
+    if samples == 0 || img_w < 2 || img_h.is_some_and(|h| h < 2) {
+        return Err(CliError::Invalid(
+            "samples must be at least 1 and dimensions at least 2".to_owned(),
+        ));
+    }
+    if sky && transparent {
+        return Err(CliError::Invalid(
+            "`--sky` and `--transparent` can't be used together".to_owned(),
+        ));
+    }
+    if environment.is_some() && (sky || transparent) {
+        return Err(CliError::Invalid(
+            "`--environment` can't be used with `--sky` or `--transparent`".to_owned(),
+        ));
+    }
+    if volumetric.is_some_and(|step: f64| step.is_nan() || step <= 0.0) {
+        return Err(CliError::Invalid(
+            "volumetric step must be positive".to_owned(),
+        ));
+    }
+    if !exposure.is_finite() {
+        return Err(CliError::Invalid("exposure must be finite".to_owned()));
+    }
+    if white.is_some_and(|white: f64| white.is_nan() || white <= 0.0) {
+        return Err(CliError::Invalid("white point must be positive".to_owned()));
+    }
+    let img_h = img_h.unwrap_or_else(|| default_height(img_w, scene.aspect_ratio));
+
+    if region.is_some_and(|(x0, y0, x1, y1)| x0 >= x1 || y0 >= y1 || x1 > img_w || y1 > img_h) {
+        return Err(CliError::Invalid(
+            "region must be non-empty and inside the image".to_owned(),
+        ));
+    }
+
+    let out = out.unwrap_or_else(|| format!("image0.{}", format.extension()).into());
+
+    let seed = match seed {
+        Some(seed) => seed,
+        None => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| CliError::Invalid(e.to_string()))?
+            .as_secs(),
+    };
+
+    Ok(Config {
+        scene,
+        samples,
+        img_w,
+        img_h,
+        seed,
+        out,
+        format,
+        region,
+        sampler,
+        jitter,
+        volumetric,
+        dither,
+        exposure,
+        white,
+        hash,
+        transparent,
+        sky,
+        environment,
+        preview,
+    })
+}
+
+fn parse_number<T: core::str::FromStr>(flag: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::Invalid(format!("invalid value `{}` for `{}`", value, flag)))
+}
+
+fn parse_sampler(value: &str) -> Result<rtiow::sampler::Sampler, CliError> {
+    match value.to_ascii_lowercase().as_str() {
+        "random" => Ok(rtiow::sampler::Sampler::Random),
+        "cmj" => Ok(rtiow::sampler::Sampler::CorrelatedMultiJittered),
+        _ => Err(CliError::Invalid(format!(
+            "unknown sampler `{}`, expected one of: random, cmj",
+            value
+        ))),
+    }
+}
+
+fn parse_jitter(value: &str) -> Result<rtiow::sampler::Jitter, CliError> {
+    match value.to_ascii_lowercase().as_str() {
+        "box" => Ok(rtiow::sampler::Jitter::Box),
+        "tent" => Ok(rtiow::sampler::Jitter::Tent),
+        "gaussian" => Ok(rtiow::sampler::Jitter::Gaussian),
+        _ => Err(CliError::Invalid(format!(
+            "unknown jitter `{}`, expected one of: box, tent, gaussian",
+            value
+        ))),
+    }
+}
+
+fn parse_region(flag: &str, value: &str) -> Result<(u32, u32, u32, u32), CliError> {
+    let coords = value
+        .split(',')
+        .map(|v| parse_number(flag, v.trim()))
+        .collect::<Result<Vec<u32>, _>>()?;
+    match coords[..] {
+        [x0, y0, x1, y1] => Ok((x0, y0, x1, y1)),
+        _ => Err(CliError::Invalid(format!(
+            "expected `x0,y0,x1,y1` for `{}`, got `{}`",
+            flag, value
+        ))),
+    }
+}
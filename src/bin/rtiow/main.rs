@@ -1,67 +1,185 @@
 #![warn(rust_2018_idioms, unsafe_code)]
 
-use rand::SeedableRng;
-
-use rtiow::scene::third::*;
+mod cli;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize values for the image output
-    let aspect_ratio: f64 = 16.0 / 9.0;
-    let img_w: u32 = 384;
+    let config = match cli::parse(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(cli::CliError::Help) => {
+            print!("{}", cli::usage());
+            return Ok(());
+        }
+        Err(cli::CliError::List) => {
+            for scene in rtiow::scene::SCENES {
+                println!("{}", scene.name);
+            }
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::usage());
+            std::process::exit(2);
+        }
+    };
 
-    let samples: u32 = 100;
-    let max_depth = 50;
-    let seed = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)?
-        .as_secs() as u64;
-    let background = rtiow::vec3::Color::new_with(0.0);
+    if let Some(warning) = config.aspect_warning() {
+        eprintln!("warning: {}", warning);
+    }
 
-    // Cli arg parsing. `-- image0.ppm samples width height seed`.
-    let mut args = std::env::args().skip(1);
-    let filename = &args.next().unwrap_or_else(|| "image0.ppm".to_owned());
-    let samples = args
-        .next()
-        .map_or_else(|| samples, |v| v.parse().unwrap_or_else(|_| samples));
-    let img_w = args
-        .next()
-        .map_or_else(|| img_w, |v| v.parse().unwrap_or_else(|_| img_w));
-    let img_h = args.next().map_or_else(
-        || (f64::from(img_w) * aspect_ratio.recip()) as u32,
-        |v| {
-            v.parse()
-                .unwrap_or_else(|_| (f64::from(img_w) * aspect_ratio.recip()) as u32)
+    let mut rng = rtiow::rng::scene_rng(config.seed);
+    #[cfg_attr(not(any(feature = "sky", feature = "images")), allow(unused_mut))]
+    let mut background = if config.transparent {
+        rtiow::render::Background::Transparent
+    } else {
+        rtiow::render::Background::Solid(config.scene.background)
+    };
+    #[cfg(feature = "sky")]
+    if config.sky {
+        background = rtiow::render::Background::Sky(rtiow::sky::Sky::default());
+    }
+    #[cfg(feature = "images")]
+    if let Some(path) = &config.environment {
+        background = rtiow::render::Background::Environment(std::sync::Arc::new(
+            rtiow::environment::EnvironmentMap::new(path)?,
+        ));
+    }
+    let render_config = rtiow::render::RenderConfig {
+        samples: config.samples,
+        background,
+        mode: match config.volumetric {
+            Some(step) => rtiow::render::RenderMode::Volumetric { step },
+            None => rtiow::render::RenderMode::Shaded,
+        },
+        region: config.region,
+        sampler: config.sampler,
+        jitter: config.jitter,
+        seed: config.seed,
+        dither: if config.dither { 1.0 } else { 0.0 },
+        exposure: config.exposure,
+        tone_map: match config.white {
+            Some(white) => rtiow::conversion::ToneMap::Reinhard { white },
+            None => rtiow::conversion::ToneMap::Linear,
         },
-    );
-    let seed = args
-        .next()
-        .map_or_else(|| seed, |v| v.parse().unwrap_or_else(|_| seed));
-    let mut w = std::io::BufWriter::new(std::fs::File::create(&filename)?);
-    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+        progress: Some(rtiow::render::print_scanlines_remaining),
+        ..rtiow::render::RenderConfig::new(config.img_w, config.img_h)
+    };
+
+    // Settings needed to reproduce the render, stored in the output file
+    let mut metadata: Vec<(String, String)> = vec![
+        ("scene".to_owned(), config.scene.name.to_owned()),
+        ("seed".to_owned(), config.seed.to_string()),
+        ("samples".to_owned(), config.samples.to_string()),
+        ("max_depth".to_owned(), render_config.max_depth.to_string()),
+        (
+            "size".to_owned(),
+            format!("{}x{}", config.img_w, config.img_h),
+        ),
+        (
+            "software".to_owned(),
+            format!("rtiow {}", env!("CARGO_PKG_VERSION")),
+        ),
+    ];
+    if config.sampler != rtiow::sampler::Sampler::Random {
+        metadata.push(("sampler".to_owned(), format!("{:?}", config.sampler)));
+    }
+    if config.jitter != rtiow::sampler::Jitter::Box {
+        metadata.push(("jitter".to_owned(), format!("{:?}", config.jitter)));
+    }
+    if config.sky {
+        metadata.push(("background".to_owned(), "sky".to_owned()));
+    }
+    if let Some(path) = &config.environment {
+        metadata.push(("environment".to_owned(), path.display().to_string()));
+    }
+    if let Some(step) = config.volumetric {
+        metadata.push(("volumetric_step".to_owned(), step.to_string()));
+    }
+    if config.exposure != 0.0 {
+        metadata.push(("exposure".to_owned(), config.exposure.to_string()));
+    }
+    if let Some(white) = config.white {
+        metadata.push(("white_point".to_owned(), white.to_string()));
+    }
 
     // Create world and camera
-    let (cam, world, lights) = cornell_box_sphere(&mut rng, img_w, img_h)?;
+    let (cam, world) =
+        rtiow::scene::Scene::build(config.scene, &mut rng, config.img_w, config.img_h)?;
 
     // Raytrace!
-    /* Single thread */
-    // let now = std::time::Instant::now();
-    // rtiow::render::run_single_ppm(
-    //     &mut w, img_w, img_h, samples, max_depth, &mut rng, &world, &cam,
-    // )?;
-    // eprintln!("\nDone in {:.2?}.", std::time::Instant::now() - now);
-
-    /* rayon PPM output */
     let now = std::time::Instant::now();
-    rtiow::render::run_threaded_ppm(
-        &mut w,
-        img_w,
-        img_h,
-        samples,
-        max_depth,
-        &world,
-        lights,
-        &cam,
-        &background,
-    )?;
+    #[cfg(feature = "preview")]
+    #[cfg_attr(not(any(feature = "images", feature = "exr")), allow(unused_variables))]
+    let (colors, coverage) = if config.preview {
+        let preview = rtiow::preview::run_preview(&render_config, &world, &cam)?;
+        (preview.colors, preview.coverage)
+    } else {
+        rtiow::render::render_linear_with_coverage(&render_config, &world, &cam)
+    };
+    #[cfg(not(feature = "preview"))]
+    #[cfg_attr(not(any(feature = "images", feature = "exr")), allow(unused_variables))]
+    let (colors, coverage) =
+        rtiow::render::render_linear_with_coverage(&render_config, &world, &cam);
+
+    #[cfg(feature = "stats")]
+    {
+        let lengths = rtiow::ray::take_path_lengths();
+        let total = lengths.iter().sum::<u64>().max(1) as f64;
+        eprintln!("\nBounces    Paths");
+        for (bounces, &count) in lengths.iter().enumerate() {
+            eprintln!(
+                "{:>7} {:>8} {:>6.2}%",
+                bounces,
+                count,
+                100.0 * count as f64 / total
+            );
+        }
+    }
+
+    if config.hash {
+        let colors = rtiow::render::quantize(&colors, &render_config);
+        println!("{:016x}", rtiow::render::hash_colors(&colors));
+    }
+
+    match config.format {
+        cli::Format::Ppm => {
+            let colors = rtiow::render::quantize(&colors, &render_config);
+            let mut w = std::io::BufWriter::new(std::fs::File::create(&config.out)?);
+            rtiow::render::write_ppm(&mut w, config.img_w, config.img_h, &colors, &metadata)?;
+        }
+        #[cfg(feature = "images")]
+        cli::Format::Png if config.transparent => {
+            let colors = rtiow::render::with_alpha(&colors, &coverage, &render_config);
+            rtiow::render::write_png_rgba(
+                &config.out,
+                config.img_w,
+                config.img_h,
+                &colors,
+                &metadata,
+            )?;
+        }
+        #[cfg(feature = "images")]
+        cli::Format::Png => {
+            let colors = rtiow::render::quantize(&colors, &render_config);
+            rtiow::render::write_png(&config.out, config.img_w, config.img_h, &colors, &metadata)?;
+        }
+        #[cfg(feature = "exr")]
+        cli::Format::Exr => {
+            let aovs = rtiow::render::aovs(&render_config, &world, &cam);
+            let alpha = if config.transparent {
+                Some(&coverage[..])
+            } else {
+                None
+            };
+            rtiow::render::write_exr(
+                &config.out,
+                config.img_w,
+                config.img_h,
+                &colors,
+                alpha,
+                Some(&aovs),
+                &metadata,
+            )?;
+        }
+    }
     eprintln!("\nDone in {:.2?}.", std::time::Instant::now() - now);
 
     Ok(())
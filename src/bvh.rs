@@ -5,8 +5,60 @@ use std::sync::Arc;
 use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable, HittableList};
 
+/// Subtrees with more objects than this are built on separate threads.
+#[cfg(feature = "threads")]
+const PARALLEL_THRESHOLD: usize = 4096;
+
+#[cfg(feature = "stats")]
+thread_local! {
+    static NODE_VISITS: core::cell::Cell<u64> = const { core::cell::Cell::new(0) };
+}
+
+/// Return the number of `BvhNode`s visited by the current thread since the
+/// last call, resetting the counter.
+#[cfg(feature = "stats")]
+pub fn take_node_visits() -> u64 {
+    NODE_VISITS.with(|visits| visits.replace(0))
+}
+
+/// Summary of the shape of a bounding volume hierarchy.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BvhStats {
+    /// Total number of nodes in the tree.
+    pub node_count: usize,
+    /// Number of nodes whose children are primitives rather than nodes.
+    pub leaf_count: usize,
+    /// Depth of the deepest node, where the root has depth 1.
+    pub max_depth: usize,
+    /// Average number of distinct primitives held by a leaf.
+    pub avg_leaf_size: f64,
+}
+
+/// Reasons a bounding volume hierarchy can't be built from a list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BvhError {
+    /// The list has no objects.
+    Empty,
+    /// The object at this index of the list has no bounding box, like an
+    /// infinite plane, so it has to be kept outside the hierarchy.
+    UnboundedChild(usize),
+}
+
+impl core::fmt::Display for BvhError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BvhError::Empty => write!(f, "cannot build a BVH from 0 objects"),
+            BvhError::UnboundedChild(index) => {
+                write!(f, "object {} has no bounding box for the BVH", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BvhError {}
+
 /// Bounding volume hierarchy node.
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct BvhNode {
     /// Child to the left.
     pub left: Option<Arc<dyn Hittable + Send + Sync>>,
@@ -26,44 +78,105 @@ impl BvhNode {
         Self { left, right, bbox }
     }
 
+    /// Build a bounding volume hierarchy from the objects of `list`, or return
+    /// why it can't be built.
+    ///
+    /// The `Arc` pointers are cloned into a working vector, so `list` is left
+    /// untouched and can be reused after the hierarchy is built. Nodes are
+    /// split along the longest axis of their bounds.
+    pub fn try_from_list(list: &HittableList, time0: f64, time1: f64) -> Result<Self, BvhError> {
+        if list.is_empty() {
+            return Err(BvhError::Empty);
+        }
+        let mut bbox = Aabb::default();
+        if let Some(index) = list
+            .iter()
+            .position(|object| !object.bounding_box(time0, time1, &mut bbox))
+        {
+            return Err(BvhError::UnboundedChild(index));
+        }
+
+        let mut objects = list.objects.clone();
+        Ok(Self::from_slice(&mut objects, time0, time1))
+    }
+
+    /// Build a bounding volume hierarchy from the objects of `list`.
+    ///
+    /// Panics if `list` is empty or holds an object without a bounding box,
+    /// see [`BvhNode::try_from_list`].
+    pub fn from_list(list: &HittableList, time0: f64, time1: f64) -> Self {
+        Self::try_from_list(list, time0, time1).unwrap_or_else(|e| panic!("{}", e))
+    }
+
     /// Split the nodes into hierarchies.
+    ///
+    /// This no longer drains or reorders `list`; it delegates to
+    /// [`BvhNode::from_list`], which should be preferred in new code. `rng` is
+    /// no longer used and is kept so existing callers continue to compile.
+    #[allow(clippy::self_named_constructors)]
     pub fn bvh_node<R: rand::Rng>(
-        rng: &mut R,
+        _rng: &mut R,
         list: &mut HittableList,
         time0: f64,
         time1: f64,
     ) -> Self {
+        Self::from_list(list, time0, time1)
+    }
+
+    /// Build the hierarchies for both halves of a split.
+    ///
+    /// The halves are disjoint and the split axis doesn't depend on any random
+    /// state, so large halves can be built in parallel and still produce the
+    /// same tree as a serial build.
+    fn from_halves(
+        a: &mut [Arc<dyn Hittable + Send + Sync>],
+        b: &mut [Arc<dyn Hittable + Send + Sync>],
+        time0: f64,
+        time1: f64,
+    ) -> (Self, Self) {
+        #[cfg(feature = "threads")]
+        {
+            if a.len() + b.len() > PARALLEL_THRESHOLD {
+                return rayon::join(
+                    || Self::from_slice(a, time0, time1),
+                    || Self::from_slice(b, time0, time1),
+                );
+            }
+        }
+
+        (
+            Self::from_slice(a, time0, time1),
+            Self::from_slice(b, time0, time1),
+        )
+    }
+
+    /// Recursively build the hierarchy, sorting `objects` in place.
+    ///
+    /// Every object must have a bounding box.
+    fn from_slice(objects: &mut [Arc<dyn Hittable + Send + Sync>], time0: f64, time1: f64) -> Self {
         let left;
         let right;
-        let axis = match rng.gen_range(0u8, 3) {
-            0 => Axis::X,
-            1 => Axis::Y,
-            _ => Axis::Z,
-        };
+        let axis = Self::span_box(objects, time0, time1).longest_axis();
 
-        let object_span = list.objects.len();
+        let object_span = objects.len();
 
         match object_span {
             0 => panic!("Cannot make a BVH from 0 objects!"),
             1 => {
-                left = Some(list.objects.first().unwrap().clone());
-                right = Some(list.objects.first().unwrap().clone());
+                left = Some(objects[0].clone());
+                right = Some(objects[0].clone());
             }
             2 => {
-                if Self::box_compare(
-                    list.objects.get(0).unwrap(),
-                    list.objects.get(1).unwrap(),
-                    axis,
-                ) {
-                    left = Some(list.objects.get(0).unwrap().clone());
-                    right = Some(list.objects.get(1).unwrap().clone());
+                if Self::box_compare(&objects[0], &objects[1], axis) {
+                    left = Some(objects[0].clone());
+                    right = Some(objects[1].clone());
                 } else {
-                    left = Some(list.objects.get(1).unwrap().clone());
-                    right = Some(list.objects.get(0).unwrap().clone());
+                    left = Some(objects[1].clone());
+                    right = Some(objects[0].clone());
                 }
             }
             _ => {
-                list.objects.sort_unstable_by(|a, b| {
+                objects.sort_unstable_by(|a, b| {
                     if Self::box_compare(a, b, axis) {
                         core::cmp::Ordering::Greater
                     } else {
@@ -71,43 +184,82 @@ impl BvhNode {
                     }
                 });
                 let mid = object_span / 2;
-                left = Some(Arc::new(Self::bvh_node(
-                    rng,
-                    &mut HittableList {
-                        objects: list.objects.drain(mid..).collect(),
-                    },
-                    time0,
-                    time1,
-                )));
-                right = Some(Arc::new(Self::bvh_node(rng, list, time0, time1)));
+                let (lower, upper) = objects.split_at_mut(mid);
+                let (upper_node, lower_node) = Self::from_halves(upper, lower, time0, time1);
+                left = Some(Arc::new(upper_node));
+                right = Some(Arc::new(lower_node));
             }
         }
 
         let mut box_left = Aabb::default();
         let mut box_right = Aabb::default();
-
-        let left_node = match left.clone() {
-            Some(node) => node.bounding_box(time0, time1, &mut box_left),
-            None => false,
-        };
-        let right_node = match right.clone() {
-            Some(node) => node.bounding_box(time0, time1, &mut box_right),
-            None => false,
-        };
-
-        if !left_node || !right_node {
-            eprintln!("No bounding box in bvh_node constructor.\n");
-            return Self {
-                left: None,
-                right: None,
-                bbox: Aabb::default(),
-            };
+        if let Some(node) = &left {
+            node.bounding_box(time0, time1, &mut box_left);
+        }
+        if let Some(node) = &right {
+            node.bounding_box(time0, time1, &mut box_right);
         }
 
         let bbox = Aabb::surrounding_box(&box_left, &box_right);
         Self { left, right, bbox }
     }
 
+    /// Compute the box surrounding every object that has a bounding box.
+    fn span_box(objects: &[Arc<dyn Hittable + Send + Sync>], time0: f64, time1: f64) -> Aabb {
+        let mut span: Option<Aabb> = None;
+        let mut temp_box = Aabb::default();
+
+        for object in objects.iter() {
+            if object.bounding_box(time0, time1, &mut temp_box) {
+                span = Some(match span {
+                    Some(span) => Aabb::surrounding_box(&span, &temp_box),
+                    None => temp_box,
+                });
+            }
+        }
+
+        span.unwrap_or_default()
+    }
+
+    /// Walk the tree and collect statistics about its balance.
+    ///
+    /// This visits every node, so it's meant for tuning rather than for use
+    /// while rendering.
+    pub fn stats(&self) -> BvhStats {
+        let mut stats = BvhStats::default();
+        let mut primitives = 0;
+        self.walk(1, &mut stats, &mut primitives);
+
+        if stats.leaf_count > 0 {
+            stats.avg_leaf_size = primitives as f64 / stats.leaf_count as f64;
+        }
+        stats
+    }
+
+    fn walk(&self, depth: usize, stats: &mut BvhStats, primitives: &mut usize) {
+        stats.node_count += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+
+        let mut leaf_size = 0;
+        for child in [&self.left, &self.right].iter().filter_map(|c| c.as_ref()) {
+            match child.as_bvh_node() {
+                Some(node) => node.walk(depth + 1, stats, primitives),
+                None => leaf_size += 1,
+            }
+        }
+
+        if leaf_size > 0 {
+            // A single object is stored as both children, count it once
+            if let (Some(left), Some(right)) = (&self.left, &self.right) {
+                if leaf_size == 2 && Arc::ptr_eq(left, right) {
+                    leaf_size = 1;
+                }
+            }
+            stats.leaf_count += 1;
+            *primitives += leaf_size;
+        }
+    }
+
     /// Comparator for node bounding boxes.
     pub fn box_compare(
         a: &Arc<dyn Hittable + Send + Sync>,
@@ -128,10 +280,73 @@ impl BvhNode {
             Axis::Z => box_a.min().z() < box_b.min().z(),
         }
     }
+
+    /// Where `r` enters `child`, or `None` if it misses, and the child as a
+    /// node if it is one. Primitives are taken to be entered at `t_min`.
+    fn entry<'a>(
+        child: Option<&'a (dyn Hittable + Send + Sync)>,
+        r: &crate::ray::Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<(f64, &'a (dyn Hittable + Send + Sync), Option<&'a BvhNode>)> {
+        let child = child?;
+        match child.as_bvh_node() {
+            Some(node) => {
+                #[cfg(feature = "stats")]
+                NODE_VISITS.with(|visits| visits.set(visits.get() + 1));
+
+                let (t, _) = node.bbox.hit_t(r, t_min, t_max)?;
+                Some((t, child, Some(node)))
+            }
+            None => Some((t_min, child, None)),
+        }
+    }
+
+    /// Find the closest hit among the children of a node whose own box the
+    /// ray is known to enter.
+    ///
+    /// The boxes of children which are nodes are tested here, so the child
+    /// the ray enters first is searched first and the other is skipped if it
+    /// starts beyond the hit found. Primitives are hit in their stored order.
+    fn hit_children(
+        &self,
+        r: &crate::ray::Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let mut children = [
+            Self::entry(self.left.as_deref(), r, t_min, t_max),
+            Self::entry(self.right.as_deref(), r, t_min, t_max),
+        ];
+        if let [Some((left, ..)), Some((right, ..))] = children {
+            if right < left {
+                children.swap(0, 1);
+            }
+        }
+
+        let mut closest = t_max;
+        let mut hit_anything = false;
+        for &(t_enter, child, node) in children.iter().flatten() {
+            if t_enter > closest {
+                continue;
+            }
+            let hit = match node {
+                Some(node) => node.hit_children(r, t_min, closest, rec),
+                None => child.hit(r, t_min, closest, rec),
+            };
+            if hit {
+                hit_anything = true;
+                closest = rec.t;
+            }
+        }
+
+        hit_anything
+    }
 }
 
 /// Cartesian axes.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Axis {
     /// X-axis.
     X,
@@ -148,29 +363,115 @@ impl Hittable for BvhNode {
     }
 
     fn hit(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        #[cfg(feature = "stats")]
+        NODE_VISITS.with(|visits| visits.set(visits.get() + 1));
+
+        if !self.bbox.hit(r, t_min, t_max) {
+            return false;
+        }
+
+        self.hit_children(r, t_min, t_max, rec)
+    }
+
+    fn occluded(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64) -> bool {
+        #[cfg(feature = "stats")]
+        NODE_VISITS.with(|visits| visits.set(visits.get() + 1));
+
         if !self.bbox.hit(r, t_min, t_max) {
             return false;
         }
 
-        let hit_left = match &self.left {
-            Some(node) => node.hit(r, t_min, t_max, rec),
-            None => false,
-        };
-        let hit_right = match &self.right {
-            Some(node) => node.hit(r, t_min, t_max, rec),
-            None => false,
-        };
+        // Any hit will do, so there's no need to search the nearer child first
+        [&self.left, &self.right]
+            .iter()
+            .filter_map(|child| child.as_ref())
+            .any(|child| child.occluded(r, t_min, t_max))
+    }
 
-        hit_left || hit_right
+    fn as_bvh_node(&self) -> Option<&BvhNode> {
+        Some(self)
     }
 }
 
-impl core::default::Default for BvhNode {
-    fn default() -> Self {
-        Self {
-            left: None,
-            right: None,
-            bbox: Aabb::default(),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Sphere;
+    use crate::material::Material;
+    use crate::vec3::Point3;
+
+    #[test]
+    fn hit_keeps_the_nearer_left_child() {
+        // Split along z, so the sphere at -10 is the left child and is nearer
+        // to a ray heading +z from behind it
+        let mut list = HittableList::default();
+        list.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -10.0),
+            1.0,
+            Material::default(),
+        )));
+        list.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Material::default(),
+        )));
+        let bvh = BvhNode::try_from_list(&list, 0.0, 1.0).unwrap();
+
+        let r = crate::ray::Ray::new(
+            Point3::new(0.0, 0.0, -15.0),
+            crate::vec3::Vec3::new(0.0, 0.0, 1.0),
+            0.0,
+        );
+        let mut rec = HitRecord::default();
+        assert!(bvh.hit(&r, 0.001, f64::INFINITY, &mut rec));
+        assert!((rec.t - 4.0).abs() < 1e-9, "hit at t = {}", rec.t);
+    }
+
+    #[test]
+    fn hits_match_searching_every_object() {
+        // Overlapping spheres, so boxes are often entered before a hit found
+        // in the other child and each child has to be searched up to it
+        let mut rng = crate::rng::pixel_rng(1, 0);
+        let mut list = HittableList::default();
+        for _ in 0..64 {
+            list.add(Arc::new(Sphere::new(
+                crate::vec3::Vec3::random_range(&mut rng, -5.0, 5.0),
+                rand::Rng::gen_range(&mut rng, 0.2, 2.0),
+                Material::default(),
+            )));
         }
+        let bvh = BvhNode::try_from_list(&list, 0.0, 1.0).unwrap();
+
+        for _ in 0..1000 {
+            let origin = 10.0 * crate::vec3::Vec3::random_unit_vector(&mut rng);
+            let target = crate::vec3::Vec3::random_range(&mut rng, -3.0, 3.0);
+            let r = crate::ray::Ray::new(origin, target - origin, 0.0);
+            let (mut expected, mut got) = (HitRecord::default(), HitRecord::default());
+            let hit = list.hit(&r, 0.001, f64::INFINITY, &mut expected);
+            assert_eq!(bvh.hit(&r, 0.001, f64::INFINITY, &mut got), hit);
+            if hit {
+                assert_eq!(got.t, expected.t);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_lists_are_rejected() {
+        let result = BvhNode::try_from_list(&HittableList::default(), 0.0, 1.0);
+        assert_eq!(result.err(), Some(BvhError::Empty));
+    }
+
+    #[test]
+    fn unbounded_children_are_rejected_by_index() {
+        // An empty list has no bounding box
+        let mut list = HittableList::default();
+        list.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Material::default(),
+        )));
+        list.add(Arc::new(HittableList::default()));
+        let result = BvhNode::try_from_list(&list, 0.0, 1.0);
+        assert_eq!(result.err(), Some(BvhError::UnboundedChild(1)));
     }
 }
@@ -1,10 +1,76 @@
 //! Module for managing the virtual camera in a scene.
 
+use std::sync::Arc;
+
 use crate::ray::Ray;
+use crate::texture::Texture;
 use crate::vec3::{Point3, Vec3};
 
+/// Number of points tried on a masked lens before falling back to its center.
+const MAX_LENS_TRIES: u32 = 64;
+
+/// Number of times tried sampling a Gaussian shutter before falling back to the
+/// middle of the interval.
+const MAX_SHUTTER_TRIES: u32 = 64;
+
+/// How the shutter's exposure is spread over the interval `time0..time1`,
+/// which shapes motion blur streaks.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum ShutterCurve {
+    /// Equal exposure for the whole interval.
+    #[default]
+    Uniform,
+    /// Exposure rising linearly to a peak and falling linearly after it.
+    Triangle {
+        /// Fraction of the interval where exposure peaks. Values near `0.0`
+        /// model a shutter which opens fast and closes slowly.
+        peak: f64,
+    },
+    /// Exposure following a normal distribution around the middle of the
+    /// interval, truncated to the interval. A `sigma` which isn't finite, or
+    /// is so wide that samples keep missing the interval, gives the middle.
+    Gaussian {
+        /// Standard deviation as a fraction of the interval.
+        sigma: f64,
+    },
+}
+
+impl ShutterCurve {
+    /// Map a uniform sample to a fraction of the shutter interval.
+    fn sample<R: rand::Rng>(self, rng: &mut R) -> f64 {
+        match self {
+            ShutterCurve::Uniform => rng.gen::<f64>(),
+            ShutterCurve::Triangle { peak } => {
+                // Inverse of the triangular distribution's CDF
+                let peak = peak.clamp(0.0, 1.0);
+                let x = rng.gen::<f64>();
+                if x < peak {
+                    (x * peak).sqrt()
+                } else {
+                    1.0 - ((1.0 - x) * (1.0 - peak)).sqrt()
+                }
+            }
+            ShutterCurve::Gaussian { sigma } => {
+                // The normal CDF has no closed form inverse, so use Box-Muller
+                // and reject samples outside the interval. A `sigma` which
+                // isn't finite never lands inside, so give up eventually
+                for _ in 0..MAX_SHUTTER_TRIES {
+                    let r1 = 1.0 - rng.gen::<f64>();
+                    let r2 = rng.gen::<f64>();
+                    let z = (-2.0 * r1.ln()).sqrt() * (crate::conversion::TWO_PI * r2).cos();
+                    let x = 0.5 + sigma * z;
+                    if (0.0..=1.0).contains(&x) {
+                        return x;
+                    }
+                }
+                0.5
+            }
+        }
+    }
+}
+
 /// Adjustable scene camera.
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct Camera {
     orig: Point3,
     lower_left_corner: Point3,
@@ -12,14 +78,18 @@ pub struct Camera {
     vertical: Vec3,
     u: Vec3,
     v: Vec3,
+    #[allow(dead_code)]
     w: Vec3,
     lens_radius: f64,
     time0: f64,
     time1: f64,
+    shutter: ShutterCurve,
+    aperture_mask: Option<Arc<dyn Texture + Send + Sync>>,
 }
 
 impl Camera {
     /// Create new instance of adjustable camera.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lookfrom: Point3,
         lookat: Point3,
@@ -52,6 +122,101 @@ impl Camera {
             lens_radius: aperture * 0.5,
             time0,
             time1,
+            shutter: ShutterCurve::default(),
+            aperture_mask: None,
+        }
+    }
+
+    /// Ratio of the width of the view to its height.
+    pub fn aspect_ratio(&self) -> f64 {
+        self.horizontal.length() / self.vertical.length()
+    }
+
+    /// Create a camera from the settings of a real one.
+    ///
+    /// The horizontal field of view is the angle the `sensor_width_mm` wide
+    /// sensor covers behind a lens of `focal_length_mm`, and the lens opening
+    /// is `focal_length_mm / f_stop` across. Lengths in the scene are taken to
+    /// be millimeters for the depth of field to match the real camera.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_photographic(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        focal_length_mm: f64,
+        sensor_width_mm: f64,
+        aspect_ratio: f64,
+        f_stop: f64,
+        focus_dist: f64,
+        time0: f64,
+        time1: f64,
+    ) -> Self {
+        // The sensor's height follows from the image's aspect ratio
+        let vfov = field_of_view(focal_length_mm, sensor_width_mm / aspect_ratio);
+
+        Camera::new(
+            lookfrom,
+            lookat,
+            vup,
+            vfov,
+            aspect_ratio,
+            focal_length_mm / f_stop,
+            focus_dist,
+            time0,
+            time1,
+        )
+    }
+
+    /// Create a camera looking along `direction` which fits all of `bounds` in
+    /// view, focused on its center.
+    ///
+    /// The box is framed by its bounding sphere, so it fits whichever way the
+    /// camera faces but may leave a margin around it.
+    pub fn frame_bounds(
+        bounds: &crate::aabb::Aabb,
+        direction: Vec3,
+        vup: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+    ) -> Self {
+        let lookat = bounds.centroid();
+        let radius = 0.5 * bounds.diagonal().length();
+
+        // The narrower of the two fields of view decides the distance
+        let half_height = (vfov.to_radians() * 0.5).tan();
+        let half_fov = half_height.min(aspect_ratio * half_height).atan();
+        let distance = radius / half_fov.sin();
+
+        let lookfrom = lookat - distance * direction.unit_vector();
+        Camera::new(
+            lookfrom,
+            lookat,
+            vup,
+            vfov,
+            aspect_ratio,
+            0.0,
+            distance,
+            0.0,
+            1.0,
+        )
+    }
+
+    /// Return the camera with a different shutter curve.
+    pub fn with_shutter(self, shutter: ShutterCurve) -> Self {
+        Self { shutter, ..self }
+    }
+
+    /// Return the camera with its lens shaped by `mask`, giving out of focus
+    /// highlights its shape.
+    ///
+    /// The mask covers the square around the lens, with `u` and `v` running
+    /// from `0.0` to `1.0` across it. The mean of its channels at a point is
+    /// the chance that a lens sample there is kept, so grays soften the edges
+    /// of the bokeh.
+    pub fn with_aperture_mask(self, mask: Arc<dyn Texture + Send + Sync>) -> Self {
+        Self {
+            aperture_mask: Some(mask),
+            ..self
         }
     }
 
@@ -70,19 +235,124 @@ impl Camera {
         )
     }
 
+    /// Angle in radians covered by one pixel of an image `img_h` pixels high.
+    pub fn pixel_spread(&self, img_h: u32) -> f64 {
+        let focus_dist = (self.lower_left_corner + 0.5 * self.horizontal + 0.5 * self.vertical
+            - self.orig)
+            .length();
+        self.vertical.length() / (focus_dist * f64::from(img_h.max(2) - 1))
+    }
+
     /// Create a ray from the camera.
     pub fn get_ray<R: rand::Rng>(&self, rng: &mut R, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_circle(rng);
+        let first = Vec3::random_in_unit_disk(rng);
+        let rd = self.lens_radius * self.sample_lens(rng, first);
+        self.ray_from_lens(rng, s, t, rd)
+    }
+
+    /// Jittered primary rays for `samples` uniform random samples of pixel
+    /// `(i, j)` in an image `img_w` by `img_h` pixels, where `j` counts up from
+    /// the bottom row.
+    ///
+    /// This is the sampling of [`Sampler::Random`](crate::sampler::Sampler)
+    /// with a box filter, for driving the camera outside the render loop.
+    pub fn pixel_rays<'a, R: rand::Rng>(
+        &'a self,
+        rng: &'a mut R,
+        i: u32,
+        j: u32,
+        img_w: u32,
+        img_h: u32,
+        samples: u32,
+    ) -> impl Iterator<Item = Ray> + 'a {
+        let (scale_u, scale_v) = (f64::from(img_w - 1).recip(), f64::from(img_h - 1).recip());
+        (0..samples).map(move |_| {
+            let u = (f64::from(i) + rng.gen::<f64>()) * scale_u;
+            let v = (f64::from(j) + rng.gen::<f64>()) * scale_v;
+            self.get_ray(rng, u, v)
+        })
+    }
+
+    /// Create a ray from the camera through a chosen point on the lens.
+    ///
+    /// `lens` is a point in the unit square which is mapped onto the lens, so
+    /// stratified points in the square stay stratified on the lens.
+    pub fn get_ray_through_lens<R: rand::Rng>(
+        &self,
+        rng: &mut R,
+        s: f64,
+        t: f64,
+        lens: (f64, f64),
+    ) -> Ray {
+        let rd = self.lens_radius * self.sample_lens(rng, square_to_disk(lens.0, lens.1));
+        self.ray_from_lens(rng, s, t, rd)
+    }
+
+    /// Point on the unit disk to send a ray through, `first` unless the
+    /// aperture mask rejects it.
+    ///
+    /// Rejected points are replaced by random ones until the mask accepts one,
+    /// giving up at the center of the lens for masks which are nearly black.
+    fn sample_lens<R: rand::Rng>(&self, rng: &mut R, first: Vec3) -> Vec3 {
+        let mask = match &self.aperture_mask {
+            Some(mask) => mask,
+            None => return first,
+        };
+
+        let mut point = first;
+        for _ in 0..MAX_LENS_TRIES {
+            let u = 0.5 * (point.x() + 1.0);
+            let v = 0.5 * (point.y() + 1.0);
+            let m = mask.value(u, v, &point);
+            if rng.gen::<f64>() < (m.x() + m.y() + m.z()) / 3.0 {
+                return point;
+            }
+            point = Vec3::random_in_unit_disk(rng);
+        }
+        Vec3::new_with(0.0)
+    }
+
+    fn ray_from_lens<R: rand::Rng>(&self, rng: &mut R, s: f64, t: f64, rd: Vec3) -> Ray {
         let offset = self.u * rd.x() + self.v * rd.y();
 
+        let time = match self.shutter {
+            ShutterCurve::Uniform => rng.gen_range(self.time0, self.time1),
+            curve => self.time0 + curve.sample(rng) * (self.time1 - self.time0),
+        };
+
         Ray::new(
             self.orig + offset,
             self.lower_left_corner + s * self.horizontal + t * self.vertical - self.orig - offset,
-            rng.gen_range(self.time0, self.time1),
+            time,
         )
     }
 }
 
+/// Angle in degrees covered by a sensor `sensor_mm` across behind a lens of
+/// `focal_length_mm`.
+pub fn field_of_view(focal_length_mm: f64, sensor_mm: f64) -> f64 {
+    2.0 * (sensor_mm / (2.0 * focal_length_mm)).atan().to_degrees()
+}
+
+/// Map a point in the unit square onto the unit disk with Shirley and Chiu's
+/// concentric mapping, which keeps neighboring points close.
+fn square_to_disk(a: f64, b: f64) -> Vec3 {
+    let (a, b) = (2.0 * a - 1.0, 2.0 * b - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec3::new_with(0.0);
+    }
+
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, core::f64::consts::FRAC_PI_4 * (b / a))
+    } else {
+        (
+            b,
+            core::f64::consts::FRAC_PI_2 - core::f64::consts::FRAC_PI_4 * (a / b),
+        )
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
 impl core::default::Default for Camera {
     fn default() -> Self {
         Camera::new(
@@ -98,3 +368,92 @@ impl core::default::Default for Camera {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_rays_stay_within_their_pixel() {
+        // Looking down -Z with a viewport 4 wide and 2 tall at Z = -1
+        let cam = Camera::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            90.0,
+            2.0,
+            0.0,
+            1.0,
+            0.0,
+            1.0,
+        );
+        let (img_w, img_h) = (5, 4);
+        let (du, dv) = (4.0 / f64::from(img_w - 1), 2.0 / f64::from(img_h - 1));
+        let mut rng = crate::rng::pixel_rng(1, 0);
+        for j in 0..img_h {
+            for i in 0..img_w {
+                let rays: Vec<_> = cam.pixel_rays(&mut rng, i, j, img_w, img_h, 8).collect();
+                assert_eq!(rays.len(), 8);
+                for r in rays {
+                    // Where the ray crosses the viewport
+                    let d = r.direction() / -r.direction().z();
+                    let (x, y) = (d.x() + 2.0, d.y() + 1.0);
+                    let eps = 1e-9;
+                    assert!(
+                        x >= f64::from(i) * du - eps && x <= f64::from(i + 1) * du + eps,
+                        "pixel ({}, {}) ray crosses x = {}",
+                        i,
+                        j,
+                        x
+                    );
+                    assert!(
+                        y >= f64::from(j) * dv - eps && y <= f64::from(j + 1) * dv + eps,
+                        "pixel ({}, {}) ray crosses y = {}",
+                        i,
+                        j,
+                        y
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fifty_millimeters_on_full_frame_spans_forty_degrees() {
+        assert!((field_of_view(50.0, 36.0) - 39.6).abs() < 0.05);
+
+        // Across the width of a 3:2 frame
+        let focus_dist = 2000.0;
+        let cam = Camera::new_photographic(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            50.0,
+            36.0,
+            1.5,
+            2.8,
+            focus_dist,
+            0.0,
+            1.0,
+        );
+        let hfov = 2.0
+            * (0.5 * cam.horizontal.length() / focus_dist)
+                .atan()
+                .to_degrees();
+        assert!((hfov - 39.6).abs() < 0.05, "{}", hfov);
+        assert!((cam.lens_radius - 0.5 * 50.0 / 2.8).abs() < 1e-12);
+    }
+
+    #[test]
+    fn gaussian_shutter_gives_up_on_bad_sigmas() {
+        let rng = &mut crate::rng::pixel_rng(1, 0);
+        for &sigma in &[f64::NAN, f64::INFINITY, -f64::INFINITY] {
+            let x = ShutterCurve::Gaussian { sigma }.sample(rng);
+            assert_eq!(x, 0.5, "sigma {}", sigma);
+        }
+        for _ in 0..1000 {
+            let x = ShutterCurve::Gaussian { sigma: 0.2 }.sample(rng);
+            assert!((0.0..=1.0).contains(&x));
+        }
+    }
+}
@@ -10,6 +10,42 @@ pub const TWO_PI: f64 = 2.0 * core::f64::consts::PI;
 const C23: u32 = 0x4b00_0000;
 const C52: u64 = 0x4330_0000_0000_0000;
 
+/// Thresholds of a 4x4 Bayer matrix for ordered dithering.
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Curve compressing linear colors towards the displayable range before gamma
+/// correction.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ToneMap {
+    /// Leave colors as they are, so quantizing clips anything above `1.0`.
+    #[default]
+    Linear,
+    /// Reinhard's curve on luminance, which keeps the hue and maps a luminance
+    /// of `white` to `1.0`. Brighter areas still clip. `f64::INFINITY` gives
+    /// the basic curve, which never quite reaches `1.0`.
+    Reinhard {
+        /// Smallest luminance shown as full white.
+        white: f64,
+    },
+}
+
+impl ToneMap {
+    /// Apply the curve to a linear color.
+    pub fn apply(self, color: crate::vec3::Color) -> crate::vec3::Color {
+        match self {
+            ToneMap::Linear => color,
+            ToneMap::Reinhard { white } => {
+                let luminance = 0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z();
+                if luminance <= 0.0 {
+                    return color;
+                }
+                let mapped = luminance * (1.0 + luminance / (white * white)) / (1.0 + luminance);
+                color * (mapped / luminance)
+            }
+        }
+    }
+}
+
 /// Trait for fast conversion from float to u8.
 pub trait IntoU8 {
     /// Convert and clamp float input to u8.
@@ -37,14 +73,61 @@ impl IntoU8 for f64 {
 }
 
 impl crate::vec3::Color {
+    /// Scale a linear color by `2^stops`, so each stop doubles or halves it.
+    pub fn exposed(self, stops: f64) -> Self {
+        self * 2.0_f64.powf(stops)
+    }
+
+    /// Convert a float RGB color into u8 with gamma correction, after
+    /// averaging its `samples`, exposing it by `exposure` stops and applying
+    /// `tone_map`. Dithering is the same as `into_u8_color_dithered`.
+    pub fn into_u8_color_tonemapped(
+        self,
+        samples: f64,
+        exposure: f64,
+        tone_map: ToneMap,
+        x: u32,
+        y: u32,
+        dither: f64,
+    ) -> crate::vec3::ColorU8 {
+        tone_map
+            .apply((self * samples.recip()).exposed(exposure))
+            .into_u8_color_dithered(1.0, x, y, dither)
+    }
+
     /// Convert a float RGB color into u8 with gamma correction.
     pub fn into_u8_color(self, samples: f64) -> crate::vec3::ColorU8 {
         let scale = samples.recip();
 
         crate::vec3::ColorU8(
-            crate::conversion::IntoU8::into_u8((self.0 * scale).sqrt()),
-            crate::conversion::IntoU8::into_u8((self.1 * scale).sqrt()),
-            crate::conversion::IntoU8::into_u8((self.2 * scale).sqrt()),
+            crate::conversion::IntoU8::into_u8((self.x() * scale).sqrt()),
+            crate::conversion::IntoU8::into_u8((self.y() * scale).sqrt()),
+            crate::conversion::IntoU8::into_u8((self.z() * scale).sqrt()),
+        )
+    }
+
+    /// Convert a float RGB color into u8 with gamma correction and ordered
+    /// dithering for pixel `(x, y)`.
+    ///
+    /// A Bayer matrix offset is added before rounding, so the pattern is stable
+    /// for each pixel. `strength` is the size of the offset in 8-bit steps,
+    /// where `1.0` spreads a value evenly between its two nearest levels and
+    /// `0.0` matches `into_u8_color`.
+    pub fn into_u8_color_dithered(
+        self,
+        samples: f64,
+        x: u32,
+        y: u32,
+        strength: f64,
+    ) -> crate::vec3::ColorU8 {
+        let scale = samples.recip();
+        let threshold = (f64::from(BAYER_4X4[y as usize % 4][x as usize % 4]) + 0.5) / 16.0;
+        let offset = (threshold - 0.5) * strength / f64::from(u8::MAX);
+
+        crate::vec3::ColorU8(
+            crate::conversion::IntoU8::into_u8((self.x() * scale).sqrt() + offset),
+            crate::conversion::IntoU8::into_u8((self.y() * scale).sqrt() + offset),
+            crate::conversion::IntoU8::into_u8((self.z() * scale).sqrt() + offset),
         )
     }
 }
@@ -59,8 +142,51 @@ impl IntoF64 for u8 {
     fn into_f64(self) -> f64 {
         let comp_u = self as u64 + C52;
         let comp_f = f64::from_bits(comp_u) - f64::from_bits(C52);
-        let max_u = core::u8::MAX as u64 + C52;
+        let max_u = u8::MAX as u64 + C52;
         let max_f = (f64::from_bits(max_u) - f64::from_bits(C52)).recip();
         comp_f * max_f
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Color;
+
+    #[test]
+    fn one_stop_doubles_linear_radiance() {
+        let color = Color::new(0.1, 0.25, 0.4);
+        let components = |c: Color| [c.x(), c.y(), c.z()];
+        assert_eq!(components(color.exposed(1.0)), components(color * 2.0));
+        assert_eq!(components(color.exposed(-1.0)), components(color * 0.5));
+
+        // Exposure comes before the tone curve
+        let tone_map = ToneMap::Reinhard { white: 4.0 };
+        let exposed = color.into_u8_color_tonemapped(1.0, 1.0, tone_map, 0, 0, 0.0);
+        let doubled = tone_map.apply(color * 2.0).into_u8_color(1.0);
+        assert_eq!(
+            (exposed.0, exposed.1, exposed.2),
+            (doubled.0, doubled.1, doubled.2)
+        );
+    }
+
+    #[test]
+    fn dithering_keeps_the_mean_and_only_moves_the_last_bit() {
+        for &level in &[100.1, 100.3, 100.5, 100.7, 100.9] {
+            let gamma: f64 = level / 255.0;
+            let color = Color::new_with(gamma * gamma);
+            let plain = color.into_u8_color(1.0).0;
+
+            let mut sum = 0.0;
+            for y in 0..4 {
+                for x in 0..4 {
+                    let dithered = color.into_u8_color_dithered(1.0, x, y, 1.0).0;
+                    assert!((i32::from(dithered) - i32::from(plain)).abs() <= 1);
+                    sum += f64::from(dithered);
+                }
+            }
+            // Each tile of the matrix averages out to the unrounded level
+            assert!((sum / 16.0 - level).abs() <= 1.0 / 16.0, "{}", level);
+        }
+    }
+}
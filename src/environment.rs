@@ -0,0 +1,193 @@
+//! Image based background seen by rays that miss everything.
+
+use crate::conversion::PI;
+use crate::vec3::{Color, Vec3};
+
+/// One level of an [`EnvironmentMap`]'s mip pyramid.
+#[derive(Clone)]
+struct MipLevel {
+    width: usize,
+    height: usize,
+    texels: Vec<Color>,
+}
+
+impl MipLevel {
+    /// Average the level down to `width` by `height` texels, weighting each
+    /// source texel by how much of it a new texel covers.
+    fn downsample(&self, width: usize, height: usize) -> Self {
+        let columns = box_weights(self.width, width);
+        let rows = box_weights(self.height, height);
+
+        let mut narrow = Vec::with_capacity(width * self.height);
+        for y in 0..self.height {
+            let row = &self.texels[y * self.width..(y + 1) * self.width];
+            for weights in &columns {
+                narrow.push(
+                    weights
+                        .iter()
+                        .fold(Color::new_with(0.0), |sum, &(x, w)| sum + w * row[x]),
+                );
+            }
+        }
+
+        let mut texels = Vec::with_capacity(width * height);
+        for weights in &rows {
+            for x in 0..width {
+                texels.push(weights.iter().fold(Color::new_with(0.0), |sum, &(y, w)| {
+                    sum + w * narrow[y * width + x]
+                }));
+            }
+        }
+
+        Self {
+            width,
+            height,
+            texels,
+        }
+    }
+
+    /// Bilinearly filtered color at texture coordinates `(u, v)`, wrapping
+    /// around in `u`.
+    fn sample(&self, u: f64, v: f64) -> Color {
+        let x = u * self.width as f64 - 0.5;
+        let y = (1.0 - v) * self.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let column = |x: f64| (x as i64).rem_euclid(self.width as i64) as usize;
+        let row = |y: f64| (y.max(0.0) as usize).min(self.height - 1);
+        let texel = |x: f64, y: f64| self.texels[row(y) * self.width + column(x)];
+
+        (1.0 - fy) * ((1.0 - fx) * texel(x0, y0) + fx * texel(x0 + 1.0, y0))
+            + fy * ((1.0 - fx) * texel(x0, y0 + 1.0) + fx * texel(x0 + 1.0, y0 + 1.0))
+    }
+}
+
+/// Source texels, and their weights, averaged into each of `dst` texels
+/// covering the same span as `src`.
+fn box_weights(src: usize, dst: usize) -> Vec<Vec<(usize, f64)>> {
+    let ratio = src as f64 / dst as f64;
+    (0..dst)
+        .map(|d| {
+            let (start, end) = (d as f64 * ratio, (d + 1) as f64 * ratio);
+            (start.floor() as usize..(end.ceil() as usize).min(src))
+                .map(|s| {
+                    let overlap = end.min(s as f64 + 1.0) - start.max(s as f64);
+                    (s, overlap / ratio)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Equirectangular image surrounding the scene, mapped onto directions like
+/// [`get_sphere_uv`](crate::hittable::get_sphere_uv) maps a texture onto a
+/// sphere.
+///
+/// A mip pyramid, halving the image at each level, is built up front so that
+/// lookups can be filtered to how widely a ray has spread along its path. A
+/// mirror sees the full resolution image, while rough reflections see a
+/// blurred level instead of point samples of a detailed image, which would
+/// otherwise take many samples to average out.
+#[derive(Clone)]
+pub struct EnvironmentMap {
+    /// Mip pyramid from the full image down to a single texel.
+    levels: Vec<MipLevel>,
+}
+
+impl EnvironmentMap {
+    /// Create an environment map from linear colors, a row at a time starting
+    /// from the top.
+    ///
+    /// Fails if there are no texels, or their number doesn't match `width` by
+    /// `height`.
+    pub fn from_colors(
+        width: u32,
+        height: u32,
+        texels: Vec<Color>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let (width, height) = (width as usize, height as usize);
+        if width == 0 || height == 0 || texels.len() != width * height {
+            return Err(format!(
+                "{} texels don't make a {}x{} environment map",
+                texels.len(),
+                width,
+                height
+            )
+            .into());
+        }
+
+        let mut levels = vec![MipLevel {
+            width,
+            height,
+            texels,
+        }];
+        loop {
+            let last = &levels[levels.len() - 1];
+            if last.width == 1 && last.height == 1 {
+                break;
+            }
+            let next = last.downsample((last.width / 2).max(1), (last.height / 2).max(1));
+            levels.push(next);
+        }
+
+        Ok(Self { levels })
+    }
+
+    #[cfg(feature = "images")]
+    /// Load an environment map from an equirectangular image file, detecting
+    /// its format from its contents.
+    pub fn new<P: AsRef<std::path::Path>>(filename: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let img = image::load_from_memory(&std::fs::read(filename)?)?.to_rgb8();
+        let (width, height) = img.dimensions();
+        let texels = img
+            .pixels()
+            .map(|p| {
+                use crate::conversion::IntoF64;
+                Color::new(p[0].into_f64(), p[1].into_f64(), p[2].into_f64())
+            })
+            .collect();
+
+        Self::from_colors(width, height, texels)
+    }
+
+    /// Number of levels in the mip pyramid, including the full image.
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Color seen in `direction` by a ray standing for directions straying about
+    /// `spread` radians from it.
+    ///
+    /// The two levels whose texels are closest to `spread` tall are sampled
+    /// bilinearly and blended. Texels narrow towards the poles, so a cone
+    /// there covers more of them across than down and is blurred a little
+    /// less than it should be.
+    pub fn color(&self, direction: &Vec3, spread: f64) -> Color {
+        let (mut u, mut v) = (0.0, 0.0);
+        crate::hittable::get_sphere_uv(&direction.unit_vector(), &mut u, &mut v);
+
+        let texel_angle = PI / self.levels[0].height as f64;
+        let lod = (spread / texel_angle)
+            .log2()
+            .clamp(0.0, (self.levels.len() - 1) as f64);
+        let level = lod.floor() as usize;
+        let t = lod - level as f64;
+
+        let fine = self.levels[level].sample(u, v);
+        if t == 0.0 {
+            return fine;
+        }
+        (1.0 - t) * fine + t * self.levels[level + 1].sample(u, v)
+    }
+}
+
+impl core::fmt::Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EnvironmentMap")
+            .field("width", &self.levels[0].width)
+            .field("height", &self.levels[0].height)
+            .field("levels", &self.levels.len())
+            .finish()
+    }
+}
@@ -7,7 +7,7 @@ use crate::ray::Ray;
 use crate::vec3::{Point3, Vec3};
 
 /// A record of an object's status for being hit by a `Ray`.
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct HitRecord {
     /// Point where the `Ray` struck.
     pub p: Point3,
@@ -23,47 +23,165 @@ pub struct HitRecord {
     pub v: f64,
     /// Whether the object struck was the front face.
     pub front_face: bool,
+    /// Unit tangent perpendicular to `normal`, pointing the way `u` increases
+    /// where the object has texture coordinates to follow.
+    pub tangent: Vec3,
+    /// Identity of the object struck, shared by every face of a box, mesh or
+    /// other composite, see [`HitRecord::set_object`].
+    pub object: usize,
+}
+
+thread_local! {
+    static NO_MATERIAL: std::sync::Arc<Material> = std::sync::Arc::default();
+}
+
+impl Default for HitRecord {
+    /// Empty record, sharing one placeholder material per thread rather than
+    /// allocating a new one each time.
+    fn default() -> Self {
+        Self {
+            p: Point3::default(),
+            normal: Vec3::default(),
+            material: NO_MATERIAL.with(|material| material.clone()),
+            t: 0.0,
+            u: 0.0,
+            v: 0.0,
+            front_face: false,
+            tangent: Vec3::default(),
+            object: 0,
+        }
+    }
 }
 
 impl HitRecord {
+    /// Record `object` as the one struck, by its address. Paths use it to
+    /// tell which dielectrics they are inside of, even when several share a
+    /// material.
+    #[inline]
+    pub fn set_object<T: ?Sized>(&mut self, object: &T) {
+        self.object = object as *const T as *const () as usize;
+    }
+
     /// Determine whether the `Ray` struck from inside or outside of an object.
     #[inline]
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: &Vec3) {
-        self.front_face = r.direction().dot(&outward_normal) < 0.0;
+        self.front_face = r.direction().dot(outward_normal) < 0.0;
         self.normal = if self.front_face {
             outward_normal.unit_vector()
         } else {
             -outward_normal.unit_vector()
         };
     }
+
+    /// Set the tangent from `dp_du`, the direction the surface moves as `u`
+    /// increases, with any part along the normal removed.
+    ///
+    /// Falls back to an arbitrary tangent from an `Onb` when `dp_du` is zero or
+    /// parallel to the normal, so pass `Vec3::default()` for objects without
+    /// texture coordinates. Call it after setting the normal.
+    pub fn set_tangent(&mut self, dp_du: &Vec3) {
+        let tangent = *dp_du - self.normal * dp_du.dot(&self.normal);
+        let length_squared = tangent.length_squared();
+        self.tangent = if length_squared > 1e-12 * dp_du.length_squared() && length_squared > 0.0 {
+            tangent / length_squared.sqrt()
+        } else {
+            crate::onb::Onb::build_from_w(&self.normal).u()
+        };
+    }
+
+    /// Unit bitangent completing the frame, `normal × tangent`.
+    #[inline]
+    pub fn bitangent(&self) -> Vec3 {
+        self.normal.cross(&self.tangent)
+    }
 }
 
 /// Trait for making objects able to be hit by a `Ray`.
 pub trait Hittable {
     /// Determine whether a ray hits an object.
+    ///
+    /// `rec` must be left untouched when this returns `false`, so that lists
+    /// and hierarchies can pass the same record to each object in turn.
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
+    /// Determine whether a ray hits the object anywhere between `t_min` and
+    /// `t_max`, for shadow rays which only need a yes or no answer.
+    ///
+    /// Must agree with `hit`, which the default calls with a scratch record.
+    /// Objects override it to skip working out where and how they were hit.
+    fn occluded(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.hit(r, t_min, t_max, &mut HitRecord::default())
+    }
     /// Compute the bounding box of an object.
     fn bounding_box(&self, t0: f64, t1: f64, output_box: &mut crate::aabb::Aabb) -> bool;
+    /// Return the records where a `Ray`, extended in both directions, enters
+    /// and exits the object.
+    ///
+    /// Only meaningful for convex, closed objects which a line crosses at most
+    /// twice. The default finds the entry with `hit` and the exit with a second
+    /// `hit` just past it.
+    fn hit_interval(&self, r: &Ray) -> Option<(HitRecord, HitRecord)> {
+        let mut enter = HitRecord::default();
+        let mut exit = HitRecord::default();
+
+        if !self.hit(r, f64::NEG_INFINITY, f64::INFINITY, &mut enter) {
+            return None;
+        }
+        if !self.hit(r, r.step_past(enter.t), f64::INFINITY, &mut exit) {
+            return None;
+        }
+
+        Some((enter, exit))
+    }
     /// Return the probability density function value.
     fn pdf_value(&self, _o: &Point3, _v: &Vec3) -> f64 {
         0.0
     }
     /// Return a random direction.
-    fn random(&self, _rng: &mut rand::rngs::ThreadRng, _origin: &Vec3) -> Vec3 {
+    fn random(&self, _rng: &mut crate::rng::RenderRng, _origin: &Vec3) -> Vec3 {
         Vec3::new(1.0, 0.0, 0.0)
     }
+    /// Return the power the object gives off as a light, its area times its
+    /// average emission, or `None` if it can't be worked out. Used to weigh
+    /// lights in a [`LightList`].
+    fn power(&self) -> Option<f64> {
+        None
+    }
+    /// Return the object as a `BvhNode` if it is one, used for walking
+    /// hierarchies.
+    fn as_bvh_node(&self) -> Option<&crate::bvh::BvhNode> {
+        None
+    }
+    /// Return the object as a `ConstantMedium` if it is one, used by renderers
+    /// that handle participating media separately from surfaces.
+    fn as_medium(&self) -> Option<&ConstantMedium> {
+        None
+    }
 }
 
+mod animated;
 mod box_prim;
 mod constant_medium;
+mod csg;
+mod disk;
+mod ellipsoid;
 mod flip_face;
+mod height_field;
+mod light_list;
 mod list;
 mod sphere;
 mod translate;
+mod triangle;
 
+pub use animated::{Animated, Keyframe};
 pub use box_prim::BoxPrim;
 pub use constant_medium::ConstantMedium;
+pub use csg::{Csg, CsgOp};
+pub use disk::Disk;
+pub use ellipsoid::Ellipsoid;
 pub use flip_face::FlipFace;
+pub use height_field::{HeightField, HeightFieldMode};
+pub use light_list::{LightList, LightListError};
 pub use list::HittableList;
-pub use sphere::{get_sphere_uv, MovingSphere, Sphere};
+pub use sphere::{get_sphere_uv, sphere_tangent, MovingSphere, PoleAxis, Sphere};
 pub use translate::{RotateY, Translate};
+pub use triangle::Triangle;
@@ -0,0 +1,212 @@
+//! Objects whose translation and rotation change over the shutter interval.
+
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// Pose of an animated object at a moment in time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Keyframe {
+    /// Time of the pose.
+    pub time: f64,
+    /// Translation, applied after the rotation.
+    pub offset: Vec3,
+    /// Rotation about the Y axis in degrees.
+    pub angle: f64,
+}
+
+impl Keyframe {
+    /// Create a new `Keyframe`.
+    pub fn new(time: f64, offset: Vec3, angle: f64) -> Self {
+        Self {
+            time,
+            offset,
+            angle,
+        }
+    }
+}
+
+/// Object rotated about the Y axis and then translated, following keyframes
+/// evaluated at the time of each ray.
+///
+/// Offsets and angles are interpolated linearly between neighboring keyframes
+/// and held before the first and after the last. Angles aren't wrapped, so
+/// turning from `0.0` to `720.0` spins the object twice.
+#[derive(Clone)]
+pub struct Animated {
+    /// Pointer to the underlying object.
+    pub pointer: Arc<dyn Hittable + Send + Sync>,
+    /// Poses sorted by time.
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl Animated {
+    /// Create a new animated object. At least one keyframe is required.
+    pub fn new(pointer: Arc<dyn Hittable + Send + Sync>, mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(
+            !keyframes.is_empty(),
+            "Animated needs at least one keyframe"
+        );
+        keyframes.sort_by(|a, b| {
+            a.time
+                .partial_cmp(&b.time)
+                .unwrap_or(core::cmp::Ordering::Equal)
+        });
+
+        Self { pointer, keyframes }
+    }
+
+    /// Interpolate the pose at `time`.
+    pub fn pose(&self, time: f64) -> Keyframe {
+        let next = self.keyframes.iter().position(|k| k.time > time);
+        let (a, b) = match next {
+            Some(0) => {
+                return Keyframe {
+                    time,
+                    ..self.keyframes[0]
+                }
+            }
+            Some(idx) => (self.keyframes[idx - 1], self.keyframes[idx]),
+            None => {
+                return Keyframe {
+                    time,
+                    ..self.keyframes[self.keyframes.len() - 1]
+                }
+            }
+        };
+
+        let f = (time - a.time) / (b.time - a.time);
+        Keyframe {
+            time,
+            offset: a.offset + f * (b.offset - a.offset),
+            angle: a.angle + f * (b.angle - a.angle),
+        }
+    }
+}
+
+/// Rotate a vector about the Y axis, the same direction as `RotateY`.
+fn rotate_y(v: Vec3, sin_theta: f64, cos_theta: f64) -> Vec3 {
+    Vec3::new(
+        cos_theta * v.x() + sin_theta * v.z(),
+        v.y(),
+        -sin_theta * v.x() + cos_theta * v.z(),
+    )
+}
+
+impl Hittable for Animated {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let pose = self.pose(r.time());
+        let (sin_theta, cos_theta) = pose.angle.to_radians().sin_cos();
+
+        // Move the ray into object space with the inverse transform
+        let local_r = Ray::new(
+            rotate_y(r.origin() - pose.offset, -sin_theta, cos_theta),
+            rotate_y(r.direction(), -sin_theta, cos_theta),
+            r.time(),
+        );
+        if !self.pointer.hit(&local_r, t_min, t_max, rec) {
+            return false;
+        }
+
+        let outward_normal = if rec.front_face {
+            rec.normal
+        } else {
+            -rec.normal
+        };
+        rec.p = rotate_y(rec.p, sin_theta, cos_theta) + pose.offset;
+        rec.set_face_normal(r, &rotate_y(outward_normal, sin_theta, cos_theta));
+        rec.tangent = rotate_y(rec.tangent, sin_theta, cos_theta);
+
+        true
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64, output_box: &mut Aabb) -> bool {
+        let mut bbox = Aabb::default();
+        if !self.pointer.bounding_box(t0, t1, &mut bbox) {
+            return false;
+        }
+
+        // Offsets move in straight lines between these poses, so their boxes
+        // cover every pose in between
+        let poses: Vec<Keyframe> = core::iter::once(self.pose(t0))
+            .chain(
+                self.keyframes
+                    .iter()
+                    .filter(|k| t0 < k.time && k.time < t1)
+                    .copied(),
+            )
+            .chain(core::iter::once(self.pose(t1)))
+            .collect();
+        let spins = poses.windows(2).any(|w| w[0].angle != w[1].angle);
+
+        let corners: Vec<Point3> = (0..8)
+            .map(|i| {
+                Point3::new(
+                    if i & 1 == 0 {
+                        bbox.min().x()
+                    } else {
+                        bbox.max().x()
+                    },
+                    if i & 2 == 0 {
+                        bbox.min().y()
+                    } else {
+                        bbox.max().y()
+                    },
+                    if i & 4 == 0 {
+                        bbox.min().z()
+                    } else {
+                        bbox.max().z()
+                    },
+                )
+            })
+            .collect();
+        // A spinning object stays inside the cylinder around the Y axis that
+        // reaches its farthest corner
+        let radius = corners
+            .iter()
+            .map(|c| c.x().hypot(c.z()))
+            .fold(0.0, f64::max);
+
+        let mut swept: Option<Aabb> = None;
+        for pose in &poses {
+            let (min, max) = if spins {
+                (
+                    Point3::new(-radius, bbox.min().y(), -radius),
+                    Point3::new(radius, bbox.max().y(), radius),
+                )
+            } else {
+                let (sin_theta, cos_theta) = pose.angle.to_radians().sin_cos();
+                corners.iter().fold(
+                    (
+                        Point3::new_with(f64::INFINITY),
+                        Point3::new_with(f64::NEG_INFINITY),
+                    ),
+                    |(min, max), &c| {
+                        let c = rotate_y(c, sin_theta, cos_theta);
+                        (
+                            Point3::new(min.x().min(c.x()), min.y().min(c.y()), min.z().min(c.z())),
+                            Point3::new(max.x().max(c.x()), max.y().max(c.y()), max.z().max(c.z())),
+                        )
+                    },
+                )
+            };
+
+            let posed = Aabb::new(&(min + pose.offset), &(max + pose.offset));
+            swept = Some(match swept {
+                Some(swept) => Aabb::surrounding_box(&swept, &posed),
+                None => posed,
+            });
+        }
+
+        match swept {
+            Some(swept) => {
+                *output_box = swept;
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -2,10 +2,12 @@
 
 use std::sync::Arc;
 
+use rand::Rng;
+
 use crate::aarect::{AaRect, Plane};
 use crate::hittable::{flip_face::FlipFace, HitRecord, Hittable, HittableList};
 use crate::material::Material;
-use crate::vec3::Point3;
+use crate::vec3::{Point3, Vec3};
 
 /// Box object.
 #[derive(Clone)]
@@ -16,11 +18,21 @@ pub struct BoxPrim {
     pub box_max: Point3,
     /// Sides of the box.
     pub sides: HittableList,
+    /// Padding for the bounding box in dimensions where the box is flat.
+    pub padding: f64,
 }
 
 impl BoxPrim {
     /// Creat a new box primitive.
     pub fn new(p0: &Point3, p1: &Point3, ptr: Arc<Material>) -> Self {
+        Self::new_per_face(p0, p1, core::array::from_fn(|_| ptr.clone()))
+    }
+
+    /// Create a new box primitive with a material for each face.
+    ///
+    /// Faces are ordered +Z, -Z, +Y, -Y, +X, -X.
+    pub fn new_per_face(p0: &Point3, p1: &Point3, materials: [Arc<Material>; 6]) -> Self {
+        let [pos_z, neg_z, pos_y, neg_y, pos_x, neg_x] = materials;
         let box_min = *p0;
         let box_max = *p1;
 
@@ -32,7 +44,7 @@ impl BoxPrim {
             p0.y(),
             p1.y(),
             p1.z(),
-            ptr.clone(),
+            pos_z,
             Plane::Xy,
         )));
         sides.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
@@ -41,7 +53,7 @@ impl BoxPrim {
             p0.y(),
             p1.y(),
             p0.z(),
-            ptr.clone(),
+            neg_z,
             Plane::Xy,
         )))));
 
@@ -51,7 +63,7 @@ impl BoxPrim {
             p0.z(),
             p1.z(),
             p1.y(),
-            ptr.clone(),
+            pos_y,
             Plane::Xz,
         )));
         sides.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
@@ -60,7 +72,7 @@ impl BoxPrim {
             p0.z(),
             p1.z(),
             p0.y(),
-            ptr.clone(),
+            neg_y,
             Plane::Xz,
         )))));
 
@@ -70,7 +82,7 @@ impl BoxPrim {
             p0.z(),
             p1.z(),
             p1.x(),
-            ptr.clone(),
+            pos_x,
             Plane::Yz,
         )));
         sides.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
@@ -79,7 +91,7 @@ impl BoxPrim {
             p0.z(),
             p1.z(),
             p0.x(),
-            ptr,
+            neg_x,
             Plane::Yz,
         )))));
 
@@ -87,17 +99,119 @@ impl BoxPrim {
             box_min,
             box_max,
             sides,
+            padding: crate::aabb::PADDING,
         }
     }
+
+    /// Set the padding for the bounding box in dimensions where the box is
+    /// flat, see [`Aabb::padded`](crate::aabb::Aabb::padded).
+    pub fn with_padding(mut self, padding: f64) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Areas of the pairs of faces perpendicular to the Z, Y, and X axes.
+    fn face_areas(&self) -> [f64; 3] {
+        let size = self.box_max - self.box_min;
+        [
+            size.x() * size.y(),
+            size.x() * size.z(),
+            size.y() * size.z(),
+        ]
+    }
 }
 
 impl Hittable for BoxPrim {
     fn hit(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
-        self.sides.hit(r, t_min, t_max, rec)
+        if !self.sides.hit(r, t_min, t_max, rec) {
+            return false;
+        }
+        rec.set_object(self);
+        true
+    }
+
+    fn occluded(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64) -> bool {
+        self.sides.occluded(r, t_min, t_max)
     }
 
     fn bounding_box(&self, _t0: f64, _t1: f64, output_box: &mut crate::aabb::Aabb) -> bool {
-        *output_box = crate::aabb::Aabb::new(&self.box_min, &self.box_max);
+        *output_box = crate::aabb::Aabb::padded(&self.box_min, &self.box_max, self.padding);
         true
     }
+
+    fn pdf_value(&self, o: &Point3, v: &Vec3) -> f64 {
+        let area = 2.0 * self.face_areas().iter().sum::<f64>();
+        let ray = crate::ray::Ray::new(*o, *v, 0.0);
+
+        // Points are picked on a face in proportion to its area, so every face
+        // the direction passes through contributes to its density
+        let mut pdf = 0.0;
+        let mut t_min = ray.step_past(0.0);
+        let mut rec = HitRecord::default();
+        while self.sides.hit(&ray, t_min, f64::INFINITY, &mut rec) {
+            let distance_squared = rec.t * rec.t * v.length_squared();
+            let cosine = (v.dot(&rec.normal) * v.length().recip()).abs();
+            pdf += distance_squared * (cosine * area).recip();
+            t_min = ray.step_past(rec.t);
+        }
+
+        pdf
+    }
+
+    fn random(&self, rng: &mut crate::rng::RenderRng, origin: &Vec3) -> Vec3 {
+        let (p0, p1) = (self.box_min, self.box_max);
+        let [xy, xz, _] = self.face_areas();
+        let pick = rng.gen::<f64>() * self.face_areas().iter().sum::<f64>();
+
+        let along =
+            |rng: &mut crate::rng::RenderRng, a: f64, b: f64| a + rng.gen::<f64>() * (b - a);
+        let side = |rng: &mut crate::rng::RenderRng, a: f64, b: f64| if rng.gen() { b } else { a };
+        let random_point = if pick < xy {
+            Point3::new(
+                along(rng, p0.x(), p1.x()),
+                along(rng, p0.y(), p1.y()),
+                side(rng, p0.z(), p1.z()),
+            )
+        } else if pick < xy + xz {
+            Point3::new(
+                along(rng, p0.x(), p1.x()),
+                side(rng, p0.y(), p1.y()),
+                along(rng, p0.z(), p1.z()),
+            )
+        } else {
+            Point3::new(
+                side(rng, p0.x(), p1.x()),
+                along(rng, p0.y(), p1.y()),
+                along(rng, p0.z(), p1.z()),
+            )
+        };
+
+        random_point - *origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+
+    #[test]
+    fn faces_take_their_materials_in_order() {
+        let materials: [Arc<Material>; 6] = Default::default();
+        let dice = BoxPrim::new_per_face(
+            &Point3::new(-1.0, -1.0, -1.0),
+            &Point3::new(1.0, 1.0, 1.0),
+            materials.clone(),
+        );
+
+        // Straight at the +Z face, then at the -X face
+        let mut rec = HitRecord::default();
+        let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(dice.hit(&r, 0.001, f64::INFINITY, &mut rec));
+        assert!(Arc::ptr_eq(&rec.material, &materials[0]));
+
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(dice.hit(&r, 0.001, f64::INFINITY, &mut rec));
+        assert!(Arc::ptr_eq(&rec.material, &materials[5]));
+    }
 }
@@ -4,16 +4,27 @@ use rand::Rng;
 
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::Material;
+use crate::texture::Texture;
+use crate::vec3::Color;
 
 /// Fog volume.
+///
+/// By default the density is uniform and the scattering distance is sampled in
+/// closed form. A medium built with [`ConstantMedium::new_textured`] instead
+/// looks up its density from a texture and has to step through the volume with
+/// delta tracking, which costs one texture lookup per tentative collision. The
+/// closer the texture stays to `max_density`, the fewer wasted steps are taken.
 #[derive(Clone)]
 pub struct ConstantMedium {
     /// Boundary of volume.
     pub boundary: Arc<dyn Hittable + Send + Sync>,
     /// Material of volume.
     pub phase_function: Arc<Material>,
-    /// Negative inverse density of volume.
+    /// Negative inverse density of volume. For textured media this is the
+    /// negative inverse of the maximum density.
     pub neg_inv_density: f64,
+    /// Texture scaling the maximum density at each point, if the density varies.
+    pub density: Option<Arc<dyn Texture + Send + Sync>>,
 }
 
 impl ConstantMedium {
@@ -28,7 +39,73 @@ impl ConstantMedium {
             phase_function: Arc::new(Material::Iso(crate::material::Isotropic::new(
                 phase_function,
             ))),
-            neg_inv_density: -1.0 * neg_inv_density.recip(),
+            neg_inv_density: -neg_inv_density.recip(),
+            density: None,
+        }
+    }
+
+    /// Create a new medium whose density varies in space.
+    ///
+    /// The density at a point is `max_density` scaled by the average of the
+    /// `density` texture's channels there, which should stay within `[0, 1]`.
+    pub fn new_textured(
+        boundary: Arc<dyn Hittable + Send + Sync>,
+        phase_function: Arc<dyn crate::texture::Texture + Send + Sync>,
+        density: Arc<dyn Texture + Send + Sync>,
+        max_density: f64,
+    ) -> Self {
+        Self {
+            boundary,
+            phase_function: Arc::new(Material::Iso(crate::material::Isotropic::new(
+                phase_function,
+            ))),
+            neg_inv_density: -max_density.recip(),
+            density: Some(density),
+        }
+    }
+
+    /// Create a new constant medium that glows with `emit` at every point
+    /// where a ray scatters inside it.
+    pub fn new_emissive(
+        boundary: Arc<dyn Hittable + Send + Sync>,
+        phase_function: Arc<dyn crate::texture::Texture + Send + Sync>,
+        density: f64,
+        emit: Arc<dyn crate::texture::Texture + Send + Sync>,
+    ) -> Self {
+        Self {
+            boundary,
+            phase_function: Arc::new(Material::Iso(crate::material::Isotropic::new_emissive(
+                phase_function,
+                emit,
+            ))),
+            neg_inv_density: -density.recip(),
+            density: None,
+        }
+    }
+
+    /// Density of the medium at a point inside its boundary.
+    pub fn density_at(&self, p: &crate::vec3::Point3) -> f64 {
+        let max_density = -self.neg_inv_density.recip();
+        match &self.density {
+            None => max_density,
+            Some(density) => {
+                let d = density.value(0.0, 0.0, p);
+                max_density * (d.x() + d.y() + d.z()) / 3.0
+            }
+        }
+    }
+
+    /// Fraction of light scattered, and light emitted, at a point where a ray
+    /// scatters inside the medium.
+    pub fn scattering_at(&self, p: &crate::vec3::Point3) -> (Color, Color) {
+        match &*self.phase_function {
+            Material::Iso(iso) => (
+                iso.albedo.value(0.0, 0.0, p),
+                iso.emit
+                    .as_ref()
+                    .map_or(Color::new_with(0.0), |emit| emit.value(0.0, 0.0, p)),
+            ),
+            _ => (Color::new_with(0.0), Color::new_with(0.0)),
         }
     }
 }
@@ -47,7 +124,7 @@ impl Hittable for ConstantMedium {
 
         if !self
             .boundary
-            .hit(r, rec1.t + 0.0001, f64::INFINITY, &mut rec2)
+            .hit(r, r.step_past(rec1.t), f64::INFINITY, &mut rec2)
         {
             return false;
         }
@@ -69,19 +146,53 @@ impl Hittable for ConstantMedium {
         }
 
         let ray_length = r.direction().length();
-        let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
-        let hit_distance = self.neg_inv_density * rand::thread_rng().gen::<f64>().ln();
-
-        if hit_distance > distance_inside_boundary {
+        // `hit` has no generator passed in, so use the one the renderer seeds
+        // for each pixel
+        let hit = crate::rng::with_hit_rng(|rng| {
+            match &self.density {
+                None => {
+                    let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
+                    let hit_distance = self.neg_inv_density * rng.gen::<f64>().ln();
+
+                    if hit_distance > distance_inside_boundary {
+                        return false;
+                    }
+
+                    rec.t = rec1.t + hit_distance * ray_length.recip();
+                    rec.p = r.at(rec.t);
+                    true
+                }
+                Some(density) => {
+                    // Delta tracking: step with the maximum density and accept
+                    // each tentative collision with probability
+                    // `density / max_density`
+                    let mut t = rec1.t;
+                    loop {
+                        t += self.neg_inv_density * rng.gen::<f64>().ln() * ray_length.recip();
+                        if t >= rec2.t {
+                            return false;
+                        }
+
+                        let p = r.at(t);
+                        let d = density.value(0.0, 0.0, &p);
+                        if rng.gen::<f64>() < (d.x() + d.y() + d.z()) / 3.0 {
+                            rec.t = t;
+                            rec.p = p;
+                            return true;
+                        }
+                    }
+                }
+            }
+        });
+        if !hit {
             return false;
         }
 
-        rec.t = rec1.t + hit_distance * ray_length.recip();
-        rec.p = r.at(rec.t);
-
         rec.normal = crate::vec3::Vec3::new(1.0, 0.0, 0.0);
+        rec.tangent = crate::vec3::Vec3::new(0.0, 1.0, 0.0);
         rec.front_face = true;
         rec.material = self.phase_function.clone();
+        rec.set_object(self);
 
         true
     }
@@ -94,4 +205,50 @@ impl Hittable for ConstantMedium {
     ) -> std::primitive::bool {
         self.boundary.bounding_box(t0, t1, output_box)
     }
+
+    fn as_medium(&self) -> Option<&ConstantMedium> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Sphere;
+    use crate::texture::{Checker, SolidColor};
+    use crate::vec3::{Point3, Vec3};
+
+    #[test]
+    fn antialiased_checker_density_does_not_reborrow_the_hit_rng() {
+        let density = Checker::new(
+            Arc::new(SolidColor::new_with(1.0)),
+            Arc::new(SolidColor::new_with(0.5)),
+        )
+        .with_antialias(true);
+        let medium = ConstantMedium::new_textured(
+            Arc::new(Sphere::new(
+                Point3::new(0.0, 0.0, 0.0),
+                1.0,
+                Material::default(),
+            )),
+            Arc::new(SolidColor::new_with(0.5)),
+            Arc::new(density),
+            1.0,
+        );
+
+        // A footprint wide enough that the checker averages jittered points
+        crate::texture::set_pixel_spread(0.1);
+        crate::texture::set_path_length(1.0);
+        let r = crate::ray::Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let mut rec = HitRecord::default();
+        let hit = medium.hit(&r, 0.001, f64::INFINITY, &mut rec);
+        // On the edge between squares, so the points land on both
+        let edge = medium.density_at(&Point3::new(0.0, 0.05, 0.05));
+        crate::texture::set_pixel_spread(0.0);
+        crate::texture::set_path_length(0.0);
+
+        assert!(!hit || (4.0..=6.0).contains(&rec.t), "hit at {}", rec.t);
+        assert!(edge > 0.5 && edge < 1.0, "density {}", edge);
+        assert_eq!(crate::texture::footprint(), 0.0);
+    }
 }
@@ -0,0 +1,143 @@
+//! Constructive solid geometry for combining hittable objects.
+
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::ray::Ray;
+use crate::vec3::Point3;
+
+/// Boolean operations for combining two solids.
+#[derive(Clone, Copy, Debug)]
+pub enum CsgOp {
+    /// Points inside either solid.
+    Union,
+    /// Points inside both solids.
+    Intersection,
+    /// Points inside the first solid but not the second.
+    Difference,
+}
+
+/// Combination of two solids with a boolean operation.
+///
+/// Both operands must be convex and closed, like a `Sphere` or `BoxPrim`, so
+/// that each ray enters and exits them at most once. See
+/// [`Hittable::hit_interval`].
+#[derive(Clone)]
+pub struct Csg {
+    /// First operand.
+    pub a: Arc<dyn Hittable + Send + Sync>,
+    /// Second operand, the one removed by `CsgOp::Difference`.
+    pub b: Arc<dyn Hittable + Send + Sync>,
+    /// Operation combining the operands.
+    pub op: CsgOp,
+}
+
+impl Csg {
+    /// Create a new combination of two solids.
+    pub fn new(
+        a: Arc<dyn Hittable + Send + Sync>,
+        b: Arc<dyn Hittable + Send + Sync>,
+        op: CsgOp,
+    ) -> Self {
+        Self { a, b, op }
+    }
+}
+
+/// Whether `t` lies strictly inside an operand's interval.
+fn inside(interval: &Option<(HitRecord, HitRecord)>, t: f64) -> bool {
+    match interval {
+        Some((enter, exit)) => enter.t < t && t < exit.t,
+        None => false,
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let interval_a = self.a.hit_interval(r);
+        let interval_b = self.b.hit_interval(r);
+
+        // Every surface crossing, tagged with whether it belongs to `b`
+        let mut crossings: Vec<(&HitRecord, bool)> = Vec::with_capacity(4);
+        if let Some((enter, exit)) = &interval_a {
+            crossings.push((enter, false));
+            crossings.push((exit, false));
+        }
+        if let Some((enter, exit)) = &interval_b {
+            crossings.push((enter, true));
+            crossings.push((exit, true));
+        }
+        crossings.sort_unstable_by(|x, y| {
+            x.0.t
+                .partial_cmp(&y.0.t)
+                .unwrap_or(core::cmp::Ordering::Equal)
+        });
+
+        for (crossing, from_b) in crossings {
+            if crossing.t < t_min || crossing.t > t_max {
+                continue;
+            }
+
+            let (in_a, in_b) = (
+                inside(&interval_a, crossing.t),
+                inside(&interval_b, crossing.t),
+            );
+            let on_surface = match (self.op, from_b) {
+                (CsgOp::Union, false) => !in_b,
+                (CsgOp::Union, true) => !in_a,
+                (CsgOp::Intersection, false) => in_b,
+                (CsgOp::Intersection, true) => in_a,
+                (CsgOp::Difference, false) => !in_b,
+                (CsgOp::Difference, true) => in_a,
+            };
+            if !on_surface {
+                continue;
+            }
+
+            let mut outward_normal = if crossing.front_face {
+                crossing.normal
+            } else {
+                -crossing.normal
+            };
+            // The carved out surface faces into the removed solid
+            if from_b {
+                if let CsgOp::Difference = self.op {
+                    outward_normal = -outward_normal;
+                }
+            }
+
+            *rec = crossing.clone();
+            rec.set_face_normal(r, &outward_normal);
+            rec.set_object(self);
+            return true;
+        }
+
+        false
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64, output_box: &mut Aabb) -> bool {
+        let mut box_a = Aabb::default();
+        let mut box_b = Aabb::default();
+        if !self.a.bounding_box(t0, t1, &mut box_a) || !self.b.bounding_box(t0, t1, &mut box_b) {
+            return false;
+        }
+
+        *output_box = match self.op {
+            CsgOp::Union => Aabb::surrounding_box(&box_a, &box_b),
+            CsgOp::Intersection => Aabb::new(
+                &Point3::new(
+                    box_a.min().x().max(box_b.min().x()),
+                    box_a.min().y().max(box_b.min().y()),
+                    box_a.min().z().max(box_b.min().z()),
+                ),
+                &Point3::new(
+                    box_a.max().x().min(box_b.max().x()),
+                    box_a.max().y().min(box_b.max().y()),
+                    box_a.max().z().min(box_b.max().z()),
+                ),
+            ),
+            CsgOp::Difference => box_a,
+        };
+        true
+    }
+}
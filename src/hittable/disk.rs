@@ -0,0 +1,178 @@
+//! Flat disks and rings, such as round area lights.
+
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// Flat disk, or a ring when it has a hole in the middle.
+///
+/// Texture coordinates run around the disk in `u`, and out from the hole to
+/// the rim in `v`.
+#[derive(Clone)]
+pub struct Disk {
+    /// Center of the disk.
+    pub center: Point3,
+    /// Unit normal on the front face.
+    pub normal: Vec3,
+    /// Radius of the rim.
+    pub radius: f64,
+    /// Radius of the hole in the middle, `0.0` for a solid disk.
+    pub inner_radius: f64,
+    /// Material of the disk.
+    pub material: Arc<Material>,
+    /// Padding for the bounding box in dimensions where the disk is flat.
+    pub padding: f64,
+}
+
+impl Disk {
+    /// Create a solid disk of `radius` around `center`, with its front face
+    /// towards `normal`.
+    pub fn new(center: Point3, normal: Vec3, radius: f64, material: Arc<Material>) -> Self {
+        Self::new_annulus(center, normal, 0.0, radius, material)
+    }
+
+    /// Create a ring between `inner_radius` and `radius` around `center`,
+    /// with its front face towards `normal`, such as a ring light.
+    pub fn new_annulus(
+        center: Point3,
+        normal: Vec3,
+        inner_radius: f64,
+        radius: f64,
+        material: Arc<Material>,
+    ) -> Self {
+        Self {
+            center,
+            normal: normal.unit_vector(),
+            radius,
+            inner_radius,
+            material,
+            padding: crate::aabb::PADDING,
+        }
+    }
+
+    /// Set the padding for the bounding box in dimensions where the disk is
+    /// flat, see [`Aabb::padded`](crate::aabb::Aabb::padded).
+    pub fn with_padding(self, padding: f64) -> Self {
+        Self { padding, ..self }
+    }
+
+    /// Area of the disk, not counting the hole.
+    pub fn area(&self) -> f64 {
+        core::f64::consts::PI * (self.radius * self.radius - self.inner_radius * self.inner_radius)
+    }
+
+    /// Where `r` crosses the disk between `t_min` and `t_max`, as `t`, the
+    /// texture coordinates and the point, unless it passes through the hole,
+    /// outside the rim, or where the material cuts a hole.
+    #[inline]
+    fn crossing(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64, f64, Point3)> {
+        // Rays parallel to the disk never cross it
+        let denom = self.normal.dot(&r.direction());
+        if denom.abs() < f64::EPSILON {
+            return None;
+        }
+        let t = self.normal.dot(&(self.center - r.origin())) * denom.recip();
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let p = r.at(t);
+        let offset = p - self.center;
+        let distance_squared = offset.length_squared();
+        if distance_squared > self.radius * self.radius
+            || distance_squared < self.inner_radius * self.inner_radius
+        {
+            return None;
+        }
+
+        let axes = Onb::build_from_w(&self.normal);
+        let phi = offset.dot(&axes.v()).atan2(offset.dot(&axes.u()));
+        let u = (phi + core::f64::consts::PI) * crate::conversion::TWO_PI.recip();
+        let v = (distance_squared.sqrt() - self.inner_radius)
+            * (self.radius - self.inner_radius).recip();
+        if self.material.is_cut_out(u, v, &p) {
+            return None;
+        }
+
+        Some((t, u, v, p))
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let (t, u, v, p) = match self.crossing(r, t_min, t_max) {
+            Some(crossing) => crossing,
+            None => return false,
+        };
+
+        rec.t = t;
+        rec.p = p;
+        rec.u = u;
+        rec.v = v;
+        rec.set_face_normal(r, &self.normal);
+        // Around the center, the way `u` increases
+        rec.set_tangent(&(p - self.center).cross(&self.normal));
+        rec.material = self.material.clone();
+        rec.set_object(self);
+
+        true
+    }
+
+    fn occluded(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.crossing(r, t_min, t_max).is_some()
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64, output_box: &mut crate::aabb::Aabb) -> bool {
+        // The rim reaches out furthest along the axes the disk lies across
+        let n = self.normal;
+        let extent = self.radius
+            * Vec3::new(
+                (1.0 - n.x() * n.x()).max(0.0).sqrt(),
+                (1.0 - n.y() * n.y()).max(0.0).sqrt(),
+                (1.0 - n.z() * n.z()).max(0.0).sqrt(),
+            );
+
+        *output_box = crate::aabb::Aabb::padded(
+            &(self.center - extent),
+            &(self.center + extent),
+            self.padding,
+        );
+        true
+    }
+
+    fn power(&self) -> Option<f64> {
+        let emission = self.material.emission(0.5, 0.5, &self.center);
+        Some(self.area() * (emission.x() + emission.y() + emission.z()) / 3.0)
+    }
+
+    fn pdf_value(&self, o: &Point3, v: &Vec3) -> f64 {
+        let r = Ray::new(*o, *v, 0.0);
+        let t = match self.crossing(&r, r.step_past(0.0), f64::INFINITY) {
+            Some((t, ..)) => t,
+            None => return 0.0,
+        };
+
+        let distance_squared = t * t * v.length_squared();
+        let cosine = (v.dot(&self.normal) * v.length().recip()).abs();
+
+        distance_squared * (cosine * self.area()).recip()
+    }
+
+    fn random(&self, rng: &mut crate::rng::RenderRng, origin: &Vec3) -> Vec3 {
+        // Uniform over the area, so the square of the radius is uniform
+        // between the hole's and the rim's
+        let inner_squared = self.inner_radius * self.inner_radius;
+        let r =
+            (inner_squared + rng.gen::<f64>() * (self.radius * self.radius - inner_squared)).sqrt();
+        let phi = crate::conversion::TWO_PI * rng.gen::<f64>();
+
+        let axes = Onb::build_from_w(&self.normal);
+        self.center + axes.local_from(r * phi.cos(), r * phi.sin(), 0.0) - *origin
+    }
+}
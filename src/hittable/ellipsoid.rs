@@ -0,0 +1,77 @@
+//! Ellipsoid primitive, a sphere stretched along each axis.
+
+use std::sync::Arc;
+
+use crate::hittable::{get_sphere_uv, sphere_tangent, HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// Axis aligned ellipsoid object.
+///
+/// Rays are intersected with a unit sphere after dividing by `radii`, so this
+/// is cheaper than scaling a sphere with a general transform.
+#[derive(Clone)]
+pub struct Ellipsoid {
+    /// Center point of the ellipsoid.
+    pub center: Point3,
+    /// Radius along each of the X, Y, and Z axes.
+    pub radii: Vec3,
+    /// Material of the ellipsoid.
+    pub material: Arc<Material>,
+}
+
+impl Ellipsoid {
+    /// Create a new ellipsoid.
+    pub fn new(center: Point3, radii: Vec3, material: Arc<Material>) -> Self {
+        Self {
+            center,
+            radii,
+            material,
+        }
+    }
+}
+
+impl Hittable for Ellipsoid {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        // Intersect a unit sphere in the space where the ellipsoid is one, which
+        // keeps `t` the same as along the original ray
+        let inv_radii = self.radii.recip();
+        let oc = (r.origin() - self.center) * inv_radii;
+        let direction = r.direction() * inv_radii;
+        let a = direction.length_squared();
+        let half_b = oc.dot(&direction);
+        let c = oc.length_squared() - 1.0;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant <= 0.0 {
+            return false;
+        }
+
+        let root = discriminant.sqrt();
+        let mut t = (-half_b - root) / a;
+        if t >= t_max || t <= t_min {
+            t = (-half_b + root) / a;
+            if t >= t_max || t <= t_min {
+                return false;
+            }
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        let local = (rec.p - self.center) * inv_radii;
+        get_sphere_uv(&local, &mut rec.u, &mut rec.v);
+        // Normals transform by the inverse transpose of the scale
+        rec.set_face_normal(r, &(local * inv_radii));
+        rec.set_tangent(&(sphere_tangent(&local) * self.radii));
+        rec.material = self.material.clone();
+        rec.set_object(self);
+
+        true
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64, output_box: &mut crate::aabb::Aabb) -> bool {
+        *output_box =
+            crate::aabb::Aabb::new(&(self.center - self.radii), &(self.center + self.radii));
+        true
+    }
+}
@@ -31,6 +31,10 @@ impl Hittable for FlipFace {
         true
     }
 
+    fn occluded(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64) -> bool {
+        self.pointer.occluded(r, t_min, t_max)
+    }
+
     fn bounding_box(
         &self,
         t0: std::primitive::f64,
@@ -39,4 +43,20 @@ impl Hittable for FlipFace {
     ) -> std::primitive::bool {
         self.pointer.bounding_box(t0, t1, output_box)
     }
+
+    fn power(&self) -> Option<f64> {
+        self.pointer.power()
+    }
+
+    fn pdf_value(&self, o: &crate::vec3::Point3, v: &crate::vec3::Vec3) -> f64 {
+        self.pointer.pdf_value(o, v)
+    }
+
+    fn random(
+        &self,
+        rng: &mut crate::rng::RenderRng,
+        origin: &crate::vec3::Vec3,
+    ) -> crate::vec3::Vec3 {
+        self.pointer.random(rng, origin)
+    }
 }
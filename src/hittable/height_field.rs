@@ -0,0 +1,343 @@
+//! Terrain built from a grid of elevations.
+
+use std::sync::Arc;
+
+use crate::aarect::{AaRect, Plane};
+use crate::bvh::BvhNode;
+use crate::hittable::{flip_face::FlipFace, HitRecord, Hittable, HittableList, Triangle};
+use crate::material::Material;
+use crate::vec3::Point3;
+
+/// How a height field turns its grid of elevations into a surface.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeightFieldMode {
+    /// Elevations are grid vertices joined by two triangles per cell.
+    #[default]
+    Smooth,
+    /// Each elevation is a flat-topped column over its own cell, for a voxel
+    /// look. Tops and walls are axis-aligned rectangles, which are cheaper to
+    /// hit than triangles.
+    Stepped,
+}
+
+/// Terrain spanning `[0, width]` on the X axis and `[0, depth]` on the Z axis,
+/// rising from `0` to `height_scale` on the Y axis.
+///
+/// In [`HeightFieldMode::Smooth`] each grid cell is split into two triangles,
+/// in [`HeightFieldMode::Stepped`] each sample becomes a column. Either way the
+/// pieces are held in a BVH. Texture coordinates run from `(0, 0)` at the
+/// origin to `(1, 1)` at the far corner so textures drape over the whole
+/// terrain.
+#[derive(Clone)]
+pub struct HeightField {
+    /// Hierarchy of the terrain's triangles.
+    pub bvh: BvhNode,
+    /// Size of the terrain along the X axis.
+    pub width: f64,
+    /// Size of the terrain along the Z axis.
+    pub depth: f64,
+    /// Elevation of a grid sample with the value `1.0`.
+    pub height_scale: f64,
+    /// Surface built from the grid.
+    pub mode: HeightFieldMode,
+}
+
+impl HeightField {
+    /// Create a new height field from a grid of elevations in `[0, 1]`.
+    ///
+    /// `heights` holds `nx` samples along X for each of the `nz` rows along Z.
+    /// Both dimensions need at least two samples.
+    pub fn new(
+        heights: &[f64],
+        nx: usize,
+        nz: usize,
+        width: f64,
+        depth: f64,
+        height_scale: f64,
+        material: Arc<Material>,
+    ) -> Self {
+        Self::new_with_mode(
+            heights,
+            nx,
+            nz,
+            width,
+            depth,
+            height_scale,
+            HeightFieldMode::Smooth,
+            material,
+        )
+    }
+
+    /// Create a new height field from a grid of elevations in `[0, 1]` with the
+    /// given surface mode.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_mode(
+        heights: &[f64],
+        nx: usize,
+        nz: usize,
+        width: f64,
+        depth: f64,
+        height_scale: f64,
+        mode: HeightFieldMode,
+        material: Arc<Material>,
+    ) -> Self {
+        assert!(nx >= 2 && nz >= 2, "HeightField needs at least a 2x2 grid");
+        assert_eq!(heights.len(), nx * nz, "HeightField grid size mismatch");
+
+        let pieces = match mode {
+            HeightFieldMode::Smooth => {
+                Self::triangles(heights, nx, nz, width, depth, height_scale, material)
+            }
+            HeightFieldMode::Stepped => {
+                Self::columns(heights, nx, nz, width, depth, height_scale, material)
+            }
+        };
+
+        Self {
+            // An empty grid gets an empty node, which nothing hits
+            bvh: BvhNode::try_from_list(&pieces, 0.0, 1.0).unwrap_or_default(),
+            width,
+            depth,
+            height_scale,
+            mode,
+        }
+    }
+
+    /// Two triangles for each cell between four neighbouring samples.
+    fn triangles(
+        heights: &[f64],
+        nx: usize,
+        nz: usize,
+        width: f64,
+        depth: f64,
+        height_scale: f64,
+        material: Arc<Material>,
+    ) -> HittableList {
+        let vertex = |i: usize, j: usize| -> (Point3, (f64, f64)) {
+            let u = i as f64 / (nx - 1) as f64;
+            let v = j as f64 / (nz - 1) as f64;
+            let y = heights[j * nx + i] * height_scale;
+            (Point3::new(u * width, y, v * depth), (u, v))
+        };
+
+        let mut triangles = HittableList::with_capacity(2 * (nx - 1) * (nz - 1));
+        for j in 0..nz - 1 {
+            for i in 0..nx - 1 {
+                let (p00, uv00) = vertex(i, j);
+                let (p10, uv10) = vertex(i + 1, j);
+                let (p01, uv01) = vertex(i, j + 1);
+                let (p11, uv11) = vertex(i + 1, j + 1);
+
+                triangles.add(Arc::new(Triangle::with_uvs(
+                    p00,
+                    p01,
+                    p10,
+                    [uv00, uv01, uv10],
+                    material.clone(),
+                )));
+                triangles.add(Arc::new(Triangle::with_uvs(
+                    p10,
+                    p01,
+                    p11,
+                    [uv10, uv01, uv11],
+                    material.clone(),
+                )));
+            }
+        }
+
+        triangles
+    }
+
+    /// A flat top for each sample, with walls only where a column stands above
+    /// its neighbour or the edge of the terrain.
+    fn columns(
+        heights: &[f64],
+        nx: usize,
+        nz: usize,
+        width: f64,
+        depth: f64,
+        height_scale: f64,
+        material: Arc<Material>,
+    ) -> HittableList {
+        let (dx, dz) = (width / nx as f64, depth / nz as f64);
+        let height = |i: usize, j: usize| heights[j * nx + i] * height_scale;
+
+        let mut faces = HittableList::with_capacity(3 * nx * nz);
+        for j in 0..nz {
+            for i in 0..nx {
+                let (x0, x1) = (i as f64 * dx, (i + 1) as f64 * dx);
+                let (z0, z1) = (j as f64 * dz, (j + 1) as f64 * dz);
+                let y = height(i, j);
+
+                faces.add(Arc::new(AaRect::new(
+                    x0,
+                    x1,
+                    z0,
+                    z1,
+                    y,
+                    material.clone(),
+                    Plane::Xz,
+                )));
+
+                // Each wall faces away from the column, towards the lower side
+                let neighbours = [
+                    (i.checked_sub(1).map(|i| height(i, j)), x0, Plane::Yz, true),
+                    ((i + 1 < nx).then(|| height(i + 1, j)), x1, Plane::Yz, false),
+                    (j.checked_sub(1).map(|j| height(i, j)), z0, Plane::Xy, true),
+                    ((j + 1 < nz).then(|| height(i, j + 1)), z1, Plane::Xy, false),
+                ];
+                for (neighbour, k, plane, flip) in neighbours {
+                    let floor = neighbour.unwrap_or(0.0);
+                    if floor >= y {
+                        continue;
+                    }
+                    let wall = match plane {
+                        Plane::Yz => AaRect::new(floor, y, z0, z1, k, material.clone(), plane),
+                        _ => AaRect::new(x0, x1, floor, y, k, material.clone(), plane),
+                    };
+                    if flip {
+                        faces.add(Arc::new(FlipFace::new(Arc::new(wall))));
+                    } else {
+                        faces.add(Arc::new(wall));
+                    }
+                }
+            }
+        }
+
+        faces
+    }
+
+    /// Create a new height field from the brightness of a grayscale image.
+    ///
+    /// Image columns run along the X axis and rows along the Z axis.
+    #[cfg(feature = "images")]
+    pub fn from_image<P: AsRef<std::path::Path>>(
+        path: P,
+        width: f64,
+        depth: f64,
+        height_scale: f64,
+        material: Arc<Material>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_image_with_mode(
+            path,
+            width,
+            depth,
+            height_scale,
+            HeightFieldMode::Smooth,
+            material,
+        )
+    }
+
+    /// Create a new height field from the brightness of a grayscale image with
+    /// the given surface mode.
+    #[cfg(feature = "images")]
+    pub fn from_image_with_mode<P: AsRef<std::path::Path>>(
+        path: P,
+        width: f64,
+        depth: f64,
+        height_scale: f64,
+        mode: HeightFieldMode,
+        material: Arc<Material>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let img = image::open(path)?.to_luma8();
+        let (nx, nz) = img.dimensions();
+        let heights: Vec<f64> = img
+            .into_raw()
+            .into_iter()
+            .map(crate::conversion::IntoF64::into_f64)
+            .collect();
+
+        Ok(Self::new_with_mode(
+            &heights,
+            nx as usize,
+            nz as usize,
+            width,
+            depth,
+            height_scale,
+            mode,
+            material,
+        ))
+    }
+}
+
+impl Hittable for HeightField {
+    fn hit(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        if !self.bvh.hit(r, t_min, t_max, rec) {
+            return false;
+        }
+        rec.set_object(self);
+        if self.mode == HeightFieldMode::Stepped {
+            // Rectangles map textures onto themselves, so drape them over the
+            // terrain the same way the triangles do
+            rec.u = rec.p.x() / self.width;
+            rec.v = rec.p.z() / self.depth;
+        }
+        true
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64, output_box: &mut crate::aabb::Aabb) -> bool {
+        // A flat terrain has no height
+        *output_box = crate::aabb::Aabb::padded(
+            &Point3::new(0.0, 0.0, 0.0),
+            &Point3::new(self.width, self.height_scale, self.depth),
+            crate::aabb::PADDING,
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stepped_tops_face_straight_up() {
+        let heights = [0.0, 0.5, 1.0, 0.25, 0.75, 0.1];
+        let field = HeightField::new_with_mode(
+            &heights,
+            3,
+            2,
+            3.0,
+            2.0,
+            4.0,
+            HeightFieldMode::Stepped,
+            Arc::new(Material::default()),
+        );
+
+        for j in 0..2 {
+            for i in 0..3 {
+                let center = Point3::new(i as f64 + 0.5, 10.0, j as f64 + 0.5);
+                let r = crate::ray::Ray::new(center, crate::vec3::Vec3::new(0.0, -1.0, 0.0), 0.0);
+                let mut rec = HitRecord::default();
+                assert!(field.hit(&r, 0.001, f64::INFINITY, &mut rec));
+                assert_eq!(
+                    (rec.normal.x(), rec.normal.y(), rec.normal.z()),
+                    (0.0, 1.0, 0.0)
+                );
+                assert!((rec.p.y() - heights[j * 3 + i] * 4.0).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn image_terrain_is_as_tall_as_its_height_scale() {
+        let path = std::env::temp_dir().join(format!("rtiow-{}-terrain.png", std::process::id()));
+        image::GrayImage::from_raw(3, 2, vec![0, 64, 128, 32, 255, 16])
+            .unwrap()
+            .save(&path)
+            .unwrap();
+        let field = HeightField::from_image(&path, 6.0, 4.0, 2.5, Arc::new(Material::default()));
+        std::fs::remove_file(&path).unwrap();
+        let field = field.unwrap();
+
+        let mut bbox = crate::aabb::Aabb::default();
+        assert!(field.bounding_box(0.0, 1.0, &mut bbox));
+        assert_eq!(bbox.max().y() - bbox.min().y(), 2.5);
+
+        // The white pixel reaches the top of the box
+        let mut terrain = crate::aabb::Aabb::default();
+        assert!(field.bvh.bounding_box(0.0, 1.0, &mut terrain));
+        assert!((terrain.max().y() - 2.5).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::hittable::{HitRecord, Hittable, HittableList};
+use crate::vec3::{Color, Point3, Vec3};
+
+/// Error adding a light to a [`LightList`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LightListError {
+    /// The light at this index of the list can't tell its power, see
+    /// [`Hittable::power`], so it has to be added with
+    /// [`LightList::add_with_power`].
+    UnknownPower(usize),
+}
+
+impl core::fmt::Display for LightListError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LightListError::UnknownPower(index) => {
+                write!(f, "light {} can't tell its power to be sampled by", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LightListError {}
+
+/// Lights to sample towards, picking each in proportion to how much light it
+/// gives off.
+///
+/// Picking uniformly spends as many samples on a dim light as on one a hundred
+/// times brighter, though the dim one barely changes the result. Each light's
+/// power is worked out once as it's added, as its area times its average
+/// emission, and its share of the total is both how often it's picked and how
+/// much its own `pdf_value` counts for.
+#[derive(Clone, Default)]
+pub struct LightList {
+    /// The lights, which rays can also hit like any other list.
+    lights: HittableList,
+    /// Running total of the lights' powers, up to and including each light.
+    cumulative: Vec<f64>,
+}
+
+impl LightList {
+    /// Create an empty `LightList`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a light, with the power it works out from its own shape and
+    /// material, see [`Hittable::power`].
+    ///
+    /// Fails for lights which can't tell their power, such as spheres, which
+    /// have to be added with [`add_with_power`](Self::add_with_power).
+    pub fn add(&mut self, light: Arc<dyn Hittable + Send + Sync>) -> Result<(), LightListError> {
+        let power = light
+            .power()
+            .ok_or_else(|| LightListError::UnknownPower(self.len()))?;
+        self.push(light, power);
+        Ok(())
+    }
+
+    /// Add a light with a surface `area` giving off `emission`, overriding
+    /// the power it would work out itself.
+    ///
+    /// Only the ratio of the lights' powers matters, so `area` and `emission`
+    /// need only be consistent between lights, such as the emission of a
+    /// `DiffuseLight` with a solid color.
+    pub fn add_with_power(
+        &mut self,
+        light: Arc<dyn Hittable + Send + Sync>,
+        area: f64,
+        emission: Color,
+    ) {
+        let average = (emission.x() + emission.y() + emission.z()) / 3.0;
+        self.push(light, area * average);
+    }
+
+    /// Add a light giving off `power`.
+    fn push(&mut self, light: Arc<dyn Hittable + Send + Sync>, power: f64) {
+        self.cumulative.push(self.total_power() + power.max(0.0));
+        self.lights.add(light);
+    }
+
+    /// Return the number of lights.
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    /// Return whether there are no lights.
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Probability of sampling the light at `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn probability(&self, index: usize) -> f64 {
+        let below = if index == 0 {
+            0.0
+        } else {
+            self.cumulative[index - 1]
+        };
+        (self.cumulative[index] - below) * self.total_power().recip()
+    }
+
+    /// Sum of every light's power.
+    fn total_power(&self) -> f64 {
+        self.cumulative.last().copied().unwrap_or(0.0)
+    }
+}
+
+impl Hittable for LightList {
+    fn hit(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        self.lights.hit(r, t_min, t_max, rec)
+    }
+
+    fn occluded(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64) -> bool {
+        self.lights.occluded(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64, output_box: &mut crate::aabb::Aabb) -> bool {
+        self.lights.bounding_box(t0, t1, output_box)
+    }
+
+    fn pdf_value(&self, o: &Point3, v: &Vec3) -> f64 {
+        if self.total_power() <= 0.0 {
+            return 0.0;
+        }
+
+        (0..self.len())
+            .filter(|&i| self.probability(i) > 0.0)
+            .map(|i| self.probability(i) * self.lights[i].pdf_value(o, v))
+            .sum()
+    }
+
+    fn random(&self, rng: &mut crate::rng::RenderRng, origin: &Vec3) -> Vec3 {
+        let total = self.total_power();
+        if total <= 0.0 {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+
+        // First light whose running total passes the drawn power, which skips
+        // lights with no power as their totals equal the one before
+        let target = rng.gen::<f64>() * total;
+        let index = self
+            .cumulative
+            .partition_point(|&sum| sum <= target)
+            .min(self.len() - 1);
+        self.lights[index].random(rng, origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aarect::{AaRect, Plane};
+    use crate::hittable::{Disk, Sphere};
+    use crate::material::{DiffuseLight, Material};
+    use crate::texture::SolidColor;
+
+    fn light(color: Color) -> Arc<Material> {
+        Arc::new(Material::DiffLight(DiffuseLight::new(Arc::new(
+            SolidColor::from_color(color),
+        ))))
+    }
+
+    #[test]
+    fn lights_work_out_their_own_power() {
+        let bright = Color::new_with(15.0);
+        let dim = Color::new(2.0, 1.6, 1.0);
+        let rect = AaRect::new(0.0, 2.0, 0.0, 3.0, 1.0, light(bright), Plane::Xz);
+        let disk = Disk::new(Point3::default(), Vec3::new(0.0, 1.0, 0.0), 1.0, light(dim));
+
+        let mut derived = LightList::new();
+        derived.add(Arc::new(rect.clone())).unwrap();
+        derived.add(Arc::new(disk.clone())).unwrap();
+        let mut manual = LightList::new();
+        manual.add_with_power(Arc::new(rect.clone()), rect.area(), bright);
+        manual.add_with_power(Arc::new(disk.clone()), disk.area(), dim);
+        for i in 0..2 {
+            assert!((derived.probability(i) - manual.probability(i)).abs() < 1e-12);
+        }
+
+        let sphere = Sphere::new_shared(Point3::default(), 1.0, light(bright));
+        assert_eq!(
+            derived.add(Arc::new(sphere)),
+            Err(LightListError::UnknownPower(2))
+        );
+        assert_eq!(derived.len(), 2);
+    }
+}
@@ -40,34 +40,94 @@ impl HittableList {
     pub fn clear(&mut self) {
         self.objects.clear()
     }
+
+    /// Return the number of objects in the `HittableList`.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Return whether the `HittableList` has no objects.
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Iterate over the objects in the `HittableList`.
+    pub fn iter(&self) -> core::slice::Iter<'_, Arc<dyn Hittable + Send + Sync>> {
+        self.objects.iter()
+    }
+
+    /// Remove and return the object at `index`, shifting later objects down.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Arc<dyn Hittable + Send + Sync> {
+        self.objects.remove(index)
+    }
+
+    /// Box around every object over the time interval `t0..t1`, or `None` if
+    /// the list is empty or any object is unbounded.
+    pub fn scene_bounds(&self, t0: f64, t1: f64) -> Option<crate::aabb::Aabb> {
+        let mut bounds = crate::aabb::Aabb::default();
+        if self.bounding_box(t0, t1, &mut bounds) {
+            Some(bounds)
+        } else {
+            None
+        }
+    }
+}
+
+impl Extend<Arc<dyn Hittable + Send + Sync>> for HittableList {
+    fn extend<I: IntoIterator<Item = Arc<dyn Hittable + Send + Sync>>>(&mut self, iter: I) {
+        self.objects.extend(iter)
+    }
+}
+
+impl core::ops::Index<usize> for HittableList {
+    type Output = Arc<dyn Hittable + Send + Sync>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.objects[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a HittableList {
+    type Item = &'a Arc<dyn Hittable + Send + Sync>;
+    type IntoIter = core::slice::Iter<'a, Arc<dyn Hittable + Send + Sync>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.objects.iter()
+    }
 }
 
 impl Hittable for HittableList {
     fn hit(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
-        let mut temp_rec = HitRecord::default();
         let mut hit_anything = false;
         let mut closest_so_far = t_max;
 
-        for o in self.objects.iter() {
-            if o.hit(r, t_min, closest_so_far, &mut temp_rec) {
+        // Objects only write to `rec` when they're hit closer than any before
+        // them, so it can be filled in place without a temporary record
+        for o in self {
+            if o.hit(r, t_min, closest_so_far, rec) {
                 hit_anything = true;
-                closest_so_far = temp_rec.t;
-                *rec = temp_rec.clone();
+                closest_so_far = rec.t;
             }
         }
 
         hit_anything
     }
 
+    fn occluded(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64) -> bool {
+        self.iter().any(|o| o.occluded(r, t_min, t_max))
+    }
+
     fn bounding_box(&self, t0: f64, t1: f64, output_box: &mut crate::aabb::Aabb) -> bool {
-        if self.objects.is_empty() {
+        if self.is_empty() {
             return false;
         }
 
         let mut temp_box = crate::aabb::Aabb::default();
         let mut first_box = true;
 
-        for object in self.objects.iter() {
+        for object in self {
             if !(object.bounding_box(t0, t1, &mut temp_box)) {
                 return false;
             }
@@ -12,27 +12,111 @@ pub struct Sphere {
     pub radius: f64,
     /// Material of sphere.
     pub material: std::sync::Arc<Material>,
+    /// Axis through the poles of the texture, where `v` is `0.0` and `1.0`.
+    pub pole: PoleAxis,
+    /// Degrees the texture is spun around `pole`, added to `u` as a fraction
+    /// of a turn.
+    pub longitude: f64,
 }
 
 impl Sphere {
     /// Create a new sphere.
     pub fn new(center: Point3, radius: f64, material: Material) -> Self {
+        Self::new_shared(center, radius, std::sync::Arc::new(material))
+    }
+
+    /// Create a new sphere with a material that can be shared between objects.
+    pub fn new_shared(center: Point3, radius: f64, material: std::sync::Arc<Material>) -> Self {
         Self {
             center,
             radius,
-            material: std::sync::Arc::new(material),
+            material,
+            pole: PoleAxis::Y,
+            longitude: 0.0,
+        }
+    }
+
+    /// Orient the texture with its poles along `pole` and spun `longitude`
+    /// degrees around it, such as to turn a globe to a given continent.
+    ///
+    /// The mapping is the one of [`get_sphere_uv`] with the axes cycled to
+    /// bring `pole` up to Y, so with the pole along X, `u` starts from -Z,
+    /// and along Z, from -Y.
+    pub fn with_uv_orientation(self, pole: PoleAxis, longitude: f64) -> Self {
+        Self {
+            pole,
+            longitude,
+            ..self
+        }
+    }
+
+    /// Texture coordinates at `p`, a point on the unit sphere around the
+    /// center.
+    fn uv(&self, p: &Vec3) -> (f64, f64) {
+        let (mut u, mut v) = (0.0, 0.0);
+        get_sphere_uv(&to_pole_frame(p, self.pole), &mut u, &mut v);
+        if self.longitude != 0.0 {
+            u = (u + self.longitude / 360.0).rem_euclid(1.0);
         }
+        (u, v)
+    }
+
+    /// Direction that `u` increases in at `p`, relative to the center.
+    fn tangent(&self, p: &Vec3) -> Vec3 {
+        from_pole_frame(&sphere_tangent(&to_pole_frame(p, self.pole)), self.pole)
+    }
+}
+
+/// Axis through the poles of a sphere's texture.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PoleAxis {
+    /// Poles along X.
+    X,
+    /// Poles along Y, the up axis of the cameras in the scenes.
+    #[default]
+    Y,
+    /// Poles along Z.
+    Z,
+}
+
+/// Cycle the axes of `p` so that `pole` becomes Y, keeping them right-handed.
+fn to_pole_frame(p: &Vec3, pole: PoleAxis) -> Vec3 {
+    match pole {
+        PoleAxis::X => Vec3::new(p.z(), p.x(), p.y()),
+        PoleAxis::Y => *p,
+        PoleAxis::Z => Vec3::new(p.y(), p.z(), p.x()),
+    }
+}
+
+/// Undo [`to_pole_frame`].
+fn from_pole_frame(p: &Vec3, pole: PoleAxis) -> Vec3 {
+    match pole {
+        PoleAxis::X => Vec3::new(p.y(), p.z(), p.x()),
+        PoleAxis::Y => *p,
+        PoleAxis::Z => Vec3::new(p.z(), p.x(), p.y()),
     }
 }
 
 /// Utitilfy function for calculating the texture coordinates of a sphere.
+///
+/// `p` is a point on the unit sphere, measured from the center independent of
+/// the sign of the radius so hollow spheres are textured the same way. `v`
+/// runs from `0.0` at the bottom pole to `1.0` at the top, and `u` wraps around
+/// the Y axis starting from -X.
 pub fn get_sphere_uv(p: &Vec3, u: &mut f64, v: &mut f64) {
     let phi = p.z().atan2(p.x());
-    let theta = p.y().asin();
+    // Rounding can push points at the poles just past the unit sphere
+    let theta = p.y().clamp(-1.0, 1.0).asin();
     *u = 1.0 - (phi + PI) * TWO_PI.recip();
     *v = (theta + core::f64::consts::FRAC_PI_2) * PI.recip();
 }
 
+/// Direction that `u` increases in around a sphere at the point `p` relative
+/// to its center, for [`get_sphere_uv`]'s mapping. Zero at the poles.
+pub fn sphere_tangent(p: &Vec3) -> Vec3 {
+    Vec3::new(p.z(), 0.0, -p.x())
+}
+
 impl Hittable for Sphere {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
         let oc: Vec3 = r.origin() - self.center;
@@ -48,13 +132,11 @@ impl Hittable for Sphere {
                 rec.t = temp;
                 rec.p = r.at(rec.t);
                 let outward_normal: Vec3 = (rec.p - self.center) / self.radius;
-                get_sphere_uv(
-                    &((rec.p - self.center) * self.radius.recip()),
-                    &mut rec.u,
-                    &mut rec.v,
-                );
+                (rec.u, rec.v) = self.uv(&(rec.p - self.center).unit_vector());
                 rec.set_face_normal(r, &outward_normal);
+                rec.set_tangent(&self.tangent(&outward_normal));
                 rec.material = self.material.clone();
+                rec.set_object(self);
                 return true;
             }
             let temp = (-half_b + root) / a;
@@ -62,13 +144,11 @@ impl Hittable for Sphere {
                 rec.t = temp;
                 rec.p = r.at(rec.t);
                 let outward_normal: Vec3 = (rec.p - self.center) / self.radius;
-                get_sphere_uv(
-                    &((rec.p - self.center) * self.radius.recip()),
-                    &mut rec.u,
-                    &mut rec.v,
-                );
+                (rec.u, rec.v) = self.uv(&(rec.p - self.center).unit_vector());
                 rec.set_face_normal(r, &outward_normal);
+                rec.set_tangent(&self.tangent(&outward_normal));
                 rec.material = self.material.clone();
+                rec.set_object(self);
                 return true;
             }
         }
@@ -76,6 +156,21 @@ impl Hittable for Sphere {
         false
     }
 
+    fn occluded(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        let oc: Vec3 = r.origin() - self.center;
+        let a = r.direction().length_squared();
+        let half_b = oc.dot(&r.direction());
+        let c = oc.length_squared() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant <= 0.0 {
+            return false;
+        }
+
+        let root = discriminant.sqrt();
+        let within = |t: f64| t < t_max && t > t_min;
+        within((-half_b - root) / a) || within((-half_b + root) / a)
+    }
+
     fn bounding_box(&self, _t0: f64, _t1: f64, output_box: &mut crate::aabb::Aabb) -> bool {
         *output_box = crate::aabb::Aabb {
             min: self.center - Vec3::new_with(self.radius),
@@ -83,9 +178,39 @@ impl Hittable for Sphere {
         };
         true
     }
+    fn hit_interval(&self, r: &Ray) -> Option<(HitRecord, HitRecord)> {
+        let oc: Vec3 = r.origin() - self.center;
+        let a = r.direction().length_squared();
+        let half_b = oc.dot(&r.direction());
+        let c = oc.length_squared() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+
+        if discriminant <= 0.0 {
+            return None;
+        }
+
+        let root = discriminant.sqrt();
+        let record = |t: f64| {
+            let mut rec = HitRecord {
+                t,
+                p: r.at(t),
+                material: self.material.clone(),
+                ..HitRecord::default()
+            };
+            rec.set_object(self);
+            let outward_normal: Vec3 = (rec.p - self.center) / self.radius;
+            (rec.u, rec.v) = self.uv(&(rec.p - self.center).unit_vector());
+            rec.set_face_normal(r, &outward_normal);
+            rec.set_tangent(&self.tangent(&outward_normal));
+            rec
+        };
+
+        Some((record((-half_b - root) / a), record((-half_b + root) / a)))
+    }
     fn pdf_value(&self, o: &Point3, v: &Vec3) -> f64 {
         let mut rec = HitRecord::default();
-        if !self.hit(&Ray::new(*o, *v, 0.0), 0.001, f64::INFINITY, &mut rec) {
+        let r = Ray::new(*o, *v, 0.0);
+        if !self.hit(&r, r.step_past(0.0), f64::INFINITY, &mut rec) {
             return 0.0;
         }
 
@@ -94,7 +219,7 @@ impl Hittable for Sphere {
 
         (TWO_PI * (1.0 - cos_theta_max)).recip()
     }
-    fn random(&self, rng: &mut rand::prelude::ThreadRng, origin: &Vec3) -> Vec3 {
+    fn random(&self, rng: &mut crate::rng::RenderRng, origin: &Vec3) -> Vec3 {
         let direction = self.center - *origin;
         let distance_squared = direction.length_squared();
         let uvw = crate::onb::Onb::build_from_w(&direction);
@@ -165,8 +290,15 @@ impl Hittable for MovingSphere {
                 rec.t = temp;
                 rec.p = r.at(rec.t);
                 let outward_normal: Vec3 = (rec.p - self.center(r.time())) / self.radius;
+                get_sphere_uv(
+                    &(rec.p - self.center(r.time())).unit_vector(),
+                    &mut rec.u,
+                    &mut rec.v,
+                );
                 rec.set_face_normal(r, &outward_normal);
+                rec.set_tangent(&sphere_tangent(&outward_normal));
                 rec.material = self.material.clone();
+                rec.set_object(self);
                 return true;
             }
             let temp = (-half_b + root) / a;
@@ -174,8 +306,15 @@ impl Hittable for MovingSphere {
                 rec.t = temp;
                 rec.p = r.at(rec.t);
                 let outward_normal: Vec3 = (rec.p - self.center(r.time())) / self.radius;
+                get_sphere_uv(
+                    &(rec.p - self.center(r.time())).unit_vector(),
+                    &mut rec.u,
+                    &mut rec.v,
+                );
                 rec.set_face_normal(r, &outward_normal);
+                rec.set_tangent(&sphere_tangent(&outward_normal));
                 rec.material = self.material.clone();
+                rec.set_object(self);
                 return true;
             }
         }
@@ -196,3 +335,79 @@ impl Hittable for MovingSphere {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tangent_is_perpendicular_to_the_normal() {
+        let sphere = Sphere::new(Point3::new(1.0, 2.0, 3.0), 2.0, Material::default());
+        // Rays towards the center from around the sphere, the poles included
+        for i in 0..8 {
+            for j in 0..=8 {
+                let phi = f64::from(i) * TWO_PI / 8.0;
+                let theta = f64::from(j) * PI / 8.0;
+                let d = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                let r = Ray::new(sphere.center + 5.0 * d, -d, 0.0);
+
+                let mut rec = HitRecord::default();
+                assert!(sphere.hit(&r, 0.001, f64::INFINITY, &mut rec));
+                assert!((rec.tangent.length() - 1.0).abs() < 1e-9);
+                assert!(
+                    rec.tangent.dot(&rec.normal).abs() < 1e-9,
+                    "tangent {:?} against normal {:?}",
+                    rec.tangent,
+                    rec.normal
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn half_turn_longitude_maps_plus_x_to_the_seam() {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, Material::default())
+            .with_uv_orientation(PoleAxis::Y, 180.0);
+        let r = Ray::new(Point3::new(5.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+        let mut rec = HitRecord::default();
+        assert!(sphere.hit(&r, 0.001, f64::INFINITY, &mut rec));
+        // Either side of the seam
+        assert!(rec.u.min(1.0 - rec.u) < 1e-9, "u = {}", rec.u);
+        assert!((rec.v - 0.5).abs() < 1e-9, "v = {}", rec.v);
+    }
+
+    #[test]
+    fn plus_x_equator_is_the_middle_of_the_texture() {
+        let mut uv = (0.0, 0.0);
+        get_sphere_uv(&Vec3::new(1.0, 0.0, 0.0), &mut uv.0, &mut uv.1);
+        assert!(
+            (uv.0 - 0.5).abs() < 1e-9 && (uv.1 - 0.5).abs() < 1e-9,
+            "{:?}",
+            uv
+        );
+
+        // Hit from outside, whatever the size or sign of the radius
+        for &radius in &[1.0, 2.5, -1.0] {
+            let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), radius, Material::default());
+            let r = Ray::new(Point3::new(5.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+            let mut rec = HitRecord::default();
+            assert!(sphere.hit(&r, 0.001, f64::INFINITY, &mut rec));
+            assert!(
+                (rec.u - 0.5).abs() < 1e-9,
+                "u = {} at radius {}",
+                rec.u,
+                radius
+            );
+            assert!(
+                (rec.v - 0.5).abs() < 1e-9,
+                "v = {} at radius {}",
+                rec.v,
+                radius
+            );
+        }
+    }
+}
@@ -39,6 +39,11 @@ impl Hittable for Translate {
         true
     }
 
+    fn occluded(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64) -> bool {
+        let moved_r = crate::ray::Ray::new(r.origin() - self.offset, r.direction(), r.time());
+        self.pointer.occluded(&moved_r, t_min, t_max)
+    }
+
     fn bounding_box(
         &self,
         t0: std::primitive::f64,
@@ -55,6 +60,18 @@ impl Hittable for Translate {
         );
         true
     }
+
+    fn pdf_value(&self, o: &crate::vec3::Point3, v: &crate::vec3::Vec3) -> f64 {
+        self.pointer.pdf_value(&(*o - self.offset), v)
+    }
+
+    fn random(
+        &self,
+        rng: &mut crate::rng::RenderRng,
+        origin: &crate::vec3::Vec3,
+    ) -> crate::vec3::Vec3 {
+        self.pointer.random(rng, &(*origin - self.offset))
+    }
 }
 
 /// Object for allowing rotation of `Hittable`s.
@@ -98,14 +115,18 @@ impl RotateY {
                     let newx = cos_theta * x + sin_theta * z;
                     let newz = -sin_theta * x + cos_theta * z;
 
-                    let tester = crate::vec3::Vec3(newx, y, newz);
+                    let tester = crate::vec3::Vec3::new(newx, y, newz);
 
-                    min.0 = min.0.min(tester.0);
-                    max.0 = max.0.max(tester.0);
-                    min.1 = min.1.min(tester.1);
-                    max.1 = max.1.max(tester.1);
-                    min.2 = min.2.min(tester.2);
-                    max.2 = max.2.max(tester.2);
+                    min = crate::vec3::Vec3::new(
+                        min.x().min(tester.x()),
+                        min.y().min(tester.y()),
+                        min.z().min(tester.z()),
+                    );
+                    max = crate::vec3::Vec3::new(
+                        max.x().max(tester.x()),
+                        max.y().max(tester.y()),
+                        max.z().max(tester.z()),
+                    );
                 }
             }
         }
@@ -118,6 +139,24 @@ impl RotateY {
             bbox: crate::aabb::Aabb::new(&min, &max),
         }
     }
+
+    /// Rotate a point or direction from world space into the object's space.
+    fn to_object(&self, v: crate::vec3::Vec3) -> crate::vec3::Vec3 {
+        crate::vec3::Vec3::new(
+            self.cos_theta * v.x() - self.sin_theta * v.z(),
+            v.y(),
+            self.sin_theta * v.x() + self.cos_theta * v.z(),
+        )
+    }
+
+    /// Rotate a point or direction from the object's space into world space.
+    fn to_world(&self, v: crate::vec3::Vec3) -> crate::vec3::Vec3 {
+        crate::vec3::Vec3::new(
+            self.cos_theta * v.x() + self.sin_theta * v.z(),
+            v.y(),
+            -self.sin_theta * v.x() + self.cos_theta * v.z(),
+        )
+    }
 }
 
 impl Hittable for RotateY {
@@ -128,35 +167,32 @@ impl Hittable for RotateY {
         t_max: std::primitive::f64,
         rec: &mut super::HitRecord,
     ) -> std::primitive::bool {
-        let mut origin = r.origin();
-        let mut direction = r.direction();
-
-        origin.0 = self.cos_theta * r.origin().x() - self.sin_theta * r.origin().z();
-        origin.2 = self.sin_theta * r.origin().x() + self.cos_theta * r.origin().z();
-
-        direction.0 = self.cos_theta * r.direction().x() - self.sin_theta * r.direction().z();
-        direction.2 = self.sin_theta * r.direction().x() + self.cos_theta * r.direction().z();
-
-        let rotated_r = crate::ray::Ray::new(origin, direction, r.time());
+        let rotated_r = crate::ray::Ray::new(
+            self.to_object(r.origin()),
+            self.to_object(r.direction()),
+            r.time(),
+        );
 
         if !self.pointer.hit(&rotated_r, t_min, t_max, rec) {
             return false;
         }
 
-        let mut p = rec.p;
-        let mut normal = rec.normal;
-
-        p.0 = self.cos_theta * rec.p.x() + self.sin_theta * rec.p.z();
-        p.2 = -self.sin_theta * rec.p.x() + self.cos_theta * rec.p.z();
-
-        normal.0 = self.cos_theta * rec.normal.x() + self.sin_theta * rec.normal.z();
-        normal.2 = -self.sin_theta * rec.normal.x() + self.cos_theta * rec.normal.z();
-
-        rec.p = p;
+        let normal = self.to_world(rec.normal);
+        rec.p = self.to_world(rec.p);
         rec.set_face_normal(&rotated_r, &normal);
+        rec.tangent = self.to_world(rec.tangent);
 
         true
     }
+
+    fn occluded(&self, r: &crate::ray::Ray, t_min: f64, t_max: f64) -> bool {
+        let rotated_r = crate::ray::Ray::new(
+            self.to_object(r.origin()),
+            self.to_object(r.direction()),
+            r.time(),
+        );
+        self.pointer.occluded(&rotated_r, t_min, t_max)
+    }
     fn bounding_box(
         &self,
         _t0: std::primitive::f64,
@@ -166,4 +202,73 @@ impl Hittable for RotateY {
         *output_box = self.bbox;
         self.has_box
     }
+
+    fn pdf_value(&self, o: &crate::vec3::Point3, v: &crate::vec3::Vec3) -> f64 {
+        self.pointer
+            .pdf_value(&self.to_object(*o), &self.to_object(*v))
+    }
+
+    fn random(
+        &self,
+        rng: &mut crate::rng::RenderRng,
+        origin: &crate::vec3::Vec3,
+    ) -> crate::vec3::Vec3 {
+        self.to_world(self.pointer.random(rng, &self.to_object(*origin)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aarect::{AaRect, Plane};
+    use crate::hittable::flip_face::FlipFace;
+    use crate::material::Material;
+    use crate::vec3::{Point3, Vec3};
+
+    #[test]
+    fn translated_light_pdf_integrates_to_one() {
+        // A ceiling light like the Cornell box's, moved up and to the side
+        let rect = AaRect::new(
+            -0.5,
+            0.5,
+            -0.5,
+            0.5,
+            0.0,
+            Arc::new(Material::default()),
+            Plane::Xz,
+        );
+        let light = Translate::new(
+            Arc::new(FlipFace::new(Arc::new(rect))),
+            Vec3::new(0.75, 2.0, -0.25),
+        );
+        let origin = Point3::new(0.0, 0.0, 0.0);
+
+        // Midpoint rule over a grid in the polar and azimuthal angles
+        let (n_theta, n_phi) = (400, 800);
+        let (d_theta, d_phi) = (
+            core::f64::consts::PI / f64::from(n_theta),
+            crate::conversion::TWO_PI / f64::from(n_phi),
+        );
+        let mut total = 0.0;
+        for i in 0..n_theta {
+            let theta = (f64::from(i) + 0.5) * d_theta;
+            for j in 0..n_phi {
+                let phi = (f64::from(j) + 0.5) * d_phi;
+                let direction = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                total += light.pdf_value(&origin, &direction) * theta.sin() * d_theta * d_phi;
+            }
+        }
+        assert!((total - 1.0).abs() < 0.02, "{}", total);
+
+        // Sampled directions head for the moved light
+        let rng = &mut crate::rng::pixel_rng(1, 0);
+        for _ in 0..100 {
+            let direction = light.random(rng, &origin);
+            assert!(light.pdf_value(&origin, &direction) > 0.0);
+        }
+    }
 }
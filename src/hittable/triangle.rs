@@ -0,0 +1,243 @@
+//! Triangle primitive, the building block for meshes and terrain.
+
+use std::sync::Arc;
+
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// Triangle object.
+#[derive(Clone)]
+pub struct Triangle {
+    /// Corners of the triangle, wound counter-clockwise around the front face.
+    pub vertices: [Point3; 3],
+    /// Texture coordinates at each corner.
+    pub uvs: [(f64, f64); 3],
+    /// Shading normals at each corner, interpolated across the face for smooth
+    /// shading. The face is shaded flat when they are absent.
+    pub normals: Option<[Vec3; 3]>,
+    /// Material of the triangle.
+    pub material: Arc<Material>,
+    /// Padding for the bounding box in dimensions where the triangle is flat.
+    pub padding: f64,
+    /// Identity shared with the other triangles of a mesh, so a closed mesh
+    /// counts as one object, see [`HitRecord::object`]. The triangle is an
+    /// object of its own without it.
+    pub object: Option<usize>,
+}
+
+impl Triangle {
+    /// Create a new triangle with texture coordinates spanning the unit square.
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, material: Arc<Material>) -> Self {
+        Self::with_uvs(v0, v1, v2, [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)], material)
+    }
+
+    /// Create a new triangle with texture coordinates for each corner.
+    pub fn with_uvs(
+        v0: Point3,
+        v1: Point3,
+        v2: Point3,
+        uvs: [(f64, f64); 3],
+        material: Arc<Material>,
+    ) -> Self {
+        Self {
+            vertices: [v0, v1, v2],
+            uvs,
+            normals: None,
+            material,
+            padding: crate::aabb::PADDING,
+            object: None,
+        }
+    }
+
+    /// Shade the triangle smoothly by interpolating a normal at each corner.
+    ///
+    /// The normals are flipped as needed to face the incoming ray, so they
+    /// may point either way. They only change the shading, so silhouettes of a
+    /// mesh stay faceted.
+    pub fn with_vertex_normals(self, normals: [Vec3; 3]) -> Self {
+        Self {
+            normals: Some(normals),
+            ..self
+        }
+    }
+
+    /// Set the padding for the bounding box in dimensions where the triangle
+    /// is flat, see [`Aabb::padded`](crate::aabb::Aabb::padded).
+    pub fn with_padding(self, padding: f64) -> Self {
+        Self { padding, ..self }
+    }
+
+    /// Set the identity shared with the other triangles of a mesh.
+    pub fn with_object(self, object: usize) -> Self {
+        Self {
+            object: Some(object),
+            ..self
+        }
+    }
+
+    /// Direction that `u` increases in across the triangle, or zero when the
+    /// texture coordinates don't span it.
+    fn dp_du(&self, edge1: Vec3, edge2: Vec3) -> Vec3 {
+        let [uv0, uv1, uv2] = self.uvs;
+        let (du1, dv1) = (uv1.0 - uv0.0, uv1.1 - uv0.1);
+        let (du2, dv2) = (uv2.0 - uv0.0, uv2.1 - uv0.1);
+        let det = du1 * dv2 - du2 * dv1;
+        if det.abs() < f64::EPSILON {
+            return Vec3::default();
+        }
+
+        (edge1 * dv2 - edge2 * dv1) / det
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        // Möller-Trumbore intersection
+        let [v0, v1, v2] = self.vertices;
+        let edge1 = v1 - v0;
+        let edge2 = v2 - v0;
+        let pvec = r.direction().cross(&edge2);
+        let det = edge1.dot(&pvec);
+        if det.abs() < f64::EPSILON {
+            return false;
+        }
+
+        let inv_det = det.recip();
+        let tvec = r.origin() - v0;
+        let b1 = tvec.dot(&pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return false;
+        }
+
+        let qvec = tvec.cross(&edge1);
+        let b2 = r.direction().dot(&qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return false;
+        }
+
+        let t = edge2.dot(&qvec) * inv_det;
+        if t < t_min || t > t_max {
+            return false;
+        }
+
+        let b0 = 1.0 - b1 - b2;
+        let u = b0 * self.uvs[0].0 + b1 * self.uvs[1].0 + b2 * self.uvs[2].0;
+        let v = b0 * self.uvs[0].1 + b1 * self.uvs[1].1 + b2 * self.uvs[2].1;
+        let p = r.at(t);
+        if self.material.is_cut_out(u, v, &p) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = p;
+        rec.u = u;
+        rec.v = v;
+        let outward_normal: Vec3 = edge1.cross(&edge2);
+        rec.set_face_normal(r, &outward_normal);
+        if let Some([n0, n1, n2]) = self.normals {
+            // Keep the side of the surface decided by the geometric normal
+            let shading_normal = (b0 * n0 + b1 * n1 + b2 * n2).unit_vector();
+            rec.normal = if shading_normal.dot(&rec.normal) < 0.0 {
+                -shading_normal
+            } else {
+                shading_normal
+            };
+        }
+        rec.set_tangent(&self.dp_du(edge1, edge2));
+        rec.material = self.material.clone();
+        match self.object {
+            Some(object) => rec.object = object,
+            None => rec.set_object(self),
+        }
+
+        true
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64, output_box: &mut crate::aabb::Aabb) -> bool {
+        let [v0, v1, v2] = self.vertices;
+        let min = Point3::new(
+            v0.x().min(v1.x()).min(v2.x()),
+            v0.y().min(v1.y()).min(v2.y()),
+            v0.z().min(v1.z()).min(v2.z()),
+        );
+        let max = Point3::new(
+            v0.x().max(v1.x()).max(v2.x()),
+            v0.y().max(v1.y()).max(v2.y()),
+            v0.z().max(v1.z()).max(v2.z()),
+        );
+
+        *output_box = crate::aabb::Aabb::padded(&min, &max, self.padding);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::HittableList;
+
+    /// A coarse unit sphere, smoothed with normals pointing out of the
+    /// center or shaded flat.
+    fn sphere_mesh(smooth: bool) -> HittableList {
+        let (rows, columns) = (8, 16);
+        let corner = |i: u32, j: u32| {
+            let theta = core::f64::consts::PI * f64::from(i) / f64::from(rows);
+            let phi = crate::conversion::TWO_PI * f64::from(j) / f64::from(columns);
+            Point3::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            )
+        };
+
+        let mut mesh = HittableList::new();
+        for i in 0..rows {
+            for j in 0..columns {
+                let quad = [
+                    corner(i, j),
+                    corner(i + 1, j),
+                    corner(i + 1, j + 1),
+                    corner(i, j + 1),
+                ];
+                for &[a, b, c] in &[[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]] {
+                    // Quads touching the poles lose a corner
+                    if (b - a).cross(&(c - a)).length_squared() < 1e-12 {
+                        continue;
+                    }
+                    let triangle = Triangle::new(a, b, c, Arc::new(Material::default()));
+                    mesh.add(Arc::new(if smooth {
+                        triangle.with_vertex_normals([a, b, c])
+                    } else {
+                        triangle
+                    }));
+                }
+            }
+        }
+        mesh
+    }
+
+    /// Mean angle between the shading normal and the true sphere normal over
+    /// rays aimed at the center from all around.
+    fn mean_normal_error(mesh: &HittableList) -> f64 {
+        let rng = &mut crate::rng::pixel_rng(1, 0);
+        let samples = 1000;
+        let mut total = 0.0;
+        for _ in 0..samples {
+            let d = Vec3::random_unit_vector(rng);
+            let r = Ray::new(3.0 * d, -d, 0.0);
+            let mut rec = HitRecord::default();
+            assert!(mesh.hit(&r, 0.001, f64::INFINITY, &mut rec));
+            total += rec.normal.dot(&rec.p.unit_vector()).clamp(-1.0, 1.0).acos();
+        }
+        total / f64::from(samples)
+    }
+
+    #[test]
+    fn smoothed_sphere_mesh_follows_the_sphere() {
+        let flat = mean_normal_error(&sphere_mesh(false));
+        let smooth = mean_normal_error(&sphere_mesh(true));
+        assert!(smooth < 0.25 * flat, "smooth {} flat {}", smooth, flat);
+    }
+}
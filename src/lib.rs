@@ -4,16 +4,27 @@
 
 pub mod aabb;
 pub mod aarect;
+pub mod bench;
 pub mod bvh;
 pub mod camera;
 pub mod conversion;
+pub mod environment;
 pub mod hittable;
+pub mod light;
 pub mod material;
+pub mod mesh;
 pub mod onb;
 pub mod pdf;
 pub mod perlin;
+#[cfg(feature = "preview")]
+pub mod preview;
 pub mod ray;
 pub mod render;
+pub mod rng;
+pub mod sampler;
 pub mod scene;
+#[cfg(feature = "sky")]
+pub mod sky;
 pub mod texture;
 pub mod vec3;
+pub mod world;
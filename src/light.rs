@@ -0,0 +1,72 @@
+//! Point-like lights that aren't part of the geometry and can only be reached
+//! by sampling them directly.
+
+use crate::vec3::{Color, Point3, Vec3};
+
+/// Light shining from a point in a cone around `direction`.
+///
+/// It's a delta light that rays can never hit, so it only lights diffuse
+/// surfaces through next-event estimation in `ray_color`.
+#[derive(Clone, Copy, Debug)]
+pub struct SpotLight {
+    /// Point the light shines from.
+    pub position: Point3,
+    /// Axis of the cone of light.
+    pub direction: Vec3,
+    /// Color of the light.
+    pub color: Color,
+    /// Angle from the axis in degrees within which the light is at full
+    /// strength.
+    pub inner_angle: f64,
+    /// Angle from the axis in degrees past which there is no light.
+    pub outer_angle: f64,
+    /// Strength of the light one unit away along the axis.
+    pub intensity: f64,
+}
+
+impl SpotLight {
+    /// Create a new spotlight.
+    pub fn new(
+        position: Point3,
+        direction: Vec3,
+        color: Color,
+        inner_angle: f64,
+        outer_angle: f64,
+        intensity: f64,
+    ) -> Self {
+        Self {
+            position,
+            direction,
+            color,
+            inner_angle,
+            outer_angle,
+            intensity,
+        }
+    }
+
+    /// Light arriving at `p` from the spotlight, ignoring anything in between.
+    ///
+    /// The cone fades smoothly between the inner and outer angles, and the
+    /// light falls off with the square of the distance.
+    pub fn incident(&self, p: &Point3) -> Color {
+        let to_p = *p - self.position;
+        let distance_squared = to_p.length_squared();
+        let cos_angle = to_p.unit_vector().dot(&self.direction.unit_vector());
+
+        let cos_outer = self.outer_angle.to_radians().cos();
+        let cos_inner = self.inner_angle.to_radians().cos();
+        let cone = if cos_inner <= cos_outer {
+            // Hard edged cone
+            if cos_angle >= cos_outer {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            let x = ((cos_angle - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0);
+            x * x * (3.0 - 2.0 * x)
+        };
+
+        self.color * (self.intensity * cone * distance_squared.recip())
+    }
+}
@@ -3,9 +3,10 @@
 use std::sync::Arc;
 
 use crate::hittable::HitRecord;
+use crate::pdf::Pdf;
 use crate::ray::Ray;
 use crate::texture::{SolidColor, Texture};
-use crate::vec3::{Color, Vec3};
+use crate::vec3::{Color, Point3, Vec3};
 
 /// Type of material.
 #[derive(Clone)]
@@ -20,8 +21,48 @@ pub enum Material {
     DiffLight(DiffuseLight),
     /// Isotropic material.
     Iso(Isotropic),
+    /// Another material with its normals perturbed by a height texture.
+    Bumped(BumpMap),
+    /// Diffuse material with a glossy Phong highlight.
+    Phong(Phong),
+    /// Thin diffuse material which lets some light through to the far side.
+    Translucent(Translucent),
 }
 
+/// Material parameters rejected by the checked constructors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaterialError {
+    /// A refraction index which isn't positive and finite.
+    RefractionIndex(f64),
+    /// A fuzz factor which isn't finite and non-negative.
+    Fuzz(f64),
+    /// A light intensity which isn't finite and non-negative.
+    Intensity(f64),
+    /// A light spread power which isn't finite and non-negative.
+    SpreadPower(f64),
+}
+
+impl core::fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MaterialError::RefractionIndex(n) => {
+                write!(f, "refraction index {} must be positive and finite", n)
+            }
+            MaterialError::Fuzz(fuzz) => {
+                write!(f, "fuzz {} must be non-negative and finite", fuzz)
+            }
+            MaterialError::Intensity(intensity) => {
+                write!(f, "intensity {} must be non-negative and finite", intensity)
+            }
+            MaterialError::SpreadPower(power) => {
+                write!(f, "spread power {} must be non-negative and finite", power)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MaterialError {}
+
 impl core::default::Default for Material {
     fn default() -> Self {
         Material::Lambertian(Lambert::default())
@@ -29,7 +70,7 @@ impl core::default::Default for Material {
 }
 
 /// Struct that holds whether a material has speculars.
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct ScatterRecord {
     /// The ray of the specular reflection.
     pub specular_ray: Option<Ray>,
@@ -37,9 +78,34 @@ pub struct ScatterRecord {
     pub attenuation: Color,
     /// The PDF of the material.
     pub pdf_ptr: Option<Arc<dyn crate::pdf::Pdf + Send + Sync>>,
+    /// Rough angle in radians that scattered rays stray from the center of
+    /// the material's lobe, for filtering what they see.
+    pub spread: f64,
 }
 
 impl ScatterRecord {
+    /// Panic in debug builds if the attenuation isn't finite and non-negative,
+    /// or over `1.0` when `bounded`, so that it would add energy, or if the
+    /// specular ray's direction isn't finite. Does nothing in release builds.
+    pub fn debug_check(&self, bounded: bool) {
+        let a = self.attenuation;
+        debug_assert!(
+            [a.x(), a.y(), a.z()]
+                .iter()
+                .all(|c| c.is_finite() && *c >= 0.0 && (!bounded || *c <= 1.0)),
+            "attenuation {:?} is outside {}",
+            a,
+            if bounded { "[0, 1]" } else { "[0, inf)" }
+        );
+        if let Some(ray) = &self.specular_ray {
+            debug_assert!(
+                is_finite(&ray.direction()),
+                "specular direction {:?} isn't finite",
+                ray.direction()
+            );
+        }
+    }
+
     /// Create a new `ScatterRecord`.
     pub fn new(
         specular_ray: Option<Ray>,
@@ -50,93 +116,195 @@ impl ScatterRecord {
             specular_ray,
             attenuation,
             pdf_ptr: Some(pdf_ptr),
-        }
-    }
-}
-
-impl core::default::Default for ScatterRecord {
-    fn default() -> Self {
-        Self {
-            specular_ray: None,
-            attenuation: Color::default(),
-            pdf_ptr: None,
+            spread: 0.0,
         }
     }
 }
 
 impl Material {
     /// Scattering function for how the material affects light.
+    ///
+    /// Debug builds check that the scattered light is finite, can't gain
+    /// energy, and leaves in a finite direction, see
+    /// [`ScatterRecord::debug_check`].
     pub fn scatter<R: rand::Rng>(
         &self,
         rng: &mut R,
         r_in: &Ray,
         rec: &HitRecord,
         srec: &mut ScatterRecord,
+    ) -> bool {
+        let scatters = self.scatter_unchecked(rng, r_in, rec, srec);
+        if scatters {
+            srec.debug_check(self.attenuation_bounded());
+        }
+        scatters
+    }
+
+    /// Whether the material's attenuation stays within `[0, 1]`. Phong
+    /// divides by the chance of picking each lobe, which can take it past
+    /// `1.0` without gaining energy on average.
+    fn attenuation_bounded(&self) -> bool {
+        match self {
+            Material::Phong(_) => false,
+            Material::Bumped(bump) => bump.material.attenuation_bounded(),
+            _ => true,
+        }
+    }
+
+    fn scatter_unchecked<R: rand::Rng>(
+        &self,
+        rng: &mut R,
+        r_in: &Ray,
+        rec: &HitRecord,
+        srec: &mut ScatterRecord,
     ) -> bool {
         match self {
             Material::Lambertian(mat) => {
                 srec.specular_ray = None;
                 srec.attenuation = mat.albedo.value(rec.u, rec.v, &rec.p);
-                srec.pdf_ptr = Some(Arc::new(crate::pdf::CosPdf::new(&rec.normal)));
+                srec.spread = core::f64::consts::FRAC_1_SQRT_2;
+                srec.pdf_ptr = Some(match mat.sampling {
+                    DiffuseSampling::Cosine => Arc::new(crate::pdf::CosPdf::new(&rec.normal)),
+                    DiffuseSampling::UniformHemisphere => {
+                        Arc::new(crate::pdf::HemispherePdf::new(&rec.normal))
+                    }
+                });
 
                 true
             }
             Material::Metallic(mat) => {
                 let reflected = Vec3::reflect(&r_in.direction().unit_vector(), &rec.normal);
-                srec.specular_ray = Some(Ray::new(
-                    rec.p,
-                    reflected + mat.fuzz * Vec3::random_in_unit_sphere(rng),
-                    r_in.time(),
-                ));
-                srec.attenuation = mat.albedo;
+                srec.spread = 0.63 * mat.fuzz_at(rec);
+                if let (MetalSampling::Glossy, None) = (mat.sampling, mat.anisotropic_fuzz) {
+                    let fuzz = mat.fuzz_at(rec);
+                    if fuzz > 0.0 {
+                        srec.specular_ray = None;
+                        srec.attenuation = mat.reflectance_at(rec, &r_in.direction());
+                        srec.pdf_ptr = Some(Arc::new(crate::pdf::PhongPdf::new(
+                            &reflected,
+                            Metal::glossy_exponent(fuzz),
+                        )));
+                        return true;
+                    }
+                }
+
+                let fuzz = match mat.anisotropic_fuzz {
+                    Some((fuzz_u, fuzz_v)) => {
+                        let p = Vec3::random_in_unit_sphere(rng);
+                        crate::onb::Onb::build_from_w(&reflected).local_from(
+                            fuzz_u * p.x(),
+                            fuzz_v * p.y(),
+                            0.0,
+                        )
+                    }
+                    None => mat.fuzz_at(rec) * Vec3::random_in_unit_sphere(rng),
+                };
+                srec.specular_ray = Some(Ray::new(rec.p, reflected + fuzz, r_in.time()));
+                srec.attenuation = mat.reflectance_at(rec, &r_in.direction());
                 true
             }
             Material::Dielectric(ri) => {
-                srec.attenuation = Color::new_with(1.0);
                 let etai_over_etat = if rec.front_face {
                     1.0 * ri.refraction_index.recip()
                 } else {
                     ri.refraction_index
                 };
+                ri.scatter(rng, r_in, rec, etai_over_etat, srec);
+                true
+            }
+            Material::Iso(mat) => {
+                srec.specular_ray = None;
+                srec.attenuation = mat.albedo.value(rec.u, rec.v, &rec.p);
+                srec.spread = core::f64::consts::FRAC_PI_2;
+                srec.pdf_ptr = Some(Arc::new(crate::pdf::SpherePdf));
+                true
+            }
+            Material::Phong(mat) => {
+                // Pick a lobe in proportion to how much light it reflects
+                let diffuse = mat.diffuse.value(rec.u, rec.v, &rec.p);
+                let diffuse_weight = diffuse.x() + diffuse.y() + diffuse.z();
+                let specular_weight = mat.specular.x() + mat.specular.y() + mat.specular.z();
+                if diffuse_weight + specular_weight <= 0.0 {
+                    return false;
+                }
+                let specular_chance = specular_weight / (diffuse_weight + specular_weight);
 
-                let unit_dir = r_in.direction().unit_vector();
-                let cos_theta = (-unit_dir).dot(&rec.normal).min(1.0);
-                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                if rng.gen::<f64>() < specular_chance {
+                    let reflected = Vec3::reflect(&r_in.direction().unit_vector(), &rec.normal);
+                    let direction = crate::onb::Onb::build_from_w(&reflected).local(
+                        &crate::pdf::PhongPdf::random_lobe_direction(rng, mat.shininess),
+                    );
+                    let cosine = direction.dot(&rec.normal);
+                    if cosine <= 0.0 {
+                        return false;
+                    }
 
-                if etai_over_etat * sin_theta > 1.0
-                    || rng.gen::<f64>() < schlick(cos_theta, etai_over_etat)
-                {
-                    let reflected = Vec3::reflect(&unit_dir, &rec.normal);
-                    srec.specular_ray = Some(Ray::new(rec.p, reflected, r_in.time()));
+                    // The normalized lobe over its sampling PDF
+                    srec.specular_ray = Some(Ray::new(rec.p, direction, r_in.time()));
+                    srec.attenuation = mat.specular
+                        * ((mat.shininess + 2.0) / (mat.shininess + 1.0) * cosine
+                            / specular_chance);
+                    srec.spread = (2.0 / (mat.shininess + 3.0)).sqrt();
                 } else {
-                    let refracted = Vec3::refract(&unit_dir, &rec.normal, etai_over_etat);
-                    srec.specular_ray = Some(Ray::new(rec.p, refracted, r_in.time()));
+                    srec.specular_ray = None;
+                    srec.attenuation = diffuse / (1.0 - specular_chance);
+                    srec.spread = core::f64::consts::FRAC_1_SQRT_2;
+                    srec.pdf_ptr = Some(Arc::new(crate::pdf::CosPdf::new(&rec.normal)));
                 }
                 true
             }
-            Material::Iso(mat) => {
-                srec.specular_ray = Some(Ray::new(
-                    rec.p,
-                    Vec3::random_in_unit_sphere(rng),
-                    r_in.time(),
-                ));
+            Material::Translucent(mat) => {
+                srec.specular_ray = None;
                 srec.attenuation = mat.albedo.value(rec.u, rec.v, &rec.p);
+                srec.spread = core::f64::consts::FRAC_1_SQRT_2;
+                srec.pdf_ptr = Some(Arc::new(crate::pdf::TranslucentPdf::new(
+                    &rec.normal,
+                    mat.transmission,
+                )));
                 true
             }
             Material::DiffLight(_) => false,
+            Material::Bumped(bump) => bump.material.scatter(rng, r_in, &bump.perturb(rec), srec),
         }
     }
 
     /// Scattering probability distribution function for importance sampling.
+    ///
+    /// Debug builds check that `scattered` has a finite direction and the
+    /// density is finite and non-negative.
     pub fn scattering_pdf<R: rand::Rng>(
         &self,
-        _rng: &mut R,
-        _r_in: &Ray,
+        rng: &mut R,
+        r_in: &Ray,
+        rec: &HitRecord,
+        scattered: &Ray,
+    ) -> f64 {
+        debug_assert!(
+            is_finite(&scattered.direction()),
+            "scattered direction {:?} isn't finite",
+            scattered.direction()
+        );
+        let pdf = self.scattering_pdf_unchecked(rng, r_in, rec, scattered);
+        debug_assert!(
+            pdf.is_finite() && pdf >= 0.0,
+            "scattering pdf {} isn't finite and non-negative",
+            pdf
+        );
+        pdf
+    }
+
+    fn scattering_pdf_unchecked<R: rand::Rng>(
+        &self,
+        rng: &mut R,
+        r_in: &Ray,
         rec: &HitRecord,
         scattered: &Ray,
     ) -> f64 {
         match self {
-            Material::Lambertian(_mat) => {
+            // Phong highlights are traced like specular bounces, leaving the
+            // diffuse lobe
+            Material::Phong(_) => {
                 let cosine = rec.normal.dot(&scattered.direction().unit_vector());
                 if cosine < 0.0 {
                     0.0
@@ -144,82 +312,520 @@ impl Material {
                     cosine * core::f64::consts::FRAC_1_PI
                 }
             }
-            Material::Metallic(_mat) => todo!(),
+            Material::Lambertian(mat) => {
+                let cosine = rec.normal.dot(&scattered.direction().unit_vector());
+                if cosine < 0.0 {
+                    0.0
+                } else {
+                    match mat.sampling {
+                        DiffuseSampling::Cosine => cosine * core::f64::consts::FRAC_1_PI,
+                        DiffuseSampling::UniformHemisphere => crate::conversion::TWO_PI.recip(),
+                    }
+                }
+            }
+            // Only glossy reflections are sampled from a PDF
+            Material::Metallic(mat) => {
+                let direction = scattered.direction().unit_vector();
+                if rec.normal.dot(&direction) <= 0.0 {
+                    return 0.0;
+                }
+                let reflected = Vec3::reflect(&r_in.direction().unit_vector(), &rec.normal);
+                crate::pdf::PhongPdf::new(&reflected, Metal::glossy_exponent(mat.fuzz_at(rec)))
+                    .value(&direction)
+            }
             Material::Dielectric(_ri) => todo!(),
-            Material::Iso(_mat) => todo!(),
+            // Every direction is equally likely
+            Material::Iso(_) => 0.25 * core::f64::consts::FRAC_1_PI,
+            Material::Translucent(mat) => {
+                crate::pdf::TranslucentPdf::new(&rec.normal, mat.transmission)
+                    .value(&scattered.direction())
+            }
             Material::DiffLight(_) => todo!(),
+            Material::Bumped(bump) => {
+                bump.material
+                    .scattering_pdf(rng, r_in, &bump.perturb(rec), scattered)
+            }
+        }
+    }
+
+    /// Whether the surface has a hole at texture coordinates `(u, v)` and
+    /// point `p`, so that rays should pass through it.
+    pub fn is_cut_out(&self, u: f64, v: f64, p: &Point3) -> bool {
+        match self {
+            Material::Lambertian(Lambert {
+                alpha: Some(alpha), ..
+            }) => {
+                let a = alpha.value(u, v, p);
+                (a.x() + a.y() + a.z()) / 3.0 < ALPHA_CUTOFF
+            }
+            Material::Bumped(bump) => bump.material.is_cut_out(u, v, p),
+            _ => false,
+        }
+    }
+
+    /// Fraction of light the material reflects or transmits at a hit, for
+    /// auxiliary render outputs. Lights reflect nothing, and clear
+    /// dielectrics let everything through.
+    pub fn albedo(&self, rec: &HitRecord) -> Color {
+        match self {
+            Material::Lambertian(mat) => mat.albedo.value(rec.u, rec.v, &rec.p),
+            Material::Metallic(mat) => mat.albedo_at(rec),
+            Material::Dielectric(Diel {
+                tint: Some(tint), ..
+            }) => tint.value(rec.u, rec.v, &rec.p),
+            Material::Dielectric(_) => Color::new_with(1.0),
+            Material::DiffLight(_) => Color::new_with(0.0),
+            Material::Iso(iso) => iso.albedo.value(rec.u, rec.v, &rec.p),
+            Material::Bumped(bump) => bump.material.albedo(rec),
+            Material::Phong(phong) => phong.diffuse.value(rec.u, rec.v, &rec.p),
+            Material::Translucent(mat) => mat.albedo.value(rec.u, rec.v, &rec.p),
+        }
+    }
+
+    /// Color given off by the front face at texture coordinates `(u, v)` and
+    /// point `p`, whichever way it's seen from, such as to work out the power
+    /// of a light.
+    pub fn emission(&self, u: f64, v: f64, p: &Point3) -> Color {
+        match self {
+            Material::DiffLight(diff) => diff.emit.value(u, v, p) * diff.intensity,
+            Material::Iso(Isotropic {
+                emit: Some(emit), ..
+            }) => emit.value(u, v, p),
+            Material::Bumped(bump) => bump.material.emission(u, v, p),
+            _ => Color::new_with(0.0),
         }
     }
 
     /// Color emitted by the material.
-    pub fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Color {
+    pub fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
         match self {
             Material::DiffLight(diff) => {
                 if rec.front_face {
-                    diff.emit.value(rec.u, rec.v, &rec.p)
+                    diff.emit.value(rec.u, rec.v, &rec.p) * diff.falloff(r_in, rec)
                 } else {
                     Color::new_with(0.0)
                 }
             }
+            Material::Iso(Isotropic {
+                emit: Some(emit), ..
+            }) => emit.value(rec.u, rec.v, &rec.p),
+            Material::Bumped(bump) => bump.material.emitted(r_in, rec),
             _ => Color::new_with(0.0),
         }
     }
 }
 
+/// Alpha below which a `Lambert` with an alpha texture has a hole.
+pub const ALPHA_CUTOFF: f64 = 0.5;
+
+/// Step in texture coordinates used to finite difference a `BumpMap`'s height.
+const BUMP_EPSILON: f64 = 0.001;
+
+/// Bump mapping, which tilts the normal of a material by the gradient of a
+/// scalar height texture.
+///
+/// The height is the mean of the texture's channels and is differenced in
+/// texture coordinates, so the texture has to vary with `u` and `v` rather
+/// than the hit point. The slopes are applied along the tangents of an `Onb`
+/// built around the normal, which don't follow the surface's texture
+/// coordinates, so bumps keep their shape but not their orientation.
+#[derive(Clone)]
+pub struct BumpMap {
+    /// Height of the surface.
+    pub height: Arc<dyn Texture + Send + Sync>,
+    /// Scale applied to the height's slope before tilting the normal. `0.0`
+    /// leaves the normal untouched.
+    pub strength: f64,
+    /// Material the bumps are applied to.
+    pub material: Arc<Material>,
+}
+
+impl BumpMap {
+    /// Create a new `BumpMap` material.
+    pub fn new(
+        height: Arc<dyn Texture + Send + Sync>,
+        strength: f64,
+        material: Arc<Material>,
+    ) -> Self {
+        Self {
+            height,
+            strength,
+            material,
+        }
+    }
+
+    /// Return a copy of `rec` with its normal tilted by the height's gradient.
+    pub fn perturb(&self, rec: &HitRecord) -> HitRecord {
+        let height = |u: f64, v: f64| {
+            let c = self.height.value(u, v, &rec.p);
+            (c.x() + c.y() + c.z()) / 3.0
+        };
+
+        // Forward differences of the height along u and v
+        let h = height(rec.u, rec.v);
+        let du = (height(rec.u + BUMP_EPSILON, rec.v) - h) * BUMP_EPSILON.recip();
+        let dv = (height(rec.u, rec.v + BUMP_EPSILON) - h) * BUMP_EPSILON.recip();
+
+        let uvw = crate::onb::Onb::build_from_w(&rec.normal);
+        let mut bumped = rec.clone();
+        bumped.normal = uvw
+            .local_from(-self.strength * du, -self.strength * dv, 1.0)
+            .unit_vector();
+        bumped.set_tangent(&rec.tangent);
+        bumped
+    }
+}
+
+/// How a diffuse material picks its scattered directions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DiffuseSampling {
+    /// Directions weighted by the cosine to the normal, an ideal Lambertian
+    /// surface.
+    #[default]
+    Cosine,
+    /// Directions spread evenly over the hemisphere, the diffuse look from
+    /// early in the first book. Grazing light is brighter than on an ideal
+    /// Lambertian surface, and paths without light sampling are noisier.
+    UniformHemisphere,
+}
+
 /// Diffuse material.
 #[derive(Clone)]
 pub struct Lambert {
     /// Base color of the material.
     pub albedo: Arc<dyn Texture + Send + Sync>,
+    /// Distribution of the scattered directions.
+    pub sampling: DiffuseSampling,
+    /// Coverage of the surface, which has holes where it's below
+    /// `ALPHA_CUTOFF`.
+    pub alpha: Option<Arc<dyn Texture + Send + Sync>>,
 }
 
 impl Lambert {
     /// Create a new `Lambert` material.
     pub fn new(color: Arc<dyn Texture + Send + Sync>) -> Self {
-        Self { albedo: color }
+        Self {
+            albedo: color,
+            sampling: DiffuseSampling::default(),
+            alpha: None,
+        }
+    }
+
+    /// Scatter with a different distribution of directions.
+    pub fn with_sampling(self, sampling: DiffuseSampling) -> Self {
+        Self { sampling, ..self }
+    }
+
+    /// Cut holes in the surface where the mean of `alpha`'s channels is below
+    /// `ALPHA_CUTOFF`, as for leaves or fences drawn on flat cards.
+    ///
+    /// Only rectangles and triangles check the alpha, and rays pass through
+    /// the holes as if nothing was there.
+    pub fn with_alpha(self, alpha: Arc<dyn Texture + Send + Sync>) -> Self {
+        Self {
+            alpha: Some(alpha),
+            ..self
+        }
     }
 }
 
 impl core::default::Default for Lambert {
     fn default() -> Self {
+        Self::new(Arc::new(SolidColor::new(0.2, 0.6, 0.8)))
+    }
+}
+
+/// Glossy material with a Phong highlight over a diffuse base.
+///
+/// The highlight follows the energy conserving Phong model, so the diffuse and
+/// specular colors should add up to at most one in each channel.
+#[derive(Clone)]
+pub struct Phong {
+    /// Color of the diffuse base.
+    pub diffuse: Arc<dyn Texture + Send + Sync>,
+    /// Color of the highlight.
+    pub specular: Color,
+    /// Exponent of the highlight's `cos^n` lobe around the mirror direction.
+    /// Higher values give smaller, sharper highlights.
+    pub shininess: f64,
+}
+
+impl Phong {
+    /// Create a new `Phong` material.
+    pub fn new(diffuse: Arc<dyn Texture + Send + Sync>, specular: Color, shininess: f64) -> Self {
+        Self {
+            diffuse,
+            specular,
+            shininess,
+        }
+    }
+}
+
+/// Thin diffuse material, such as paper, leaves or a lampshade, which scatters
+/// some of the light through to its far side.
+///
+/// Light leaves either side in a cosine lobe, with `transmission` of it on the
+/// far side and the rest on the side it arrived from. Unlike a dielectric, the
+/// surface has no inside, so nothing refracts and rays don't enter a medium.
+#[derive(Clone)]
+pub struct Translucent {
+    /// Color of the scattered light on both sides.
+    pub albedo: Arc<dyn Texture + Send + Sync>,
+    /// Fraction of the scattered light which goes through to the far side,
+    /// between `0.0` for a plain diffuse surface and `1.0`.
+    pub transmission: f64,
+}
+
+impl Translucent {
+    /// Create a new `Translucent` material, clamping `transmission` between
+    /// `0.0` and `1.0`.
+    pub fn new(albedo: Arc<dyn Texture + Send + Sync>, transmission: f64) -> Self {
         Self {
-            albedo: Arc::new(SolidColor::new(0.2, 0.6, 0.8)),
+            albedo,
+            transmission: transmission.clamp(0.0, 1.0),
         }
     }
 }
 
+/// How a metal material picks its reflected directions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MetalSampling {
+    /// The mirror direction nudged by a random point in a sphere of radius
+    /// `fuzz`, traced like a specular bounce as in the first book.
+    #[default]
+    Fuzz,
+    /// Directions from a Phong lobe around the mirror direction, about as wide
+    /// as the fuzz, mixed with light sampling like a diffuse surface. Rough
+    /// metal under small lights is much less noisy, but below a fuzz of about
+    /// `0.2` the lobe is too narrow to gain from it and gets noisier. Mirrors
+    /// with no fuzz and brushed metal are still traced like `Fuzz`.
+    Glossy,
+}
+
 /// Metallic material.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Default)]
 pub struct Metal {
     /// Base color of the material.
     pub albedo: Color,
     /// Fuzz factor of the reflection.
     pub fuzz: f64,
+    /// Fuzz factors along the `u` and `v` axes of an `Onb` around the
+    /// reflected direction, used instead of `fuzz` when set.
+    pub anisotropic_fuzz: Option<(f64, f64)>,
+    /// Fuzz factor varying across the surface, taken from the mean of the
+    /// texture's channels and used instead of `fuzz` when set.
+    pub roughness: Option<Arc<dyn Texture + Send + Sync>>,
+    /// Color varying across the surface, used instead of `albedo` when set.
+    pub albedo_map: Option<Arc<dyn Texture + Send + Sync>>,
+    /// Distribution of the reflected directions.
+    pub sampling: MetalSampling,
+    /// Whether reflections brighten towards white at grazing angles, following
+    /// [`fresnel`] with the albedo as the color head on. Off by default, for
+    /// the constant albedo of the books.
+    pub fresnel: bool,
 }
 
 impl Metal {
     /// Create a new `Metal` material.
+    ///
+    /// `fuzz` is clamped to at most `1.0`. Pass `0.0` for a perfect mirror, as
+    /// in the early first book scenes which predate fuzzy reflection.
     pub fn new(albedo: Color, fuzz: f64) -> Self {
         Self {
             albedo,
             fuzz: if fuzz < 1.0 { fuzz } else { 1.0 },
+            anisotropic_fuzz: None,
+            roughness: None,
+            albedo_map: None,
+            sampling: MetalSampling::default(),
+            fresnel: false,
+        }
+    }
+
+    /// Create a new `Metal` material, rejecting a negative or non-finite
+    /// `fuzz`.
+    pub fn try_new(albedo: Color, fuzz: f64) -> Result<Self, MaterialError> {
+        if !(fuzz.is_finite() && fuzz >= 0.0) {
+            return Err(MaterialError::Fuzz(fuzz));
+        }
+        Ok(Self::new(albedo, fuzz))
+    }
+
+    /// Create a new brushed `Metal` material whose reflections are stretched
+    /// along one axis.
+    ///
+    /// `fuzz_u` and `fuzz_v` are clamped to at most `1.0`. The `u` axis lies in
+    /// the plane of the reflected direction and the world X axis, or the Y axis
+    /// for reflections close to X, so a larger `fuzz_u` streaks highlights
+    /// along X.
+    pub fn new_anisotropic(albedo: Color, fuzz_u: f64, fuzz_v: f64) -> Self {
+        Self {
+            albedo,
+            fuzz: fuzz_u.max(fuzz_v).min(1.0),
+            anisotropic_fuzz: Some((fuzz_u.min(1.0), fuzz_v.min(1.0))),
+            roughness: None,
+            albedo_map: None,
+            sampling: MetalSampling::default(),
+            fresnel: false,
+        }
+    }
+
+    /// Vary the fuzz factor across the surface with a texture, from polished
+    /// where it's black to matte where it's white. Values are clamped to at
+    /// most `1.0`.
+    pub fn with_roughness(self, roughness: Arc<dyn Texture + Send + Sync>) -> Self {
+        Self {
+            roughness: Some(roughness),
+            ..self
+        }
+    }
+
+    /// Vary the color of the reflections across the surface with a texture.
+    pub fn with_albedo_map(self, albedo_map: Arc<dyn Texture + Send + Sync>) -> Self {
+        Self {
+            albedo_map: Some(albedo_map),
+            ..self
+        }
+    }
+
+    /// Reflect with a different distribution of directions.
+    pub fn with_sampling(self, sampling: MetalSampling) -> Self {
+        Self { sampling, ..self }
+    }
+
+    /// Brighten reflections towards white at grazing angles, for the bright
+    /// rims of real metals.
+    pub fn with_fresnel(self, fresnel: bool) -> Self {
+        Self { fresnel, ..self }
+    }
+
+    /// Exponent of the Phong lobe matching a fuzz factor.
+    ///
+    /// Fuzz tilts reflections by a root mean square of about `0.63 * fuzz`
+    /// radians, and a `cos^n` lobe by about `(2 / (n + 3)).sqrt()`.
+    pub fn glossy_exponent(fuzz: f64) -> f64 {
+        (5.0 / (fuzz * fuzz) - 3.0).max(0.0)
+    }
+
+    /// Fuzz factor at a hit.
+    fn fuzz_at(&self, rec: &HitRecord) -> f64 {
+        match &self.roughness {
+            Some(roughness) => {
+                let r = roughness.value(rec.u, rec.v, &rec.p);
+                ((r.x() + r.y() + r.z()) / 3.0).min(1.0)
+            }
+            None => self.fuzz,
+        }
+    }
+
+    /// Color of the reflections at a hit for a ray arriving from `direction`.
+    ///
+    /// With `fresnel`, the angle is taken between the incoming ray and the
+    /// normal, which for rough metal stands in for the angle to the
+    /// microfacet each reflection bounces off.
+    fn reflectance_at(&self, rec: &HitRecord, direction: &Vec3) -> Color {
+        let albedo = self.albedo_at(rec);
+        if !self.fresnel {
+            return albedo;
+        }
+        let cos = -direction.unit_vector().dot(&rec.normal);
+        fresnel(cos.clamp(0.0, 1.0), albedo)
+    }
+
+    /// Color of the reflections at a hit.
+    fn albedo_at(&self, rec: &HitRecord) -> Color {
+        match &self.albedo_map {
+            Some(albedo) => albedo.value(rec.u, rec.v, &rec.p),
+            None => self.albedo,
         }
     }
 }
 
 /// Dielectric material for simulating clear objects like water and glass.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Default)]
 pub struct Diel {
     /// Refraction index of the dielectric.
     ///
     /// Air is typically 1.0, glass 1.3-1.7, and diamond is 2.4.
     pub refraction_index: f64,
+    /// Rate at which each color channel is absorbed per unit of distance
+    /// travelled inside the material. [`ray_color`](crate::ray::ray_color)
+    /// applies it over every segment of a path inside the object, and `scatter`
+    /// leaves it out.
+    pub absorption: Color,
+    /// Which material wins where dielectric objects overlap, higher first.
+    /// Boundaries of a lower priority object inside a higher one are ignored.
+    pub priority: u32,
+    /// Color filtering light as it refracts into the material, looked up
+    /// where it enters. Unlike `absorption` it doesn't depend on thickness.
+    pub tint: Option<Arc<dyn Texture + Send + Sync>>,
 }
 
 impl Diel {
-    /// Create a new `Diel` material.
+    /// Create a new clear `Diel` material.
     pub fn new(refraction_index: f64) -> Self {
-        Self { refraction_index }
+        Self {
+            refraction_index,
+            absorption: Color::new_with(0.0),
+            priority: 0,
+            tint: None,
+        }
+    }
+
+    /// Create a new clear `Diel` material, rejecting a refraction index which
+    /// isn't positive and finite.
+    pub fn try_new(refraction_index: f64) -> Result<Self, MaterialError> {
+        if !(refraction_index.is_finite() && refraction_index > 0.0) {
+            return Err(MaterialError::RefractionIndex(refraction_index));
+        }
+        Ok(Self::new(refraction_index))
+    }
+
+    /// Create a new `Diel` material tinted by `tint`, like stained glass.
+    pub fn new_textured(refraction_index: f64, tint: Arc<dyn Texture + Send + Sync>) -> Self {
+        Self {
+            tint: Some(tint),
+            ..Self::new(refraction_index)
+        }
+    }
+
+    /// Tint the material by absorbing light as it passes through, following
+    /// the Beer-Lambert law. Thicker parts of an object appear darker.
+    pub fn with_absorption(self, absorption: Color) -> Self {
+        Self { absorption, ..self }
+    }
+
+    /// Take precedence over lower priority dielectrics where objects overlap.
+    pub fn with_priority(self, priority: u32) -> Self {
+        Self { priority, ..self }
+    }
+
+    /// Reflect or refract `r_in` at a boundary with the ratio of refraction
+    /// indices `etai_over_etat`, from the incoming side over the far side.
+    pub fn scatter<R: rand::Rng>(
+        &self,
+        rng: &mut R,
+        r_in: &Ray,
+        rec: &HitRecord,
+        etai_over_etat: f64,
+        srec: &mut ScatterRecord,
+    ) {
+        srec.attenuation = Color::new_with(1.0);
+
+        let unit_dir = r_in.direction().unit_vector();
+        let cos_theta = (-unit_dir).dot(&rec.normal).min(1.0);
+
+        if rng.gen::<f64>() < reflectance(cos_theta, etai_over_etat) {
+            let reflected = Vec3::reflect(&unit_dir, &rec.normal);
+            srec.specular_ray = Some(Ray::new(rec.p, reflected, r_in.time()));
+        } else {
+            let refracted = Vec3::refract(&unit_dir, &rec.normal, etai_over_etat);
+            srec.specular_ray = Some(Ray::new(rec.p, refracted, r_in.time()));
+            if let (true, Some(tint)) = (rec.front_face, &self.tint) {
+                srec.attenuation *= tint.value(rec.u, rec.v, &rec.p);
+            }
+        }
+        srec.debug_check(true);
     }
 }
 
@@ -228,12 +834,67 @@ impl Diel {
 pub struct DiffuseLight {
     /// Diffuse emitting texture.
     pub emit: Arc<dyn Texture + Send + Sync>,
+    /// Multiplier for the emitted texture.
+    pub intensity: f64,
+    /// Exponent `n` of the `cos^n` falloff away from the surface normal, `0.0`
+    /// for uniform emission.
+    pub spread_power: f64,
 }
 
 impl DiffuseLight {
     /// Create a new diffuse light.
     pub fn new(emit: Arc<dyn Texture + Send + Sync>) -> Self {
-        Self { emit }
+        Self {
+            emit,
+            intensity: 1.0,
+            spread_power: 0.0,
+        }
+    }
+
+    /// Create a new diffuse light whose emission falls off as `cos^n` of the
+    /// angle from its normal, like a softbox with a grid.
+    ///
+    /// Narrowing the spread keeps the light as bright head-on but emits
+    /// `2 / (n + 2)` of the power, so multiply `intensity` by `(n + 2) / 2` to
+    /// keep the total power. Lights are still sampled uniformly over their
+    /// area, so narrow spreads are noisier.
+    pub fn new_directional(
+        emit: Arc<dyn Texture + Send + Sync>,
+        intensity: f64,
+        spread_power: f64,
+    ) -> Self {
+        Self {
+            emit,
+            intensity,
+            spread_power,
+        }
+    }
+
+    /// Create a new directional diffuse light, rejecting a negative or
+    /// non-finite `intensity` or `spread_power`.
+    pub fn try_new_directional(
+        emit: Arc<dyn Texture + Send + Sync>,
+        intensity: f64,
+        spread_power: f64,
+    ) -> Result<Self, MaterialError> {
+        if !(intensity.is_finite() && intensity >= 0.0) {
+            return Err(MaterialError::Intensity(intensity));
+        }
+        if !(spread_power.is_finite() && spread_power >= 0.0) {
+            return Err(MaterialError::SpreadPower(spread_power));
+        }
+        Ok(Self::new_directional(emit, intensity, spread_power))
+    }
+
+    /// Scale of the emission towards the origin of `r_in`.
+    fn falloff(&self, r_in: &Ray, rec: &HitRecord) -> f64 {
+        if self.spread_power == 0.0 {
+            return self.intensity;
+        }
+
+        // The normal faces the ray, so the ray leaves the light along it
+        let cosine = -r_in.direction().unit_vector().dot(&rec.normal);
+        self.intensity * cosine.max(0.0).powf(self.spread_power)
     }
 }
 
@@ -242,18 +903,332 @@ impl DiffuseLight {
 pub struct Isotropic {
     /// Based texture of the material.
     pub albedo: Arc<dyn Texture + Send + Sync>,
+    /// Light emitted at each scattering point, if any.
+    pub emit: Option<Arc<dyn Texture + Send + Sync>>,
 }
 
 impl Isotropic {
     /// Create new isotropic material.
     pub fn new(albedo: Arc<dyn Texture + Send + Sync>) -> Self {
-        Self { albedo }
+        Self { albedo, emit: None }
     }
+
+    /// Create new isotropic material that also glows where it scatters.
+    pub fn new_emissive(
+        albedo: Arc<dyn Texture + Send + Sync>,
+        emit: Arc<dyn Texture + Send + Sync>,
+    ) -> Self {
+        Self {
+            albedo,
+            emit: Some(emit),
+        }
+    }
+}
+/// Whether every component of `v` is finite.
+fn is_finite(v: &Vec3) -> bool {
+    v.x().is_finite() && v.y().is_finite() && v.z().is_finite()
 }
+
 /// Schlick approximation for reflectivity.
+///
+/// `ref_idx` may be either ratio of the two indices since `r0` is the same
+/// for both, but `cos` has to be taken on the side with the lower index.
 #[inline]
 pub fn schlick(cos: f64, ref_idx: f64) -> f64 {
     let mut r0 = (1.0 - ref_idx) * (1.0 + ref_idx).recip();
     r0 = r0 * r0;
     r0 + (1.0 - r0) * (1.0 - cos).powi(5)
 }
+
+/// Schlick approximation for the color reflected by a metal, whose color head
+/// on is `f0`, for light arriving at an angle with cosine `cos`.
+///
+/// Reflections brighten towards white as `cos` falls to `0.0` at grazing
+/// angles.
+#[inline]
+pub fn fresnel(cos: f64, f0: Color) -> Color {
+    f0 + (Color::new_with(1.0) - f0) * (1.0 - cos).powi(5)
+}
+
+/// Fraction of light reflected by a dielectric boundary, for a ray whose
+/// incident angle has cosine `cos_theta` moving between indices with ratio
+/// `etai_over_etat`.
+///
+/// Returns `1.0` under total internal reflection. Leaving a denser medium,
+/// Schlick's approximation uses the angle of the refracted ray so that
+/// reflectance is the same in both directions along a path.
+pub fn reflectance(cos_theta: f64, etai_over_etat: f64) -> f64 {
+    if etai_over_etat <= 1.0 {
+        return schlick(cos_theta, etai_over_etat);
+    }
+
+    let sin_t_squared = etai_over_etat * etai_over_etat * (1.0 - cos_theta * cos_theta);
+    if sin_t_squared > 1.0 {
+        return 1.0;
+    }
+
+    schlick((1.0 - sin_t_squared).sqrt(), etai_over_etat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::SolidColor;
+
+    #[test]
+    fn diel_rejects_refraction_indices_that_arent_positive() {
+        for &n in &[0.0, -1.5, f64::NAN, f64::INFINITY] {
+            assert!(
+                matches!(Diel::try_new(n), Err(MaterialError::RefractionIndex(_))),
+                "accepted {}",
+                n
+            );
+        }
+        assert!(Diel::try_new(1.5).is_ok());
+    }
+
+    #[test]
+    fn metal_rejects_fuzz_that_isnt_non_negative() {
+        let albedo = Color::new_with(0.5);
+        for &fuzz in &[-0.1, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(
+                matches!(Metal::try_new(albedo, fuzz), Err(MaterialError::Fuzz(_))),
+                "accepted {}",
+                fuzz
+            );
+        }
+        assert!(Metal::try_new(albedo, 0.0).is_ok());
+    }
+
+    #[test]
+    fn directional_lights_reject_bad_intensity_and_spread() {
+        let emit = || -> Arc<dyn Texture + Send + Sync> { Arc::new(SolidColor::new_with(1.0)) };
+        for &bad in &[-1.0, f64::NAN, f64::INFINITY] {
+            assert!(
+                matches!(
+                    DiffuseLight::try_new_directional(emit(), bad, 1.0),
+                    Err(MaterialError::Intensity(_))
+                ),
+                "accepted intensity {}",
+                bad
+            );
+            assert!(
+                matches!(
+                    DiffuseLight::try_new_directional(emit(), 1.0, bad),
+                    Err(MaterialError::SpreadPower(_))
+                ),
+                "accepted spread power {}",
+                bad
+            );
+        }
+        assert!(DiffuseLight::try_new_directional(emit(), 0.0, 0.0).is_ok());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "attenuation")]
+    fn nan_albedo_trips_the_scatter_check() {
+        let material = Material::Lambertian(Lambert::new(Arc::new(SolidColor::new_with(f64::NAN))));
+        let rec = HitRecord {
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            front_face: true,
+            ..HitRecord::default()
+        };
+        let r_in = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        material.scatter(
+            &mut crate::rng::pixel_rng(1, 0),
+            &r_in,
+            &rec,
+            &mut ScatterRecord::default(),
+        );
+    }
+
+    /// Difference between renders with two seeds, at 16 samples per pixel, of
+    /// a rough metal floor reflecting a small light.
+    fn metal_floor_noise(sampling: MetalSampling) -> f64 {
+        use crate::aarect::{AaRect, Plane};
+        use crate::hittable::{FlipFace, HittableList};
+
+        let floor =
+            Material::Metallic(Metal::new(Color::new_with(0.8), 0.6).with_sampling(sampling));
+        let light = || {
+            AaRect::new(
+                -0.5,
+                0.5,
+                -0.5,
+                0.5,
+                4.0,
+                Arc::new(Material::DiffLight(DiffuseLight::new(Arc::new(
+                    SolidColor::new_with(15.0),
+                )))),
+                Plane::Xz,
+            )
+        };
+        let mut objects = HittableList::new();
+        objects.add(Arc::new(AaRect::new(
+            -5.0,
+            5.0,
+            -5.0,
+            5.0,
+            0.0,
+            Arc::new(floor),
+            Plane::Xz,
+        )));
+        objects.add(Arc::new(FlipFace::new(Arc::new(light()))));
+        let world = crate::world::World::new(objects, Arc::new(light()));
+        let cam = crate::camera::Camera::new(
+            Point3::new(0.0, 2.0, 5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            5.0,
+            0.0,
+            1.0,
+        );
+
+        let render = |seed| {
+            let config = crate::render::RenderConfig {
+                samples: 16,
+                threads: false,
+                seed,
+                ..crate::render::RenderConfig::new(24, 24)
+            };
+            crate::render::render_linear(&config, &world, &cam)
+        };
+        let (a, b) = (render(1), render(2));
+        let squared: f64 = a
+            .iter()
+            .zip(&b)
+            .map(|(x, y)| (*x - *y).length_squared())
+            .sum();
+        (squared / a.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn glossy_metal_is_less_noisy_under_a_small_light() {
+        let fuzz = metal_floor_noise(MetalSampling::Fuzz);
+        let glossy = metal_floor_noise(MetalSampling::Glossy);
+        assert!(
+            glossy < 0.5 * fuzz,
+            "glossy {} against fuzz {}",
+            glossy,
+            fuzz
+        );
+    }
+
+    #[test]
+    fn isotropic_pdf_integrates_to_one() {
+        let material = Material::Iso(Isotropic::new(Arc::new(SolidColor::new_with(0.5))));
+        let rec = HitRecord::default();
+        let r_in = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rng = &mut crate::rng::pixel_rng(1, 0);
+
+        // Midpoint rule over a grid in the polar and azimuthal angles
+        let (n_theta, n_phi) = (90, 180);
+        let (d_theta, d_phi) = (
+            core::f64::consts::PI / f64::from(n_theta),
+            crate::conversion::TWO_PI / f64::from(n_phi),
+        );
+        let mut total = 0.0;
+        for i in 0..n_theta {
+            let theta = (f64::from(i) + 0.5) * d_theta;
+            for j in 0..n_phi {
+                let phi = (f64::from(j) + 0.5) * d_phi;
+                let direction = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+                let scattered = Ray::new(Point3::default(), direction, 0.0);
+                total += material.scattering_pdf(rng, &r_in, &rec, &scattered)
+                    * theta.sin()
+                    * d_theta
+                    * d_phi;
+            }
+        }
+        assert!((total - 1.0).abs() < 1e-3, "integrates to {}", total);
+    }
+
+    #[test]
+    fn metal_fresnel_approaches_one_at_grazing_angles() {
+        let gold = Color::new(1.0, 0.71, 0.29);
+        let at = |cos: f64| fresnel(cos, gold);
+        assert_eq!(at(1.0).z(), gold.z());
+        // Brightening towards white as the angle grows
+        let mut last = at(1.0);
+        for k in (0..10).rev() {
+            let next = at(f64::from(k) / 10.0);
+            assert!(next.y() >= last.y() && next.z() >= last.z());
+            last = next;
+        }
+        let grazing = at(1e-4);
+        assert!(grazing.y() > 0.999 && grazing.z() > 0.999, "{:?}", grazing);
+
+        // The same through a metal with Fresnel turned on
+        let metal = Metal::new(gold, 0.0).with_fresnel(true);
+        let rec = HitRecord {
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            ..HitRecord::default()
+        };
+        let reflected = metal.reflectance_at(&rec, &Vec3::new(1.0, -1e-4, 0.0));
+        assert!(reflected.z() > 0.999, "{:?}", reflected);
+    }
+
+    #[test]
+    fn glass_reflectance_head_on_matches_the_analytic_value() {
+        let (n1, n2): (f64, f64) = (1.0, 1.5);
+        let analytic = ((n1 - n2) / (n1 + n2)).powi(2);
+        assert!((reflectance(1.0, n1 / n2) - analytic).abs() < 1e-12);
+        assert!((reflectance(1.0, n2 / n1) - analytic).abs() < 1e-12);
+
+        // Entering and leaving along the same path reflect the same share
+        let cos_in: f64 = 0.6;
+        let sin_out = n1 / n2 * (1.0 - cos_in * cos_in).sqrt();
+        let cos_out = (1.0 - sin_out * sin_out).sqrt();
+        let entering = reflectance(cos_in, n1 / n2);
+        let leaving = reflectance(cos_out, n2 / n1);
+        assert!(
+            (entering - leaving).abs() < 1e-12,
+            "{} {}",
+            entering,
+            leaving
+        );
+    }
+
+    #[test]
+    fn uniform_roughness_matches_scalar_fuzz() {
+        let rec = HitRecord {
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            ..HitRecord::default()
+        };
+        let r_in = Ray::new(Point3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0), 0.0);
+        for &sampling in &[MetalSampling::Fuzz, MetalSampling::Glossy] {
+            let albedo = Color::new(0.9, 0.7, 0.4);
+            let scalar = Material::Metallic(Metal::new(albedo, 0.25).with_sampling(sampling));
+            let textured = Material::Metallic(
+                Metal::new(albedo, 0.0)
+                    .with_roughness(Arc::new(SolidColor::new_with(0.25)))
+                    .with_sampling(sampling),
+            );
+
+            let scatter = |material: &Material| {
+                let rng = &mut crate::rng::pixel_rng(1, 0);
+                let mut srec = ScatterRecord::default();
+                assert!(material.scatter(rng, &r_in, &rec, &mut srec));
+                let direction = match (&srec.specular_ray, &srec.pdf_ptr) {
+                    (Some(specular), _) => specular.direction(),
+                    (None, Some(pdf)) => pdf.generate(rng),
+                    (None, None) => unreachable!(),
+                };
+                (
+                    direction.as_array(),
+                    srec.attenuation.as_array(),
+                    srec.spread,
+                )
+            };
+            assert_eq!(scatter(&scalar), scatter(&textured), "{:?}", sampling);
+        }
+    }
+}
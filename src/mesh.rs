@@ -0,0 +1,332 @@
+//! Loaders for triangle meshes.
+
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use crate::bvh::BvhNode;
+use crate::hittable::{HittableList, Triangle};
+use crate::material::{Lambert, Material};
+use crate::texture::SolidColor;
+use crate::vec3::{Color, Point3, Vec3};
+
+/// Scalar types of PLY properties.
+#[derive(Clone, Copy, Debug)]
+enum PlyType {
+    Int8,
+    Uint8,
+    Int16,
+    Uint16,
+    Int32,
+    Uint32,
+    Float32,
+    Float64,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match name {
+            "char" | "int8" => Ok(PlyType::Int8),
+            "uchar" | "uint8" => Ok(PlyType::Uint8),
+            "short" | "int16" => Ok(PlyType::Int16),
+            "ushort" | "uint16" => Ok(PlyType::Uint16),
+            "int" | "int32" => Ok(PlyType::Int32),
+            "uint" | "uint32" => Ok(PlyType::Uint32),
+            "float" | "float32" => Ok(PlyType::Float32),
+            "double" | "float64" => Ok(PlyType::Float64),
+            _ => Err(format!("unknown PLY type `{}`", name).into()),
+        }
+    }
+
+    /// Size of the type in binary files.
+    fn size(self) -> usize {
+        match self {
+            PlyType::Int8 | PlyType::Uint8 => 1,
+            PlyType::Int16 | PlyType::Uint16 => 2,
+            PlyType::Int32 | PlyType::Uint32 | PlyType::Float32 => 4,
+            PlyType::Float64 => 8,
+        }
+    }
+
+    /// Value representing full intensity when the type holds a color channel.
+    fn color_scale(self) -> f64 {
+        match self {
+            PlyType::Int8 => f64::from(i8::MAX),
+            PlyType::Uint8 => f64::from(u8::MAX),
+            PlyType::Int16 => f64::from(i16::MAX),
+            PlyType::Uint16 => f64::from(u16::MAX),
+            PlyType::Int32 => f64::from(i32::MAX),
+            PlyType::Uint32 => f64::from(u32::MAX),
+            PlyType::Float32 | PlyType::Float64 => 1.0,
+        }
+    }
+}
+
+/// Property of a PLY element. `list` holds the type of the length of list
+/// properties.
+struct PlyProperty {
+    name: String,
+    ty: PlyType,
+    list: Option<PlyType>,
+}
+
+/// Element declared in a PLY header, such as `vertex` or `face`.
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+impl PlyElement {
+    fn has_property(&self, name: &str) -> bool {
+        self.properties.iter().any(|p| p.name == name)
+    }
+}
+
+/// Data following a PLY header.
+enum PlyBody<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    BinaryLe(&'a [u8]),
+}
+
+impl PlyBody<'_> {
+    fn read(&mut self, ty: PlyType) -> Result<f64, Box<dyn std::error::Error>> {
+        match self {
+            PlyBody::Ascii(tokens) => {
+                let token = tokens.next().ok_or("unexpected end of PLY data")?;
+                Ok(token.parse()?)
+            }
+            PlyBody::BinaryLe(bytes) => {
+                if bytes.len() < ty.size() {
+                    return Err("unexpected end of PLY data".into());
+                }
+                let (value, rest) = bytes.split_at(ty.size());
+                *bytes = rest;
+
+                Ok(match ty {
+                    PlyType::Int8 => f64::from(value[0] as i8),
+                    PlyType::Uint8 => f64::from(value[0]),
+                    PlyType::Int16 => f64::from(i16::from_le_bytes(value.try_into()?)),
+                    PlyType::Uint16 => f64::from(u16::from_le_bytes(value.try_into()?)),
+                    PlyType::Int32 => f64::from(i32::from_le_bytes(value.try_into()?)),
+                    PlyType::Uint32 => f64::from(u32::from_le_bytes(value.try_into()?)),
+                    PlyType::Float32 => f64::from(f32::from_le_bytes(value.try_into()?)),
+                    PlyType::Float64 => f64::from_le_bytes(value.try_into()?),
+                })
+            }
+        }
+    }
+}
+
+/// Load a Stanford PLY mesh into a BVH of triangles.
+///
+/// See [`parse_ply`] for the supported contents.
+pub fn load_ply<P: AsRef<std::path::Path>>(
+    path: P,
+    material: Arc<Material>,
+) -> Result<BvhNode, Box<dyn std::error::Error>> {
+    parse_ply(&std::fs::read(path)?, material)
+}
+
+/// Parse the bytes of a Stanford PLY mesh into a BVH of triangles.
+///
+/// ASCII and binary little-endian files are supported. Polygons are split
+/// into triangle fans. Vertex normals (`nx`, `ny`, `nz`) smooth the shading,
+/// and vertex colors (`red`, `green`, `blue`) are averaged into a Lambertian
+/// albedo for each triangle in place of `material`.
+pub fn parse_ply(
+    data: &[u8],
+    material: Arc<Material>,
+) -> Result<BvhNode, Box<dyn std::error::Error>> {
+    let header_end = data
+        .windows(b"end_header".len())
+        .position(|w| w == b"end_header")
+        .ok_or("missing PLY `end_header`")?;
+    let body_start = data[header_end..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(data.len(), |p| header_end + p + 1);
+    let header = std::str::from_utf8(&data[..header_end])?;
+
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err("missing PLY magic number".into());
+    }
+
+    let mut binary = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["format", "ascii", _] => binary = Some(false),
+            ["format", "binary_little_endian", _] => binary = Some(true),
+            ["format", format, _] => {
+                return Err(format!("unsupported PLY format `{}`", format).into())
+            }
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_owned(),
+                count: count.parse()?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_ty, ty, name] => elements
+                .last_mut()
+                .ok_or("PLY property declared before any element")?
+                .properties
+                .push(PlyProperty {
+                    name: name.to_owned(),
+                    ty: PlyType::parse(ty)?,
+                    list: Some(PlyType::parse(count_ty)?),
+                }),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or("PLY property declared before any element")?
+                .properties
+                .push(PlyProperty {
+                    name: name.to_owned(),
+                    ty: PlyType::parse(ty)?,
+                    list: None,
+                }),
+            // Comments and other metadata
+            _ => {}
+        }
+    }
+
+    let body = &data[body_start..];
+    let mut body = match binary {
+        Some(false) => PlyBody::Ascii(std::str::from_utf8(body)?.split_ascii_whitespace()),
+        Some(true) => PlyBody::BinaryLe(body),
+        None => return Err("missing PLY format".into()),
+    };
+
+    let mut vertices = Vec::new();
+    let mut normals = Vec::new();
+    let mut colors = Vec::new();
+    let mut faces = Vec::new();
+    for element in &elements {
+        for _ in 0..element.count {
+            let mut position = [0.0; 3];
+            let mut normal = [0.0; 3];
+            let mut color = [0.0; 3];
+            let mut indices = Vec::new();
+
+            for property in &element.properties {
+                if let Some(count_ty) = property.list {
+                    let count = body.read(count_ty)? as usize;
+                    let items = (0..count)
+                        .map(|_| body.read(property.ty))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if property.name == "vertex_indices" || property.name == "vertex_index" {
+                        indices = items.into_iter().map(|i| i as usize).collect();
+                    }
+                    continue;
+                }
+
+                let value = body.read(property.ty)?;
+                let channel = value / property.ty.color_scale();
+                match property.name.as_str() {
+                    "x" => position[0] = value,
+                    "y" => position[1] = value,
+                    "z" => position[2] = value,
+                    "nx" => normal[0] = value,
+                    "ny" => normal[1] = value,
+                    "nz" => normal[2] = value,
+                    "red" => color[0] = channel,
+                    "green" => color[1] = channel,
+                    "blue" => color[2] = channel,
+                    _ => {}
+                }
+            }
+
+            match element.name.as_str() {
+                "vertex" => {
+                    vertices.push(Point3::from(position));
+                    normals.push(Vec3::from(normal));
+                    colors.push(Color::from(color));
+                }
+                "face" => faces.push(indices),
+                _ => {}
+            }
+        }
+    }
+
+    let vertex_element = elements.iter().find(|e| e.name == "vertex");
+    let has_normals = vertex_element.is_some_and(|e| e.has_property("nx"));
+    let has_colors = vertex_element.is_some_and(|e| e.has_property("red"));
+
+    let mut triangles = HittableList::new();
+    // The whole mesh takes the identity of its first triangle
+    let mut object = None;
+    for face in &faces {
+        if let Some(&i) = face.iter().find(|&&i| i >= vertices.len()) {
+            return Err(format!("PLY face refers to missing vertex {}", i).into());
+        }
+
+        for k in 1..face.len().saturating_sub(1) {
+            let [a, b, c] = [face[0], face[k], face[k + 1]];
+            let material = if has_colors {
+                let albedo = (colors[a] + colors[b] + colors[c]) / 3.0;
+                Arc::new(Material::Lambertian(Lambert::new(Arc::new(
+                    SolidColor::new(albedo.x(), albedo.y(), albedo.z()),
+                ))))
+            } else {
+                material.clone()
+            };
+
+            let mut triangle = Triangle::new(vertices[a], vertices[b], vertices[c], material);
+            if has_normals {
+                triangle = triangle.with_vertex_normals([normals[a], normals[b], normals[c]]);
+            }
+            if let Some(object) = object {
+                triangle = triangle.with_object(object);
+            }
+            let triangle = Arc::new(triangle);
+            object.get_or_insert(Arc::as_ptr(&triangle) as usize);
+            triangles.add(triangle);
+        }
+    }
+
+    if triangles.is_empty() {
+        return Err("PLY mesh has no faces".into());
+    }
+
+    Ok(BvhNode::try_from_list(&triangles, 0.0, 1.0)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Hittable;
+
+    #[test]
+    fn ascii_tetrahedron_loads_four_triangles() {
+        let ply = b"ply
+format ascii 1.0
+comment a unit tetrahedron
+element vertex 4
+property float x
+property float y
+property float z
+element face 4
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+0 1 0
+0 0 1
+3 0 2 1
+3 0 1 3
+3 0 3 2
+3 1 2 3
+";
+        let mesh = parse_ply(ply, Arc::new(Material::default())).unwrap();
+        let stats = mesh.stats();
+        assert_eq!(stats.leaf_count as f64 * stats.avg_leaf_size, 4.0);
+
+        let mut bbox = crate::aabb::Aabb::default();
+        assert!(mesh.bounding_box(0.0, 1.0, &mut bbox));
+        // Faces lying in the coordinate planes are padded, see `Aabb::padded`
+        let pad = crate::aabb::PADDING;
+        let (min, max) = (bbox.min(), bbox.max());
+        assert_eq!((min.x(), min.y(), min.z()), (-pad, -pad, -pad));
+        assert_eq!((max.x(), max.y(), max.z()), (1.0, 1.0, 1.0));
+    }
+}
@@ -13,7 +13,7 @@ pub trait Pdf {
     /// Return the value from a PDF.
     fn value(&self, direction: &Vec3) -> f64;
     /// Generate the direction from a PDF.
-    fn generate(&self, rng: &mut rand::rngs::ThreadRng) -> Vec3;
+    fn generate(&self, rng: &mut crate::rng::RenderRng) -> Vec3;
 }
 
 /// Cosine probability distribution struct holding an orthonormal basis.
@@ -27,7 +27,7 @@ impl CosPdf {
     /// Create a new cosine PDF.
     pub fn new(w: &Vec3) -> Self {
         Self {
-            uvw: Onb::build_from_w(&w),
+            uvw: Onb::build_from_w(w),
         }
     }
 
@@ -55,11 +55,140 @@ impl Pdf for CosPdf {
         }
     }
 
-    fn generate(&self, rng: &mut rand::rngs::ThreadRng) -> Vec3 {
+    fn generate(&self, rng: &mut crate::rng::RenderRng) -> Vec3 {
         self.uvw.local(&CosPdf::random_cosine_direction(rng))
     }
 }
 
+/// Probability distribution of a Phong lobe, falling off as `cos^n` of the
+/// angle to an axis.
+#[derive(Clone, Default)]
+pub struct PhongPdf {
+    /// Orthonormal basis around the axis of the lobe.
+    pub uvw: Onb,
+    /// Exponent `n` of the lobe.
+    pub exponent: f64,
+}
+
+impl PhongPdf {
+    /// Create a new Phong lobe PDF around `axis`.
+    pub fn new(axis: &Vec3, exponent: f64) -> Self {
+        Self {
+            uvw: Onb::build_from_w(axis),
+            exponent,
+        }
+    }
+
+    /// Return a random direction of a `cos^exponent` lobe around the Z axis.
+    pub fn random_lobe_direction<R: rand::Rng>(rng: &mut R, exponent: f64) -> Vec3 {
+        let cos_alpha = rng.gen::<f64>().powf((exponent + 1.0).recip());
+        let sin_alpha = (1.0 - cos_alpha * cos_alpha).sqrt();
+        let phi = crate::conversion::TWO_PI * rng.gen::<f64>();
+
+        Vec3::new(phi.cos() * sin_alpha, phi.sin() * sin_alpha, cos_alpha)
+    }
+}
+
+impl Pdf for PhongPdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cosine = direction.unit_vector().dot(&self.uvw.w());
+        if cosine <= 0.0 {
+            0.0
+        } else {
+            (self.exponent + 1.0) * crate::conversion::TWO_PI.recip() * cosine.powf(self.exponent)
+        }
+    }
+
+    fn generate(&self, rng: &mut crate::rng::RenderRng) -> Vec3 {
+        self.uvw
+            .local(&PhongPdf::random_lobe_direction(rng, self.exponent))
+    }
+}
+
+/// Cosine lobes on both sides of a surface, with `transmission` of the
+/// probability on the side away from the normal.
+#[derive(Clone, Default)]
+pub struct TranslucentPdf {
+    /// Orthonormal basis around the normal.
+    pub uvw: Onb,
+    /// Probability of a direction on the far side.
+    pub transmission: f64,
+}
+
+impl TranslucentPdf {
+    /// Create a new translucent PDF around `normal`.
+    pub fn new(normal: &Vec3, transmission: f64) -> Self {
+        Self {
+            uvw: Onb::build_from_w(normal),
+            transmission,
+        }
+    }
+}
+
+impl Pdf for TranslucentPdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cosine = direction.unit_vector().dot(&self.uvw.w());
+        let side = if cosine < 0.0 {
+            self.transmission
+        } else {
+            1.0 - self.transmission
+        };
+        side * cosine.abs() * core::f64::consts::FRAC_1_PI
+    }
+
+    fn generate(&self, rng: &mut crate::rng::RenderRng) -> Vec3 {
+        let direction = self.uvw.local(&CosPdf::random_cosine_direction(rng));
+        if rng.gen::<f64>() < self.transmission {
+            -direction
+        } else {
+            direction
+        }
+    }
+}
+
+/// Uniform probability distribution over the hemisphere around a normal.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HemispherePdf {
+    /// Normal the hemisphere is centered on.
+    pub normal: Vec3,
+}
+
+impl HemispherePdf {
+    /// Create a new hemisphere PDF.
+    pub fn new(normal: &Vec3) -> Self {
+        Self { normal: *normal }
+    }
+}
+
+impl Pdf for HemispherePdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        if direction.dot(&self.normal) <= 0.0 {
+            0.0
+        } else {
+            crate::conversion::TWO_PI.recip()
+        }
+    }
+
+    fn generate(&self, rng: &mut crate::rng::RenderRng) -> Vec3 {
+        Vec3::random_in_hemisphere(rng, &self.normal)
+    }
+}
+
+/// Uniform probability distribution over all directions, the phase function
+/// of isotropic media.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: &Vec3) -> f64 {
+        0.25 * core::f64::consts::FRAC_1_PI
+    }
+
+    fn generate(&self, rng: &mut crate::rng::RenderRng) -> Vec3 {
+        Vec3::random_unit_vector(rng)
+    }
+}
+
 /// Sampling that directs light towards a hittable object.
 #[derive(Clone)]
 pub struct HittablePdf {
@@ -84,7 +213,7 @@ impl Pdf for HittablePdf {
         self.pointer.pdf_value(&self.origin, direction)
     }
 
-    fn generate(&self, rng: &mut rand::rngs::ThreadRng) -> Vec3 {
+    fn generate(&self, rng: &mut crate::rng::RenderRng) -> Vec3 {
         self.pointer.random(rng, &self.origin)
     }
 }
@@ -96,22 +225,33 @@ pub struct MixturePdf {
     pub p0: Arc<dyn Pdf>,
     /// Second probability density function.
     pub p1: Arc<dyn Pdf>,
+    /// Probability of generating a direction from `p0` rather than `p1`.
+    pub weight: f64,
 }
 
 impl MixturePdf {
-    /// Create a new `MixturePdf`.
+    /// Create a new `MixturePdf` drawing evenly from both PDFs.
     pub fn new(p0: Arc<dyn Pdf>, p1: Arc<dyn Pdf>) -> Self {
-        Self { p0, p1 }
+        Self {
+            p0,
+            p1,
+            weight: 0.5,
+        }
+    }
+
+    /// Draw from `p0` with probability `weight`, and from `p1` otherwise.
+    pub fn with_weight(self, weight: f64) -> Self {
+        Self { weight, ..self }
     }
 }
 
 impl Pdf for MixturePdf {
     fn value(&self, direction: &Vec3) -> std::primitive::f64 {
-        0.5 * self.p0.value(direction) + 0.5 * self.p1.value(direction)
+        self.weight * self.p0.value(direction) + (1.0 - self.weight) * self.p1.value(direction)
     }
 
-    fn generate(&self, rng: &mut rand::prelude::ThreadRng) -> Vec3 {
-        if rng.gen::<f32>() < 0.5 {
+    fn generate(&self, rng: &mut crate::rng::RenderRng) -> Vec3 {
+        if rng.gen::<f64>() < self.weight {
             self.p0.generate(rng)
         } else {
             self.p1.generate(rng)
@@ -121,7 +261,7 @@ impl Pdf for MixturePdf {
 
 /// Utility function for sphere PDF calculation.
 pub fn random_to_sphere(
-    rng: &mut rand::rngs::ThreadRng,
+    rng: &mut crate::rng::RenderRng,
     radius: f64,
     distance_squared: f64,
 ) -> Vec3 {
@@ -1,14 +1,14 @@
 //! Generate Perlin noise textures.
 
 use crate::vec3::{Point3, Vec3};
-use rand::Rng;
 
 /// Type of noise.
 ///
 /// Perlin noise is filtered to get different appearances.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub enum NoiseType {
     /// Unfiltered noise.
+    #[default]
     Square,
     /// Smooth interpolation with random unit vectors.
     Smooth,
@@ -22,7 +22,6 @@ pub enum NoiseType {
 /// Perlin noise generator.
 #[derive(Clone, Debug, Default)]
 pub struct Perlin {
-    point_count: usize,
     ranfloat: Vec<f64>,
     ranvec: Vec<Vec3>,
     perm_x: Vec<i32>,
@@ -32,12 +31,19 @@ pub struct Perlin {
 
 impl Perlin {
     /// Initialize Perlin noise texture.
+    ///
+    /// Every generator made this way has the same noise, so renders are
+    /// reproducible. Use [`Perlin::from_rng`] for different noise.
     pub fn new() -> Self {
+        Self::from_rng(&mut crate::rng::scene_rng(0))
+    }
+
+    /// Initialize Perlin noise texture with random numbers from `rng`.
+    pub fn from_rng<R: rand::Rng>(rng: &mut R) -> Self {
         let point_count = 256;
-        let mut rng = rand::thread_rng();
 
         fn perlin_generate_permute<R: rand::Rng>(rng: &mut R, point_count: usize) -> Vec<i32> {
-            let mut p: Vec<i32> = (0..256).map(|i| i).collect();
+            let mut p: Vec<i32> = (0..256).collect();
             for i in (1..point_count).rev() {
                 let target = rng.gen_range(0, i);
                 p.swap(i, target);
@@ -47,18 +53,19 @@ impl Perlin {
         }
 
         Self {
-            point_count,
             ranfloat: (0..256).map(|_| rng.gen()).collect(),
             ranvec: (0..256)
-                .map(|_| Vec3::random_range(&mut rng, -1.0, 1.0).unit_vector())
+                .map(|_| Vec3::random_range(rng, -1.0, 1.0).unit_vector())
                 .collect(),
-            perm_x: perlin_generate_permute(&mut rng, point_count),
-            perm_y: perlin_generate_permute(&mut rng, point_count),
-            perm_z: perlin_generate_permute(&mut rng, point_count),
+            perm_x: perlin_generate_permute(rng, point_count),
+            perm_y: perlin_generate_permute(rng, point_count),
+            perm_z: perlin_generate_permute(rng, point_count),
         }
     }
 
     /// Hash the generated noise.
+    // The corner offsets index the lattice as well as `c`
+    #[allow(clippy::needless_range_loop)]
     pub fn noise(&self, p: &crate::vec3::Point3, noise: NoiseType) -> f64 {
         match noise {
             NoiseType::Square => {
@@ -89,8 +96,8 @@ impl Perlin {
                     for dj in 0..2 {
                         for dk in 0..2 {
                             c[di][dj][dk] = self.ranfloat[(self.perm_x[(i as usize + di) & 255]
-                                ^ self.perm_y[((j as usize + dj) & 255)]
-                                ^ self.perm_z[((k as usize + dk) & 255)])
+                                ^ self.perm_y[(j as usize + dj) & 255]
+                                ^ self.perm_z[(k as usize + dk) & 255])
                                 as usize];
                         }
                     }
@@ -117,8 +124,8 @@ impl Perlin {
                     for dj in 0..2 {
                         for dk in 0..2 {
                             c[di][dj][dk] = self.ranvec[(self.perm_x[(i as usize + di) & 255]
-                                ^ self.perm_y[((j as usize + dj) & 255)]
-                                ^ self.perm_z[((k as usize + dk) & 255)])
+                                ^ self.perm_y[(j as usize + dj) & 255]
+                                ^ self.perm_z[(k as usize + dk) & 255])
                                 as usize];
                         }
                     }
@@ -130,6 +137,8 @@ impl Perlin {
     }
 
     #[inline]
+    // The corner offsets index the lattice as well as `c`
+    #[allow(clippy::needless_range_loop)]
     fn perlin_interp(c: [[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
         let mut acc = 0.0;
         for i in 0..2 {
@@ -147,8 +156,14 @@ impl Perlin {
         acc
     }
 
-    /// Turbulence noise calculation.
+    /// Turbulence noise calculation, always non-negative.
     pub fn turb(&self, p: &Point3, depth: u32, noise_type: NoiseType) -> f64 {
+        self.turb_signed(p, depth, noise_type).abs()
+    }
+
+    /// Turbulence noise calculation keeping its sign, for displacement or flow
+    /// effects.
+    pub fn turb_signed(&self, p: &Point3, depth: u32, noise_type: NoiseType) -> f64 {
         let mut accum = 0.0;
         let mut temp_p = *p;
         let mut weight = 1.0;
@@ -159,18 +174,14 @@ impl Perlin {
             temp_p *= 2.0;
         }
 
-        accum.abs()
-    }
-}
-
-impl core::default::Default for NoiseType {
-    fn default() -> Self {
-        Self::Square
+        accum
     }
 }
 
 /// Trilinear interpolation.
 #[inline]
+// The corner offsets index the lattice as well as `c`
+#[allow(clippy::needless_range_loop)]
 pub fn trilinear_interp(c: [[[f64; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
     let mut acc = 0.0;
     for i in 0..2 {
@@ -186,3 +197,23 @@ pub fn trilinear_interp(c: [[[f64; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
 
     acc
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_turbulence_goes_negative_but_turb_never_does() {
+        let perlin = Perlin::from_rng(&mut crate::rng::scene_rng(1));
+        let mut negative = false;
+        for i in 0..1000 {
+            let p = Point3::new(f64::from(i) * 0.173, f64::from(i % 7) * 0.31, 0.5);
+            let signed = perlin.turb_signed(&p, 7, NoiseType::Smooth);
+            let turb = perlin.turb(&p, 7, NoiseType::Smooth);
+            negative |= signed < 0.0;
+            assert!(turb >= 0.0);
+            assert_eq!(turb, signed.abs());
+        }
+        assert!(negative);
+    }
+}
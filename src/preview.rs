@@ -0,0 +1,72 @@
+//! Live preview of a render as it converges. Requires the `preview` feature.
+
+use minifb::{Key, Window, WindowOptions};
+
+use crate::camera::Camera;
+use crate::render::{accumulate_with_coverage, RenderConfig};
+use crate::vec3::{Color, ColorU8};
+use crate::world::World;
+
+/// What a preview rendered before it finished or was stopped.
+#[derive(Clone, Debug)]
+pub struct Preview {
+    /// Averaged linear colors, top row first.
+    pub colors: Vec<Color>,
+    /// Fraction of each pixel's samples whose camera ray hit an object, as
+    /// from [`render_linear_with_coverage`](crate::render::render_linear_with_coverage).
+    pub coverage: Vec<f64>,
+    /// Number of passes finished, each adding a sample to every pixel.
+    pub passes: u32,
+}
+
+/// Render progressively, one sample per pixel per pass, showing the running
+/// average in a window after every pass.
+///
+/// Closing the window or pressing Escape stops the render early, returning
+/// what was rendered so far.
+pub fn run_preview(
+    config: &RenderConfig,
+    world: &World,
+    cam: &Camera,
+) -> Result<Preview, Box<dyn std::error::Error>> {
+    let (img_w, img_h) = (config.img_w as usize, config.img_h as usize);
+    let mut window = Window::new("rtiow", img_w, img_h, WindowOptions::default())?;
+    let mut accum = vec![(Color::new_with(0.0), 0.0); img_w * img_h];
+    let mut buffer = vec![0u32; img_w * img_h];
+    let mut passes = 0;
+
+    while passes < config.samples && window.is_open() && !window.is_key_down(Key::Escape) {
+        let pass_config = RenderConfig {
+            seed: config.seed.wrapping_add(u64::from(passes)),
+            ..config.clone()
+        };
+        accumulate_with_coverage(&pass_config, world, cam, &mut accum);
+        passes += 1;
+
+        // Window pixels are packed as 0RGB
+        for (px, (color, _)) in buffer.iter_mut().zip(&accum) {
+            let ColorU8(r, g, b) = color.into_u8_color_tonemapped(
+                f64::from(passes),
+                config.exposure,
+                config.tone_map,
+                0,
+                0,
+                0.0,
+            );
+            *px = u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b);
+        }
+        window.update_with_buffer(&buffer, img_w, img_h)?;
+        eprint!("\rPasses finished: {}/{}   ", passes, config.samples);
+    }
+
+    let scale = f64::from(passes.max(1)).recip();
+    let (colors, coverage) = accum
+        .into_iter()
+        .map(|(color, coverage)| (color * scale, coverage * scale))
+        .unzip();
+    Ok(Preview {
+        colors,
+        coverage,
+        passes,
+    })
+}
@@ -1,7 +1,7 @@
 //! Vector that simulates the path of light in a scene.
 
-use crate::hittable::{HitRecord, Hittable};
-use crate::material::ScatterRecord;
+use crate::hittable::HitRecord;
+use crate::material::{Diel, Material, ScatterRecord};
 use crate::pdf::Pdf;
 use crate::vec3::{Color, Point3, Vec3};
 
@@ -41,58 +41,681 @@ impl Ray {
     pub fn at(&self, t: f64) -> Point3 {
         self.orig + self.dir * t
     }
+
+    /// Return the nearest `t` past `t` where a hit is clear of the surface at
+    /// `self.at(t)`, such as to look for where the ray leaves an object it
+    /// enters there.
+    ///
+    /// The step grows with the size of the coordinates, as their rounding
+    /// does, so it holds the same in scenes of any scale without tuning.
+    pub fn step_past(&self, t: f64) -> f64 {
+        let size = self.orig.length().max(self.at(t).length());
+        t + (STEP_PAST * size).max(f64::MIN_POSITIVE) * self.dir.length().recip()
+    }
+}
+
+/// Size of [`Ray::step_past`] relative to the coordinates, far above their
+/// rounding but far below the features of any scene.
+const STEP_PAST: f64 = 1e-9;
+
+/// Longest path counted separately by [`take_path_lengths`].
+#[cfg(feature = "stats")]
+pub const MAX_COUNTED_BOUNCES: usize = 64;
+
+#[cfg(feature = "stats")]
+static PATH_LENGTHS: [std::sync::atomic::AtomicU64; MAX_COUNTED_BOUNCES + 1] =
+    [const { std::sync::atomic::AtomicU64::new(0) }; MAX_COUNTED_BOUNCES + 1];
+
+/// Return how many paths traced by `ray_color` on any thread since the last
+/// call ended after each number of bounces, resetting the counts.
+///
+/// Index `i` counts paths which scattered `i` times, with longer paths counted
+/// at `MAX_COUNTED_BOUNCES`. Paths cut off by the depth limits are counted at
+/// the number of bounces they made. Trailing zeros are trimmed.
+#[cfg(feature = "stats")]
+pub fn take_path_lengths() -> Vec<u64> {
+    let mut lengths: Vec<u64> = PATH_LENGTHS
+        .iter()
+        .map(|count| count.swap(0, std::sync::atomic::Ordering::Relaxed))
+        .collect();
+    while lengths.last() == Some(&0) {
+        lengths.pop();
+    }
+    lengths
+}
+
+/// Record the end of a path after `bounces` scatters.
+#[cfg(feature = "stats")]
+fn end_path(bounces: u32) {
+    PATH_LENGTHS[(bounces as usize).min(MAX_COUNTED_BOUNCES)]
+        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
 }
 
 /// Color produced by a ray bounce.
+///
+/// Diffuse bounces pick their next direction towards `ctx.lights` with
+/// probability `ctx.light_weight`, and from the material otherwise. `0.5` suits
+/// most scenes. Raise it, e.g. to `0.9`, when small bright lights cover a tiny
+/// solid angle and account for most of the lighting, since material samples
+/// rarely find them. Lower it when most light arrives indirectly. It must stay
+/// below `1.0` for light that doesn't come straight from the lights to be
+/// gathered. With no lights, every diffuse bounce samples its material.
+///
+/// Hits closer than `ctx.t_min` along each ray are ignored, see
+/// [`RenderConfig::t_min`](crate::render::RenderConfig::t_min).
+///
+/// A sun in the background is sampled directly from diffuse bounces, like the
+/// spotlights.
+///
+/// The ray is assumed to start outside every dielectric. Refraction between
+/// nested or overlapping dielectrics uses the indices on either side of each
+/// boundary, following `Diel::priority` where they overlap.
+///
+/// The path is followed in a loop, scaling the light gathered at each bounce
+/// by the `throughput` of the bounces before it. A path whose throughput drops
+/// to zero is still followed to the end, drawing the same random numbers as
+/// any other, so the samples after it are the same for a given seed.
+///
+/// `ctx.clamp_indirect` caps the brightest channel of light which scattered
+/// more than once before reaching the camera, keeping its hue, and drops it
+/// when it isn't finite. This tames fireflies and infinities from caustics and
+/// near-singular pdfs, but darkens the image, so it biases the result. Direct
+/// lighting is never clamped.
+///
+/// With no `ctx.specular_depth`, a path stops after `ctx.max_depth` bounces of
+/// any kind. Otherwise only diffuse bounces count against `max_depth`, while
+/// specular ones count against `specular_depth`, so long chains through glass
+/// can keep going in scenes that only need a few diffuse bounces.
 pub fn ray_color(
-    rng: &mut rand::rngs::ThreadRng,
+    rng: &mut crate::rng::RenderRng,
     r: &Ray,
-    background: &Color,
-    world: &dyn Hittable,
-    lights: std::sync::Arc<dyn Hittable + Send + Sync>,
-    max_depth: u32,
+    ctx: &crate::render::RenderContext<'_>,
 ) -> Color {
+    traced_color(rng, r, ctx).0
+}
+
+/// [`ray_color`], along with whether `r` itself struck anything, counted from
+/// the same path so a pixel's coverage matches its color.
+pub(crate) fn traced_color(
+    rng: &mut crate::rng::RenderRng,
+    r: &Ray,
+    ctx: &crate::render::RenderContext<'_>,
+) -> (Color, bool) {
+    let world = ctx.world;
+    let t_min = ctx.t_min;
+    // Light which has scattered `scatters` times on its way to the camera
+    let gathered = |light: Color, scatters: u32| match ctx.clamp_indirect {
+        Some(max) if scatters > 1 => clamp_radiance(light, max),
+        _ => light,
+    };
+    let mut radiance = Color::new_with(0.0);
+    let mut throughput = Color::new_with(1.0);
+    let mut ray = *r;
+    let mut enclosing = Enclosing::default();
     let mut rec = HitRecord::default();
+    let mut struck = false;
+    let sun = ctx.background.sun();
+    // Whether the sun was sampled directly from the last bounce
+    let mut sampled_sun = false;
+    // Distance from the camera, for filtering textures
+    let mut path_length = 0.0;
+    // How far rough bounces have spread the path, for filtering the background
+    let mut spread = 0.0;
+    let (mut diffuse_bounces, mut specular_bounces) = (0, 0);
+    let depth_left = |diffuse: u32, specular: u32| match ctx.specular_depth {
+        Some(specular_depth) => diffuse < ctx.max_depth && specular < specular_depth,
+        None => diffuse + specular < ctx.max_depth,
+    };
+
+    while depth_left(diffuse_bounces, specular_bounces) {
+        let bounce = diffuse_bounces + specular_bounces;
+        // If the ray misses everything, add the background color
+        if !world.hit(&ray, t_min, f64::INFINITY, &mut rec) {
+            #[cfg(feature = "stats")]
+            end_path(bounce);
+            let background = if sampled_sun {
+                ctx.background.color_without_sun(&ray.direction())
+            } else {
+                ctx.background.filtered_color(&ray.direction(), spread)
+            };
+            return (radiance + gathered(throughput * background, bounce), struck);
+        }
+        struck = true;
+
+        let length = ray.direction().length();
+        path_length += rec.t * length;
+        let cos_incidence = ray.direction().dot(&rec.normal).abs() / length;
+        crate::texture::set_path_length(path_length / cos_incidence.max(0.01));
+
+        // Glass absorbs along each segment a path travels through it. A path
+        // leaving glass it never entered started inside of it.
+        let medium = match (enclosing.medium(), &*rec.material) {
+            (Some(diel), _) => Some(diel),
+            (None, Material::Dielectric(diel)) if !rec.front_face => Some(diel),
+            _ => None,
+        };
+        if let Some(diel) = medium {
+            throughput *= (-diel.absorption * (rec.t * length)).exp();
+        }
+
+        let mut srec = ScatterRecord::default();
+        radiance += gathered(throughput * rec.material.emitted(&ray, &rec), bounce);
+        let scatters = match &*rec.material {
+            Material::Dielectric(diel) => {
+                match enclosing.boundary(rec.object, diel, rec.front_face) {
+                    Some(etai_over_etat) => {
+                        diel.scatter(rng, &ray, &rec, etai_over_etat, &mut srec);
+                        // Only a refracted ray ends up on the far side
+                        let transmitted = srec
+                            .specular_ray
+                            .is_some_and(|ray| ray.direction().dot(&rec.normal) < 0.0);
+                        if transmitted {
+                            enclosing.cross(rec.object, diel, rec.front_face);
+                        }
+                    }
+                    None => {
+                        // Carry on through a boundary hidden by a higher
+                        // priority medium
+                        enclosing.cross(rec.object, diel, rec.front_face);
+                        srec.attenuation = Color::new_with(1.0);
+                        srec.specular_ray = Some(Ray::new(rec.p, ray.direction(), ray.time()));
+                    }
+                }
+                true
+            }
+            material => material.scatter(rng, &ray, &rec, &mut srec),
+        };
+        if !scatters {
+            #[cfg(feature = "stats")]
+            end_path(bounce);
+            return (radiance, struck);
+        }
+        spread += srec.spread;
+
+        if let Some(specular_ray) = srec.specular_ray {
+            throughput *= srec.attenuation;
+            ray = specular_ray;
+            sampled_sun = false;
+            specular_bounces += 1;
+        } else {
+            // Without lights, scatter from the material alone
+            let material_pdf = srec.pdf_ptr.unwrap();
+            let mixture;
+            let p: &dyn Pdf = match ctx.lights {
+                Some(lights) => {
+                    let light_ptr =
+                        std::sync::Arc::new(crate::pdf::HittablePdf::new(&rec.p, lights.clone()));
+                    mixture = crate::pdf::MixturePdf::new(light_ptr, material_pdf)
+                        .with_weight(ctx.light_weight);
+                    &mixture
+                }
+                None => material_pdf.as_ref(),
+            };
+
+            // Delta lights can't be hit by scattered rays, so sample each one
+            // directly
+            let mut direct = ctx
+                .spot_lights
+                .iter()
+                .fold(Color::new_with(0.0), |sum, spot| {
+                    let to_light = spot.position - rec.p;
+                    let distance = to_light.length();
+                    let shadow_ray = Ray::new(rec.p, to_light / distance, ray.time());
+                    if world.occluded(&shadow_ray, t_min, distance - t_min) {
+                        return sum;
+                    }
+                    sum + rec.material.scattering_pdf(rng, &ray, &rec, &shadow_ray)
+                        * spot.incident(&rec.p)
+                });
+            // The same goes for the sun, which the scattered ray then ignores
+            if let Some((direction, irradiance)) = sun {
+                let shadow_ray = Ray::new(rec.p, direction, ray.time());
+                if !world.occluded(&shadow_ray, t_min, f64::INFINITY) {
+                    direct +=
+                        rec.material.scattering_pdf(rng, &ray, &rec, &shadow_ray) * irradiance;
+                }
+            }
+            sampled_sun = sun.is_some();
+            throughput *= srec.attenuation;
+            radiance += gathered(throughput * direct, bounce + 1);
+
+            let scattered = Ray::new(rec.p, p.generate(rng), ray.time());
+            let pdf_val = p.value(&scattered.direction());
+            // Directions with no density, like those from a light sampled
+            // in its own plane, carry nothing
+            if pdf_val.is_nan() || pdf_val <= 0.0 {
+                #[cfg(feature = "stats")]
+                end_path(bounce + 1);
+                return (radiance, struck);
+            }
+            throughput = throughput
+                * rec.material.scattering_pdf(rng, &ray, &rec, &scattered)
+                * pdf_val.recip();
+            ray = scattered;
+            diffuse_bounces += 1;
+        }
+    }
 
     // Stop gathering light when bounce limit reached
-    if max_depth == 0 {
+    #[cfg(feature = "stats")]
+    end_path(diffuse_bounces + specular_bounces);
+    (radiance, struck)
+}
+
+/// Scale `light` down so no channel is above `max`, or drop it when it isn't
+/// finite.
+fn clamp_radiance(light: Color, max: f64) -> Color {
+    if !(light.x().is_finite() && light.y().is_finite() && light.z().is_finite()) {
         return Color::new_with(0.0);
     }
 
-    // If the ray misses everything, return the background color
-    if !world.hit(r, 0.001, f64::INFINITY, &mut rec) {
-        return *background;
+    let peak = light.x().max(light.y()).max(light.z());
+    if peak > max {
+        light * (max / peak)
+    } else {
+        light
     }
+}
 
-    let mut srec = ScatterRecord::default();
-    let emitted = rec.material.emitted(r, &rec);
-    if !rec.material.scatter(rng, &r, &rec, &mut srec) {
-        return emitted;
+/// Dielectric objects a path is inside of, by [`HitRecord::object`], in the
+/// order they were entered.
+#[derive(Default)]
+struct Enclosing(Vec<(usize, Diel)>);
+
+impl Enclosing {
+    /// Position of the medium that decides the refraction index at the path's
+    /// position, the most recently entered of the highest priority, leaving
+    /// out the one at `skip`.
+    fn top(&self, skip: Option<usize>) -> Option<usize> {
+        (0..self.0.len())
+            .filter(|&i| Some(i) != skip)
+            .max_by_key(|&i| (self.0[i].1.priority, i))
+    }
+
+    /// Refraction index at the path's position, leaving out the medium at
+    /// `skip`.
+    fn refraction_index(&self, skip: Option<usize>) -> f64 {
+        self.top(skip).map_or(1.0, |i| self.0[i].1.refraction_index)
+    }
+
+    fn position(&self, object: usize) -> Option<usize> {
+        self.0.iter().rposition(|&(o, _)| o == object)
+    }
+
+    /// Medium filling the space the path is travelling through.
+    fn medium(&self) -> Option<&Diel> {
+        self.top(None).map(|i| &self.0[i].1)
     }
-    if let Some(_) = srec.specular_ray {
-        return srec.attenuation
-            * ray_color(
-                rng,
-                &srec.specular_ray.unwrap(),
-                background,
-                world,
-                lights,
-                max_depth - 1,
+
+    /// Ratio of refraction indices from the incoming side over the far side
+    /// at a boundary of `diel`, or `None` when a higher priority medium hides
+    /// the boundary.
+    fn boundary(&self, object: usize, diel: &Diel, front_face: bool) -> Option<f64> {
+        if front_face {
+            if self
+                .top(None)
+                .is_some_and(|i| self.0[i].1.priority > diel.priority)
+            {
+                return None;
+            }
+            return Some(self.refraction_index(None) / diel.refraction_index);
+        }
+
+        match self.position(object) {
+            // Leaving a medium that was entered, into whatever is left
+            Some(i) if self.top(None) == Some(i) => {
+                Some(diel.refraction_index / self.refraction_index(Some(i)))
+            }
+            Some(_) => None,
+            // Leaving a medium the path started in
+            None => Some(diel.refraction_index / self.refraction_index(None)),
+        }
+    }
+
+    /// Track a path crossing a boundary of `diel`, whether it's real or not.
+    fn cross(&mut self, object: usize, diel: &Diel, front_face: bool) {
+        if front_face {
+            self.0.push((object, diel.clone()));
+        } else if let Some(i) = self.position(object) {
+            self.0.remove(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::aarect::{AaRect, Plane};
+    use crate::hittable::{Hittable, HittableList, Sphere};
+    use crate::light::SpotLight;
+    use crate::material::{Lambert, Metal};
+    use crate::render::{RenderConfig, RenderContext};
+    use crate::texture::SolidColor;
+    use crate::world::World;
+
+    /// Floor through the origin, with a wall a hair's breadth from the origin
+    /// towards +X when `walled`. Every length is multiplied by `scale`.
+    fn floor(scale: f64, walled: bool) -> HittableList {
+        let grey = Arc::new(Material::Lambertian(Lambert::new(Arc::new(
+            SolidColor::new_with(0.5),
+        ))));
+        let mut objects = HittableList::new();
+        objects.add(Arc::new(AaRect::new(
+            -scale,
+            scale,
+            -scale,
+            scale,
+            0.0,
+            grey.clone(),
+            Plane::Xz,
+        )));
+        if walled {
+            objects.add(Arc::new(AaRect::new(
+                0.0,
+                0.1 * scale,
+                -0.1 * scale,
+                0.1 * scale,
+                0.0015 * scale,
+                grey,
+                Plane::Yz,
+            )));
+        }
+        objects
+    }
+
+    /// Radiance seen looking straight down at the origin of `world`, averaged
+    /// over `samples` paths, with `t_min` in step with the scene's `scale`.
+    fn seen_at_origin(world: &World, scale: f64, t_min: f64, samples: u32) -> Color {
+        let config = RenderConfig {
+            t_min,
+            ..RenderConfig::new(1, 1)
+        };
+        let ctx = RenderContext::new(&config, world);
+        let r = Ray::new(Point3::new(0.0, scale, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let mut rng = crate::rng::pixel_rng(1, 0);
+        let total: Color = (0..samples).map(|_| ray_color(&mut rng, &r, &ctx)).sum();
+        total / f64::from(samples)
+    }
+
+    /// The floor lit by a low spotlight at +X, behind the wall when `walled`.
+    fn floor_under_spotlight(scale: f64, t_min: f64, walled: bool) -> Color {
+        let world =
+            World::without_lights(floor(scale, walled)).with_spot_lights(vec![SpotLight::new(
+                Point3::new(2.0 * scale, 0.2 * scale, 0.0),
+                Vec3::new(-1.0, -0.1, 0.0),
+                Color::new_with(1.0),
+                20.0,
+                30.0,
+                scale * scale,
+            )]);
+        seen_at_origin(&world, scale, t_min, 1)
+    }
+
+    /// The floor lit by a low, sampled area light at +X facing the origin,
+    /// behind the wall when `walled`. It's near enough that `t_min`, counted
+    /// in multiples of the sampled directions, still falls short of the wall.
+    fn floor_under_area_light(scale: f64, t_min: f64, walled: bool) -> Color {
+        let light = Arc::new(AaRect::new(
+            0.02 * scale,
+            0.08 * scale,
+            -0.05 * scale,
+            0.05 * scale,
+            0.5 * scale,
+            Arc::new(Material::DiffLight(crate::material::DiffuseLight::new(
+                Arc::new(SolidColor::new_with(10.0)),
+            ))),
+            Plane::Yz,
+        ));
+        let mut objects = floor(scale, walled);
+        objects.add(Arc::new(crate::hittable::FlipFace::new(light.clone())));
+        seen_at_origin(&World::new(objects, light), scale, t_min, 256)
+    }
+
+    #[test]
+    fn shadows_hold_at_every_scale() {
+        // The first book's unit scenes and the 555 unit Cornell box with the
+        // default `t_min`, and a tiny scene with it scaled down to match
+        for &(scale, t_min) in &[(1.0, 0.001), (555.0, 0.001), (1e-3, 1e-6)] {
+            for light in &[floor_under_spotlight, floor_under_area_light] {
+                let lit = light(scale, t_min, false);
+                let shadowed = light(scale, t_min, true);
+                assert!(lit.x() > 0.0, "unlit floor at scale {}", scale);
+                assert!(
+                    shadowed.x() < 0.05 * lit.x(),
+                    "wall casts no shadow at scale {}: {:?} vs {:?}",
+                    scale,
+                    shadowed,
+                    lit
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sampling_a_light_from_its_own_plane_stays_finite() {
+        // Points on the paper sample directions in its plane, where the
+        // mixture pdf is zero
+        let (cam, objects, lights) =
+            crate::scene::third::cornell_diffuser(&mut crate::rng::scene_rng(1), 8, 8).unwrap();
+        let config = RenderConfig {
+            samples: 4,
+            threads: false,
+            seed: 1,
+            ..RenderConfig::new(8, 8)
+        };
+        let image = crate::render::render_linear(&config, &World::new(objects, lights), &cam);
+        for color in image {
+            assert!(
+                color.x().is_finite() && color.y().is_finite() && color.z().is_finite(),
+                "pixel {:?} isn't finite",
+                color
             );
+        }
     }
 
-    let light_ptr = std::sync::Arc::new(crate::pdf::HittablePdf::new(&rec.p, lights.clone()));
-    let p = crate::pdf::MixturePdf {
-        p0: light_ptr,
-        p1: srec.pdf_ptr.unwrap(),
-    };
+    /// `ray_color` written recursively, as it was before following paths in a
+    /// loop, for scenes without lights, spotlights or nested glass.
+    fn recursive_color(
+        rng: &mut crate::rng::RenderRng,
+        r: &Ray,
+        world: &HittableList,
+        background: Color,
+        depth: u32,
+    ) -> Color {
+        let mut rec = HitRecord::default();
+        if depth == 0 {
+            return Color::new_with(0.0);
+        }
+        if !world.hit(r, 0.001, f64::INFINITY, &mut rec) {
+            return background;
+        }
+
+        let mut srec = ScatterRecord::default();
+        let emitted = rec.material.emitted(r, &rec);
+        if !rec.material.scatter(rng, r, &rec, &mut srec) {
+            return emitted;
+        }
+        if let Some(specular_ray) = srec.specular_ray {
+            return emitted
+                + srec.attenuation
+                    * recursive_color(rng, &specular_ray, world, background, depth - 1);
+        }
+
+        let p = srec.pdf_ptr.unwrap();
+        let scattered = Ray::new(rec.p, p.generate(rng), r.time());
+        let pdf_val = p.value(&scattered.direction());
+        emitted
+            + srec.attenuation
+                * rec.material.scattering_pdf(rng, r, &rec, &scattered)
+                * recursive_color(rng, &scattered, world, background, depth - 1)
+                * pdf_val.recip()
+    }
+
+    #[test]
+    fn depth_limited_paths_match_the_recursive_version() {
+        let lambertian =
+            |albedo| Material::Lambertian(Lambert::new(Arc::new(SolidColor::new_with(albedo))));
+        let mut objects = HittableList::new();
+        objects.add(Arc::new(Sphere::new(
+            Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            lambertian(0.5),
+        )));
+        // Black, so paths off it carry nothing but still draw random numbers
+        objects.add(Arc::new(Sphere::new(
+            Point3::new(-2.0, 1.0, 0.0),
+            1.0,
+            lambertian(0.0),
+        )));
+        objects.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 1.0, 0.0),
+            1.0,
+            Material::Dielectric(Diel::new(1.5)),
+        )));
+        objects.add(Arc::new(Sphere::new(
+            Point3::new(2.0, 1.0, 0.0),
+            1.0,
+            Material::Metallic(Metal::new(Color::new(0.7, 0.6, 0.5), 0.3)),
+        )));
+        let background = Color::new(0.7, 0.8, 1.0);
+        let world = World::without_lights(objects);
+        let config = RenderConfig {
+            background: crate::render::Background::Solid(background),
+            max_depth: 8,
+            ..RenderConfig::new(1, 1)
+        };
+        let ctx = RenderContext::new(&config, &world);
+
+        // Share each generator across every ray, like the samples of a pixel
+        let mut looped = crate::rng::pixel_rng(7, 0);
+        let mut recursed = crate::rng::pixel_rng(7, 0);
+        let origin = Point3::new(0.0, 1.0, 6.0);
+        for i in 0..256 {
+            let x = f64::from(i % 16) / 15.0 * 6.0 - 3.0;
+            let y = f64::from(i / 16) / 15.0 * 3.0 - 0.5;
+            let r = Ray::new(origin, Point3::new(x, y, 0.0) - origin, 0.0);
+            let expected = recursive_color(&mut recursed, &r, &world.objects, background, 8);
+            let actual = ray_color(&mut looped, &r, &ctx);
+            assert!(
+                (actual - expected).length() <= 1e-9 * (1.0 + expected.length()),
+                "ray {}: {:?} vs {:?}",
+                i,
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn enclosing_tells_apart_objects_sharing_a_material() {
+        // Two overlapping objects of the same glass, left in the order they
+        // were entered
+        let glass = Diel::new(1.5);
+        let mut enclosing = Enclosing::default();
+        enclosing.cross(1, &glass, true);
+        enclosing.cross(2, &glass, true);
+        enclosing.cross(1, &glass, false);
+        assert_eq!(enclosing.position(1), None);
+        assert_eq!(enclosing.position(2), Some(0));
+    }
+
+    #[test]
+    fn glass_absorbs_through_hidden_boundaries() {
+        // A clear sphere hidden inside an absorbing one, neither bending light
+        let mut objects = HittableList::new();
+        objects.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            2.0,
+            Material::Dielectric(
+                Diel::new(1.0)
+                    .with_absorption(Color::new_with(0.5))
+                    .with_priority(1),
+            ),
+        )));
+        objects.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Material::Dielectric(Diel::new(1.0)),
+        )));
+        let world = World::without_lights(objects);
+        let config = RenderConfig {
+            background: crate::render::Background::Solid(Color::new_with(1.0)),
+            ..RenderConfig::new(1, 1)
+        };
+        let ctx = RenderContext::new(&config, &world);
 
-    let scattered = Ray::new(rec.p, p.generate(rng), r.time());
-    let pdf_val = p.value(&scattered.direction());
+        let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let color = ray_color(&mut crate::rng::pixel_rng(1, 0), &r, &ctx);
+        // Four units through the outer sphere's glass
+        let expected = (-2.0f64).exp();
+        assert!(
+            (color.x() - expected).abs() < 1e-9,
+            "{:?} isn't {}",
+            color,
+            expected
+        );
+    }
+
+    #[test]
+    fn scenes_without_lights_are_lit_by_the_background() {
+        // A sky fading from white at the horizon to blue overhead
+        let sky = crate::environment::EnvironmentMap::from_colors(
+            1,
+            2,
+            vec![Color::new(0.5, 0.7, 1.0), Color::new_with(1.0)],
+        )
+        .unwrap();
+        let mut objects = HittableList::new();
+        objects.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Material::Lambertian(Lambert::new(Arc::new(SolidColor::new_with(0.5)))),
+        )));
+        let cam = crate::camera::Camera::new(
+            Point3::new(0.0, 0.0, 5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            5.0,
+            0.0,
+            1.0,
+        );
+        let config = RenderConfig {
+            samples: 16,
+            threads: false,
+            seed: 1,
+            background: crate::render::Background::Environment(Arc::new(sky)),
+            ..RenderConfig::new(16, 16)
+        };
+        let image =
+            crate::render::render_linear(&config, &World::without_lights(objects.clone()), &cam);
 
-    emitted
-        + srec.attenuation
-            * rec.material.scattering_pdf(rng, r, &rec, &scattered)
-            * ray_color(rng, &scattered, background, world, lights, max_depth - 1)
-            * pdf_val.recip()
+        for color in &image {
+            assert!(color.x().is_finite() && color.y().is_finite() && color.z().is_finite());
+        }
+        // Half the albedo of a sky no brighter than white, but not black
+        let center = image[8 * 16 + 8];
+        assert!(
+            center.x() > 0.0 && center.z() > 0.0 && center.x() <= 0.5 && center.z() <= 0.5,
+            "{:?}",
+            center
+        );
+
+        // An empty list of lights is the same as none, whether it's passed to
+        // `World::new` or set afterwards
+        let empty: Arc<dyn Hittable + Send + Sync> = Arc::new(HittableList::new());
+        let mut world = World::new(objects.clone(), empty.clone());
+        assert!(world.lights.is_none());
+        world.lights = Some(empty);
+        let with_empty = crate::render::render_linear(&config, &world, &cam);
+        let components = |c: &Color| [c.x(), c.y(), c.z()];
+        assert!(image
+            .iter()
+            .zip(&with_empty)
+            .all(|(a, b)| components(a) == components(b)));
+    }
 }
@@ -1,4 +1,5 @@
-//! Rendering functions for ray tracing. Files are written out in PPM format.
+//! Rendering functions for ray tracing. Files are written out in PPM or PNG
+//! format.
 
 use std::io::Write;
 
@@ -7,80 +8,1884 @@ use rand::Rng;
 use rayon::prelude::*;
 
 use crate::camera::Camera;
-use crate::hittable::Hittable;
-use crate::ray::ray_color;
-use crate::vec3::Color;
+use crate::conversion::ToneMap;
+use crate::hittable::{ConstantMedium, Hittable, HittableList};
+use crate::light::SpotLight;
+use crate::sampler::{cmj, Jitter, Sampler};
+use crate::vec3::{Color, ColorU8, ColorU8A, Vec3};
+use crate::world::World;
 
-/// Run ray tracing in a single thread.
-pub fn run_single_ppm<W: Write>(
+/// What the color of each pixel represents.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum RenderMode {
+    /// Regular path traced image.
+    #[default]
+    Shaded,
+    /// Fraction of cosine weighted rays from the first hit which travel
+    /// `distance` without striking anything, ignoring materials and lights.
+    AmbientOcclusion {
+        /// Number of occlusion rays cast per camera ray.
+        samples: u32,
+        /// Length at which an occlusion ray counts as unoccluded.
+        distance: f64,
+    },
+    /// Regular path traced image seen through participating media which are
+    /// ray-marched with single scattering, bringing out light shafts.
+    ///
+    /// Only `ConstantMedium`s at the top level of the world are marched. Light
+    /// bouncing between surfaces passes through them unaffected.
+    Volumetric {
+        /// Distance between samples along a ray in world units, which must be
+        /// positive. Smaller steps resolve finer shafts at a higher cost.
+        step: f64,
+    },
+    /// Number of `BvhNode`s visited by each primary ray, ramping from blue at
+    /// zero visits to red at `max_visits` or more.
+    #[cfg(feature = "stats")]
+    Heatmap {
+        /// Visit count that maps to the hottest color.
+        max_visits: u32,
+    },
+}
+
+/// Order in which [`render_with`] works through the image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TileOrder {
+    /// A row at a time, top row first.
+    #[default]
+    Scanline,
+    /// Square tiles spiraling out from the center, so the middle of the image,
+    /// where the subject usually is, fills in first.
+    Spiral,
+    /// Square tiles along a Hilbert curve, each tile next to the one before.
+    Hilbert,
+}
+
+/// Width and height in pixels of the tiles of `TileOrder::Spiral` and
+/// `TileOrder::Hilbert`.
+pub const TILE_SIZE: u32 = 16;
+
+impl TileOrder {
+    /// Split an image into tiles `(x0, y0, x1, y1)`, with rows counting down
+    /// from the top, in the order they should be rendered.
+    pub fn tiles(self, img_w: u32, img_h: u32) -> Vec<(u32, u32, u32, u32)> {
+        let (cols, rows) = (img_w.div_ceil(TILE_SIZE), img_h.div_ceil(TILE_SIZE));
+        let cells: Vec<(u32, u32)> = match self {
+            TileOrder::Scanline => return (0..img_h).map(|y| (0, y, img_w, y + 1)).collect(),
+            TileOrder::Spiral => {
+                // Sort by the square ring around the center, then by angle
+                let (cx, cy) = (f64::from(cols - 1) / 2.0, f64::from(rows - 1) / 2.0);
+                let key = |&(col, row): &(u32, u32)| {
+                    let (dx, dy) = (f64::from(col) - cx, f64::from(row) - cy);
+                    (dx.abs().max(dy.abs()), dy.atan2(dx))
+                };
+                let mut cells: Vec<_> = (0..rows)
+                    .flat_map(|row| (0..cols).map(move |col| (col, row)))
+                    .collect();
+                cells.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+                cells
+            }
+            TileOrder::Hilbert => {
+                let n = cols.max(rows).next_power_of_two();
+                (0..u64::from(n) * u64::from(n))
+                    .map(|d| hilbert_cell(n, d))
+                    .filter(|&(col, row)| col < cols && row < rows)
+                    .collect()
+            }
+        };
+
+        cells
+            .into_iter()
+            .map(|(col, row)| {
+                (
+                    col * TILE_SIZE,
+                    row * TILE_SIZE,
+                    ((col + 1) * TILE_SIZE).min(img_w),
+                    ((row + 1) * TILE_SIZE).min(img_h),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Cell `(x, y)` at distance `d` along a Hilbert curve filling an `n` by `n`
+/// grid, where `n` is a power of two.
+fn hilbert_cell(n: u32, d: u64) -> (u32, u32) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < n {
+        let rx = 1 & (t / 2) as u32;
+        let ry = 1 & (t as u32 ^ rx);
+        // Rotate the quadrant so the curve joins up
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+
+    (x, y)
+}
+
+/// What rays that miss everything see.
+#[derive(Clone, Debug)]
+pub enum Background {
+    /// Opaque solid color.
+    Solid(Color),
+    /// Black for lighting, with pixels whose camera rays miss left transparent
+    /// by [`render_rgba`].
+    Transparent,
+    /// Opaque daylight sky with the sun.
+    #[cfg(feature = "sky")]
+    Sky(crate::sky::Sky),
+    /// Opaque image surrounding the scene.
+    Environment(std::sync::Arc<crate::environment::EnvironmentMap>),
+}
+
+impl Background {
+    /// Color returned by rays heading in `direction` that miss everything.
+    pub fn color(&self, direction: &crate::vec3::Vec3) -> Color {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Transparent => Color::new_with(0.0),
+            #[cfg(feature = "sky")]
+            Background::Sky(sky) => sky.color(direction),
+            Background::Environment(map) => map.color(direction, 0.0),
+        }
+    }
+
+    /// Color returned by rays heading in `direction` that miss everything,
+    /// after their paths have spread about `spread` radians through rough
+    /// bounces. Only an environment map is blurred to match.
+    pub fn filtered_color(&self, direction: &crate::vec3::Vec3, spread: f64) -> Color {
+        match self {
+            Background::Environment(map) => map.color(direction, spread),
+            _ => self.color(direction),
+        }
+    }
+
+    /// Color returned by rays heading in `direction` that miss everything,
+    /// leaving out the light from [`Background::sun`].
+    #[cfg_attr(not(feature = "sky"), allow(unused_variables))]
+    pub fn color_without_sun(&self, direction: &crate::vec3::Vec3) -> Color {
+        match self {
+            #[cfg(feature = "sky")]
+            Background::Sky(sky) => sky.sky_color(direction),
+            _ => self.color(direction),
+        }
+    }
+
+    /// Direction towards a sun too small to be found by chance, and the light
+    /// it shines on a surface facing it.
+    pub fn sun(&self) -> Option<(crate::vec3::Vec3, Color)> {
+        match self {
+            #[cfg(feature = "sky")]
+            Background::Sky(sky) => sky.sun(),
+            _ => None,
+        }
+    }
+}
+
+impl core::default::Default for Background {
+    fn default() -> Self {
+        Background::Solid(Color::new_with(0.0))
+    }
+}
+
+impl From<Color> for Background {
+    fn from(color: Color) -> Self {
+        Background::Solid(color)
+    }
+}
+
+/// Settings shared by the render functions.
+#[derive(Clone, Debug)]
+pub struct RenderConfig {
+    /// Width of the image in pixels.
+    pub img_w: u32,
+    /// Height of the image in pixels.
+    pub img_h: u32,
+    /// Number of samples per pixel.
+    pub samples: u32,
+    /// Maximum number of bounces per ray, or of diffuse bounces when
+    /// `specular_depth` is set.
+    pub max_depth: u32,
+    /// Separate maximum number of specular bounces per ray, such as through
+    /// glass or off mirrors, see [`ray_color`](crate::ray::ray_color).
+    pub specular_depth: Option<u32>,
+    /// What rays that miss everything see.
+    pub background: Background,
+    /// What the color of each pixel represents.
+    pub mode: RenderMode,
+    /// Whether to render on multiple threads. Ignored without the `threads`
+    /// feature.
+    pub threads: bool,
+    /// Only render pixels inside `(x0, y0, x1, y1)`, where `x0..x1` are columns
+    /// and `y0..y1` are rows counting down from the top of the image.
+    ///
+    /// The camera keeps its full framing. Pixels outside the region are black
+    /// in new buffers and left untouched by [`accumulate`].
+    pub region: Option<(u32, u32, u32, u32)>,
+    /// Whether each pixel's lens samples are spread over a stratified grid
+    /// instead of drawn independently, which converges depth of field faster.
+    pub stratified_lens: bool,
+    /// How each pixel's samples are placed over the pixel and lens.
+    ///
+    /// [`accumulate`] draws a single sample per pass, so it always samples
+    /// randomly.
+    pub sampler: Sampler,
+    /// Distribution of the samples within each pixel, a lighter alternative
+    /// to filtering the samples afterwards for smoother edges.
+    pub jitter: Jitter,
+    /// Strength of the ordered dithering applied when converting to 8-bit
+    /// colors, in 8-bit steps. `0.0` disables dithering.
+    pub dither: f64,
+    /// Seed of the random numbers drawn while rendering. Each pixel draws from
+    /// its own generator, so the same seed gives the same image on any number
+    /// of threads.
+    pub seed: u64,
+    /// Brightest light allowed to reach the camera after scattering more than
+    /// once, see [`ray_color`](crate::ray::ray_color). Biased, but removes
+    /// fireflies.
+    pub clamp_indirect: Option<f64>,
+    /// Order in which [`render_with`] renders the image and hands out pixels.
+    /// The image itself is the same in any order.
+    pub tile_order: TileOrder,
+    /// Stops of exposure applied to linear colors when converting them for
+    /// display, where each stop doubles the brightness.
+    pub exposure: f64,
+    /// Curve applied after `exposure` when converting colors for display.
+    pub tone_map: ToneMap,
+    /// Smallest distance along a ray, in multiples of its direction, at which
+    /// a hit counts, so rays leaving a surface don't strike it again through
+    /// rounding.
+    ///
+    /// Too small for the scene's size and rounding lets surfaces shadow
+    /// themselves in speckles of shadow acne. Too large and rays start past
+    /// thin gaps and walls, so light leaks through corners and contact shadows
+    /// fade. The default `0.001` suits everything from the first book's unit
+    /// spheres to the 555 unit Cornell box. Scale it along with scenes far
+    /// outside that range, keeping it well under their thinnest features.
+    pub t_min: f64,
+    /// Called with the number of rows left before each row of a
+    /// single-threaded render, to report progress. Threaded renders don't
+    /// call it.
+    pub progress: Option<fn(u32)>,
+}
+
+impl RenderConfig {
+    /// Create a new `RenderConfig` for an image size with default settings.
+    pub fn new(img_w: u32, img_h: u32) -> Self {
+        Self {
+            img_w,
+            img_h,
+            ..Self::default()
+        }
+    }
+
+    /// Whether pixel `(x, y)`, counting down from the top row, is rendered.
+    pub fn in_region(&self, x: u32, y: u32) -> bool {
+        match self.region {
+            Some((x0, y0, x1, y1)) => (x0..x1).contains(&x) && (y0..y1).contains(&y),
+            None => true,
+        }
+    }
+}
+
+impl core::default::Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            img_w: 384,
+            img_h: 216,
+            samples: 100,
+            max_depth: 50,
+            specular_depth: None,
+            background: Background::default(),
+            mode: RenderMode::default(),
+            threads: cfg!(feature = "threads"),
+            region: None,
+            stratified_lens: false,
+            sampler: Sampler::default(),
+            jitter: Jitter::default(),
+            dither: 0.0,
+            seed: 0,
+            clamp_indirect: None,
+            tile_order: TileOrder::default(),
+            exposure: 0.0,
+            tone_map: ToneMap::default(),
+            t_min: 0.001,
+            progress: None,
+        }
+    }
+}
+
+/// Scene and settings shared by every path of a render, see
+/// [`ray_color`](crate::ray::ray_color).
+#[derive(Clone, Copy)]
+pub struct RenderContext<'a> {
+    /// Every object that rays can strike.
+    pub world: &'a (dyn Hittable + Sync),
+    /// Objects that diffuse bounces are importance sampled towards, see
+    /// [`World::lights`].
+    pub lights: Option<&'a std::sync::Arc<dyn Hittable + Send + Sync>>,
+    /// Probability that a diffuse bounce is sampled towards `lights`.
+    pub light_weight: f64,
+    /// Spotlights sampled directly at every diffuse bounce.
+    pub spot_lights: &'a [SpotLight],
+    /// Media ray-marched by `RenderMode::Volumetric`, which should not also be
+    /// part of `world`. Spotlights don't light them.
+    pub media: &'a [&'a ConstantMedium],
+    /// What rays that miss everything see.
+    pub background: &'a Background,
+    /// See [`RenderConfig::clamp_indirect`].
+    pub clamp_indirect: Option<f64>,
+    /// See [`RenderConfig::max_depth`].
+    pub max_depth: u32,
+    /// See [`RenderConfig::specular_depth`].
+    pub specular_depth: Option<u32>,
+    /// See [`RenderConfig::t_min`].
+    pub t_min: f64,
+}
+
+impl<'a> RenderContext<'a> {
+    /// Gather the lights of `world` and the path settings of `config`, tracing
+    /// rays against every object of `world` with no media to march.
+    pub fn new(config: &'a RenderConfig, world: &'a World) -> Self {
+        Self {
+            world: &world.objects,
+            lights: world.sampled_lights(),
+            light_weight: world.light_weight,
+            spot_lights: &world.spot_lights,
+            media: &[],
+            background: &config.background,
+            clamp_indirect: config.clamp_indirect,
+            max_depth: config.max_depth,
+            specular_depth: config.specular_depth,
+            t_min: config.t_min,
+        }
+    }
+}
+
+/// Phase function of a medium that scatters equally in every direction.
+const ISOTROPIC_PHASE: f64 = 0.25 * core::f64::consts::FRAC_1_PI;
+
+/// Compute the color of a single camera ray sample for a `RenderMode`.
+///
+/// `ctx.media` are only used by `RenderMode::Volumetric`.
+pub fn sample_color(
+    mode: RenderMode,
+    rng: &mut crate::rng::RenderRng,
+    r: &crate::ray::Ray,
+    ctx: &RenderContext<'_>,
+) -> Color {
+    sample_with_coverage(mode, rng, r, ctx).0
+}
+
+/// [`sample_color`], along with how much of the sample the scene covers, from
+/// `0.0` where the ray misses everything to `1.0` where it strikes an object.
+/// Marched media in front of the background cover part of it.
+fn sample_with_coverage(
+    mode: RenderMode,
+    rng: &mut crate::rng::RenderRng,
+    r: &crate::ray::Ray,
+    ctx: &RenderContext<'_>,
+) -> (Color, f64) {
+    let (world, t_min) = (ctx.world, ctx.t_min);
+    let covered = |struck: bool| if struck { 1.0 } else { 0.0 };
+    match mode {
+        RenderMode::Shaded => {
+            let (color, struck) = crate::ray::traced_color(rng, r, ctx);
+            (color, covered(struck))
+        }
+        RenderMode::Volumetric { step } => {
+            let mut rec = crate::hittable::HitRecord::default();
+            let t_max = if world.hit(r, t_min, f64::INFINITY, &mut rec) {
+                rec.t
+            } else {
+                f64::INFINITY
+            };
+
+            let (inscattered, transmittance) = march_media(rng, r, t_max, ctx, step);
+            let (color, struck) = crate::ray::traced_color(rng, r, ctx);
+            let coverage = if struck { 1.0 } else { 1.0 - transmittance };
+            (inscattered + color * transmittance, coverage)
+        }
+        RenderMode::AmbientOcclusion { samples, distance } => {
+            let mut rec = crate::hittable::HitRecord::default();
+            if !world.hit(r, t_min, f64::INFINITY, &mut rec) {
+                return (ctx.background.color(&r.direction()), 0.0);
+            }
+
+            let uvw = crate::onb::Onb::build_from_w(&rec.normal);
+            let open = (0..samples)
+                .filter(|_| {
+                    let direction = uvw.local(&crate::pdf::CosPdf::random_cosine_direction(rng));
+                    let ao_ray = crate::ray::Ray::new(rec.p, direction, r.time());
+                    !world.occluded(&ao_ray, t_min, distance)
+                })
+                .count();
+
+            (
+                Color::new_with(open as f64 / f64::from(samples.max(1))),
+                1.0,
+            )
+        }
+        #[cfg(feature = "stats")]
+        RenderMode::Heatmap { max_visits } => {
+            crate::bvh::take_node_visits();
+            let mut rec = crate::hittable::HitRecord::default();
+            let struck = world.hit(r, t_min, f64::INFINITY, &mut rec);
+            let visits = crate::bvh::take_node_visits();
+
+            let heat = (visits as f64 / f64::from(max_visits.max(1))).min(1.0);
+            (Color::new(heat, 0.0, 1.0 - heat), covered(struck))
+        }
+    }
+}
+
+/// Parts of `r` between `0` and `t_max` inside each medium.
+fn media_spans<'a>(
+    r: &crate::ray::Ray,
+    t_max: f64,
+    media: &[&'a ConstantMedium],
+) -> Vec<(f64, f64, &'a ConstantMedium)> {
+    media
+        .iter()
+        .filter_map(|&medium| {
+            let (enter, exit) = medium.boundary.hit_interval(r)?;
+            let (t0, t1) = (enter.t.max(0.0), exit.t.min(t_max));
+            if t0 < t1 {
+                Some((t0, t1, medium))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Fraction of light passing through the media along `r` up to `t_max`, with
+/// the optical depth of each medium estimated from one random point.
+fn shadow_transmittance(
+    rng: &mut crate::rng::RenderRng,
+    r: &crate::ray::Ray,
+    t_max: f64,
+    media: &[&ConstantMedium],
+) -> f64 {
+    let depth: f64 = media_spans(r, t_max, media)
+        .into_iter()
+        .map(|(t0, t1, medium)| {
+            let p = r.at(t0 + rng.gen::<f64>() * (t1 - t0));
+            medium.density_at(&p) * (t1 - t0) * r.direction().length()
+        })
+        .sum();
+
+    (-depth).exp()
+}
+
+/// Ray-march the media along `r` up to `t_max`, returning the light scattered
+/// or emitted towards the ray's origin and the transmittance through them.
+///
+/// Each step samples a point on the lights to estimate single scattering.
+fn march_media(
+    rng: &mut crate::rng::RenderRng,
+    r: &crate::ray::Ray,
+    t_max: f64,
+    ctx: &RenderContext<'_>,
+    step: f64,
+) -> (Color, f64) {
+    assert!(step > 0.0, "volumetric step must be positive");
+    let media = ctx.media;
+
+    let mut inscattered = Color::new_with(0.0);
+    let mut transmittance = 1.0;
+    let spans = media_spans(r, t_max, media);
+    if spans.is_empty() {
+        return (inscattered, transmittance);
+    }
+
+    let ray_length = r.direction().length();
+    let end = spans.iter().map(|s| s.1).fold(f64::NEG_INFINITY, f64::max);
+    let mut t0 = spans.iter().map(|s| s.0).fold(f64::INFINITY, f64::min);
+    while t0 < end {
+        let t1 = (t0 + step * ray_length.recip()).min(end);
+        let t = t0 + rng.gen::<f64>() * (t1 - t0);
+        let p = r.at(t);
+
+        let mut density = 0.0;
+        let mut scattering = Color::new_with(0.0);
+        let mut emission = Color::new_with(0.0);
+        for &(_, _, medium) in spans.iter().filter(|s| (s.0..s.1).contains(&t)) {
+            let d = medium.density_at(&p);
+            let (albedo, emit) = medium.scattering_at(&p);
+            density += d;
+            scattering += albedo * d;
+            emission += emit * d;
+        }
+
+        if density > 0.0 {
+            let mut light = Color::new_with(0.0);
+            if let Some(lights) = ctx.lights {
+                let direction = lights.random(rng, &p);
+                let pdf = lights.pdf_value(&p, &direction);
+                let shadow_ray = crate::ray::Ray::new(p, direction, r.time());
+                let mut rec = crate::hittable::HitRecord::default();
+                if pdf > 0.0
+                    && ctx
+                        .world
+                        .hit(&shadow_ray, ctx.t_min, f64::INFINITY, &mut rec)
+                {
+                    light = rec.material.emitted(&shadow_ray, &rec)
+                        * (shadow_transmittance(rng, &shadow_ray, rec.t, media) * ISOTROPIC_PHASE
+                            / pdf);
+                }
+            }
+
+            // Integrate the transmittance over the step, treating the density
+            // as constant along it
+            let absorbed = (-density * (t1 - t0) * ray_length).exp();
+            let weight = transmittance * (1.0 - absorbed) / density;
+            inscattered += (scattering * light + emission) * weight;
+            transmittance *= absorbed;
+        }
+
+        t0 = t1;
+    }
+
+    (inscattered, transmittance)
+}
+
+/// Move the top-level media out of `objects` when the mode ray-marches them.
+fn split_media<'a>(
+    mode: RenderMode,
+    objects: &'a HittableList,
+) -> (std::borrow::Cow<'a, HittableList>, Vec<&'a ConstantMedium>) {
+    if !matches!(mode, RenderMode::Volumetric { .. }) {
+        return (std::borrow::Cow::Borrowed(objects), Vec::new());
+    }
+
+    let mut surfaces = HittableList::new();
+    let mut media = Vec::new();
+    for object in objects {
+        match object.as_medium() {
+            Some(medium) => media.push(medium),
+            None => surfaces.add(object.clone()),
+        }
+    }
+
+    (std::borrow::Cow::Owned(surfaces), media)
+}
+
+/// Jittered point in cell `k` of the largest square grid with at most `samples`
+/// cells, or a random point for samples past the grid.
+fn lens_stratum(rng: &mut crate::rng::RenderRng, k: u32, samples: u32) -> (f64, f64) {
+    let n = f64::from(samples).sqrt() as u32;
+    if k >= n * n {
+        return (rng.gen::<f64>(), rng.gen::<f64>());
+    }
+
+    let cell = f64::from(n).recip();
+    (
+        (f64::from(k % n) + rng.gen::<f64>()) * cell,
+        (f64::from(k / n) + rng.gen::<f64>()) * cell,
+    )
+}
+
+/// Sum the colors and coverage of the samples for pixel `(i, j)`, where `j`
+/// counts up from the bottom row.
+fn pixel_color(
+    config: &RenderConfig,
+    ctx: &RenderContext<'_>,
+    cam: &Camera,
+    i: u32,
+    j: u32,
+) -> (Color, f64) {
+    let (mut pix, mut coverage) = (Color::new_with(0.0), 0.0);
+    pixel_samples(config, ctx, cam, i, j, |sample, covered| {
+        pix += sample;
+        coverage += covered;
+    });
+    (pix, coverage)
+}
+
+/// Trace each of pixel `(i, j)`'s samples in turn, passing their colors and
+/// coverage to `each`, with `j` counting up from the bottom row.
+fn pixel_samples<F: FnMut(Color, f64)>(
+    config: &RenderConfig,
+    ctx: &RenderContext<'_>,
+    cam: &Camera,
+    i: u32,
+    j: u32,
+    mut each: F,
+) {
+    let pattern = j * config.img_w + i;
+    let rng = &mut crate::rng::pixel_rng(config.seed, u64::from(pattern));
+    crate::rng::seed_hit_rng(config.seed, u64::from(pattern));
+    crate::texture::set_pixel_spread(cam.pixel_spread(config.img_h));
+    // The first NaN sample and how many there were, reported once per pixel
+    #[cfg(debug_assertions)]
+    let mut nans: Option<(u32, u32)> = None;
+    for k in 0..config.samples {
+        let (offset, lens) = match config.sampler {
+            Sampler::Random => {
+                let offset = (rng.gen::<f64>(), rng.gen::<f64>());
+                let lens = if config.stratified_lens {
+                    Some(lens_stratum(rng, k, config.samples))
+                } else {
+                    None
+                };
+                (offset, lens)
+            }
+            Sampler::CorrelatedMultiJittered => (
+                cmj(k, config.samples, pattern),
+                Some(cmj(k, config.samples, !pattern)),
+            ),
+        };
+        let offset = config.jitter.offset(offset);
+
+        let u = (f64::from(i) + offset.0) * f64::from(config.img_w - 1).recip();
+        let v = (f64::from(j) + offset.1) * f64::from(config.img_h - 1).recip();
+        let r = match lens {
+            Some(lens) => cam.get_ray_through_lens(rng, u, v, lens),
+            None => cam.get_ray(rng, u, v),
+        };
+        let (color, coverage) = sample_with_coverage(config.mode, rng, &r, ctx);
+        #[cfg(debug_assertions)]
+        if color.x().is_nan() || color.y().is_nan() || color.z().is_nan() {
+            let (first, count) = nans.unwrap_or((k, 0));
+            nans = Some((first, count + 1));
+        }
+        each(color, coverage);
+    }
+    #[cfg(debug_assertions)]
+    if let Some((first, count)) = nans {
+        eprintln!(
+            "\n{} samples of pixel ({}, {}) are NaN, starting with sample {}, see `debug_pixel`",
+            count,
+            i,
+            config.img_h - 1 - j,
+            first
+        );
+    }
+}
+
+/// Trace pixel `(x, y)` again, with `y` counting down from the top row, and
+/// return the color of each of its samples in the order they're drawn.
+///
+/// Every pixel draws its samples from generators seeded by `config.seed` and
+/// its position, so these are the same samples a full render with the same
+/// `config` averages for the pixel, whichever thread or tile it was rendered
+/// in. Re-running a single pixel like this helps track down which sample of
+/// a firefly or NaN went wrong, without rendering the rest of the image.
+///
+/// Returns `None` if `(x, y)` is outside the image.
+pub fn debug_pixel(
+    config: &RenderConfig,
+    world: &World,
+    cam: &Camera,
+    x: u32,
+    y: u32,
+) -> Option<Vec<Color>> {
+    if x >= config.img_w || y >= config.img_h {
+        return None;
+    }
+
+    let (objects, media) = split_media(config.mode, &world.objects);
+    let ctx = RenderContext {
+        world: &*objects,
+        media: &media,
+        ..RenderContext::new(config, world)
+    };
+    let mut samples = Vec::with_capacity(config.samples as usize);
+    pixel_samples(config, &ctx, cam, x, config.img_h - 1 - y, |sample, _| {
+        samples.push(sample)
+    });
+    Some(samples)
+}
+
+/// Average the colors and coverage of pixel `(i, j)`'s samples, where `j`
+/// counts up from the bottom row, or leave it black and uncovered outside
+/// `config.region`.
+fn pixel_average(
+    config: &RenderConfig,
+    ctx: &RenderContext<'_>,
+    cam: &Camera,
+    i: u32,
+    j: u32,
+) -> (Color, f64) {
+    if !config.in_region(i, config.img_h - 1 - j) {
+        return (Color::new_with(0.0), 0.0);
+    }
+
+    let scale = f64::from(config.samples).recip();
+    let (color, coverage) = pixel_color(config, ctx, cam, i, j);
+    (color * scale, coverage * scale)
+}
+
+fn render_single(
+    config: &RenderConfig,
+    ctx: &RenderContext<'_>,
+    cam: &Camera,
+) -> Vec<(Color, f64)> {
+    let mut pixels = Vec::with_capacity((config.img_w * config.img_h) as usize);
+
+    for j in (0..config.img_h).rev() {
+        if let Some(progress) = config.progress {
+            progress(j);
+        }
+        for i in 0..config.img_w {
+            pixels.push(pixel_average(config, ctx, cam, i, j));
+        }
+    }
+
+    pixels
+}
+
+#[cfg(feature = "threads")]
+fn render_threaded(
+    config: &RenderConfig,
+    ctx: &RenderContext<'_>,
+    cam: &Camera,
+) -> Vec<(Color, f64)> {
+    let img_w = config.img_w;
+    let img_h = config.img_h;
+
+    (0..img_h * img_w)
+        .into_par_iter()
+        .map(|x| pixel_average(config, ctx, cam, x % img_w, img_h - 1 - x / img_w))
+        .collect()
+}
+
+/// Add one more sample to every pixel of `accum`, top row first.
+///
+/// Call repeatedly for progressive rendering, where the running average is
+/// `accum / passes`. Change `config.seed` between passes, or every pass draws
+/// the same samples.
+pub fn accumulate(config: &RenderConfig, world: &World, cam: &Camera, accum: &mut [Color]) {
+    accumulate_by(config, world, cam, accum, |pix, color, _| *pix += color);
+}
+
+/// Add one more sample to the color and coverage of every pixel of `accum`,
+/// top row first, like [`accumulate`].
+///
+/// The running average coverage of pixel `i` is `accum[i].1 / passes`, for
+/// [`with_alpha`].
+pub fn accumulate_with_coverage(
+    config: &RenderConfig,
+    world: &World,
+    cam: &Camera,
+    accum: &mut [(Color, f64)],
+) {
+    accumulate_by(config, world, cam, accum, |pix, color, coverage| {
+        pix.0 += color;
+        pix.1 += coverage;
+    });
+}
+
+/// Draw one more sample for every pixel of `accum`, top row first, and `add`
+/// its color and coverage to the pixel.
+fn accumulate_by<T: Send, F: Fn(&mut T, Color, f64) + Sync>(
+    config: &RenderConfig,
+    world: &World,
+    cam: &Camera,
+    accum: &mut [T],
+    add: F,
+) {
+    let single = RenderConfig {
+        samples: 1,
+        sampler: Sampler::Random,
+        ..config.clone()
+    };
+    let img_w = config.img_w;
+    let img_h = config.img_h;
+    let (objects, media) = split_media(config.mode, &world.objects);
+    let ctx = RenderContext {
+        world: &*objects,
+        media: &media,
+        ..RenderContext::new(config, world)
+    };
+    let sample = |x: usize, pix: &mut T| {
+        let x = x as u32;
+        let (i, j) = (x % img_w, img_h - 1 - x / img_w);
+        if !config.in_region(i, x / img_w) {
+            return;
+        }
+        let (color, coverage) = pixel_color(&single, &ctx, cam, i, j);
+        add(pix, color, coverage);
+    };
+
+    #[cfg(feature = "threads")]
+    {
+        if config.threads {
+            accum
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(x, pix)| sample(x, pix));
+            return;
+        }
+    }
+
+    accum
+        .iter_mut()
+        .enumerate()
+        .for_each(|(x, pix)| sample(x, pix));
+}
+
+/// Render the averaged linear color of every pixel, top row first.
+pub fn render_linear(config: &RenderConfig, world: &World, cam: &Camera) -> Vec<Color> {
+    render_linear_with_coverage(config, world, cam).0
+}
+
+/// Render the averaged linear color of every pixel, top row first, along with
+/// the fraction of each pixel's samples whose camera ray hit an object.
+///
+/// Coverage is counted from the same samples as the colors, so with
+/// [`Background::Transparent`] dividing a color by its coverage averages the
+/// samples that hit, see [`with_alpha`]. Volumes are hit stochastically, so
+/// they come out partially covered.
+pub fn render_linear_with_coverage(
+    config: &RenderConfig,
+    world: &World,
+    cam: &Camera,
+) -> (Vec<Color>, Vec<f64>) {
+    let (objects, media) = split_media(config.mode, &world.objects);
+    let ctx = RenderContext {
+        world: &*objects,
+        media: &media,
+        ..RenderContext::new(config, world)
+    };
+
+    #[cfg(feature = "threads")]
+    {
+        if config.threads {
+            return render_threaded(config, &ctx, cam).into_iter().unzip();
+        }
+    }
+
+    render_single(config, &ctx, cam).into_iter().unzip()
+}
+
+/// Auxiliary outputs for compositing, top row first, averaged over the camera
+/// rays of each pixel which hit an object.
+#[derive(Clone, Debug, Default)]
+pub struct Aovs {
+    /// Shading normal at the first hit, facing the camera, or zero where
+    /// every ray misses.
+    pub normal: Vec<Vec3>,
+    /// Distance from the camera to the first hit, or infinite where every ray
+    /// misses.
+    pub depth: Vec<f64>,
+    /// Albedo of the material at the first hit, see
+    /// [`Material::albedo`](crate::material::Material::albedo), or zero where
+    /// every ray misses.
+    pub albedo: Vec<Color>,
+}
+
+/// Normal, depth and albedo of pixel `(i, j)`.
+fn pixel_aovs(
+    config: &RenderConfig,
+    objects: &dyn Hittable,
+    cam: &Camera,
+    i: u32,
+    j: u32,
+) -> (Vec3, f64, Color) {
+    let pattern = j * config.img_w + i;
+    let rng = &mut crate::rng::pixel_rng(config.seed, u64::from(pattern));
+    crate::rng::seed_hit_rng(config.seed, u64::from(pattern));
+    let mut rec = crate::hittable::HitRecord::default();
+    let (mut hits, mut normal, mut depth, mut albedo) = (0, Vec3::default(), 0.0, Color::default());
+    for r in cam.pixel_rays(rng, i, j, config.img_w, config.img_h, config.samples) {
+        if objects.hit(&r, config.t_min, f64::INFINITY, &mut rec) {
+            hits += 1;
+            normal += rec.normal;
+            depth += rec.t * r.direction().length();
+            albedo += rec.material.albedo(&rec);
+        }
+    }
+
+    if hits == 0 {
+        return (normal, f64::INFINITY, albedo);
+    }
+    let scale = f64::from(hits).recip();
+    (normal * scale, depth * scale, albedo * scale)
+}
+
+/// Render the normal, depth and albedo of every pixel in one pass over the
+/// camera rays.
+///
+/// For alpha, use the coverage from [`render_linear_with_coverage`] instead,
+/// which is counted from the same samples as the colors.
+pub fn aovs(config: &RenderConfig, world: &World, cam: &Camera) -> Aovs {
+    let img_w = config.img_w;
+    let img_h = config.img_h;
+    let pixel = |x: u32| {
+        let (i, j) = (x % img_w, img_h - 1 - x / img_w);
+        if !config.in_region(i, x / img_w) {
+            return (Vec3::default(), f64::INFINITY, Color::default());
+        }
+        pixel_aovs(config, &world.objects, cam, i, j)
+    };
+
+    #[cfg(feature = "threads")]
+    let pixels: Vec<_> = if config.threads {
+        (0..img_h * img_w).into_par_iter().map(pixel).collect()
+    } else {
+        (0..img_h * img_w).map(pixel).collect()
+    };
+    #[cfg(not(feature = "threads"))]
+    let pixels: Vec<_> = (0..img_h * img_w).map(pixel).collect();
+
+    let mut aovs = Aovs::default();
+    for (normal, depth, albedo) in pixels {
+        aovs.normal.push(normal);
+        aovs.depth.push(depth);
+        aovs.albedo.push(albedo);
+    }
+    aovs
+}
+
+/// Convert linear colors, top row first, into 8-bit gamma corrected colors
+/// with the exposure, tone mapping and dithering of `config`.
+///
+/// This is cheap next to rendering, so a buffer from [`render_linear`] can be
+/// converted again with other settings to bring out detail in bright or dark
+/// areas.
+pub fn quantize(colors: &[Color], config: &RenderConfig) -> Vec<ColorU8> {
+    let img_w = config.img_w;
+    colors
+        .iter()
+        .enumerate()
+        .map(|(x, color)| {
+            let x = x as u32;
+            color.into_u8_color_tonemapped(
+                1.0,
+                config.exposure,
+                config.tone_map,
+                x % img_w,
+                x / img_w,
+                config.dither,
+            )
+        })
+        .collect()
+}
+
+/// Combine linear colors rendered against a black background with their
+/// coverage into 8-bit colors with straight alpha, converted like
+/// [`quantize`].
+pub fn with_alpha(colors: &[Color], coverage: &[f64], config: &RenderConfig) -> Vec<ColorU8A> {
+    let img_w = config.img_w;
+    colors
+        .iter()
+        .zip(coverage)
+        .enumerate()
+        .map(|(x, (&color, &alpha))| {
+            let x = x as u32;
+            // Missed samples added black, so divide them back out
+            let color = if alpha > 0.0 { color / alpha } else { color };
+            let ColorU8(r, g, b) = color.into_u8_color_tonemapped(
+                1.0,
+                config.exposure,
+                config.tone_map,
+                x % img_w,
+                x / img_w,
+                config.dither,
+            );
+            ColorU8A(r, g, b, crate::conversion::IntoU8::into_u8(alpha))
+        })
+        .collect()
+}
+
+/// Render every pixel as an 8-bit gamma corrected color with alpha, top row
+/// first.
+///
+/// Pixels are only transparent with [`Background::Transparent`], otherwise
+/// every pixel is opaque.
+pub fn render_rgba(config: &RenderConfig, world: &World, cam: &Camera) -> Vec<ColorU8A> {
+    let (colors, coverage) = render_linear_with_coverage(config, world, cam);
+    match config.background {
+        Background::Transparent => with_alpha(&colors, &coverage, config),
+        _ => with_alpha(&colors, &vec![1.0; colors.len()], config),
+    }
+}
+
+/// Render every pixel as 8-bit gamma corrected RGBA bytes, top row first,
+/// ready for an HTML canvas's `ImageData`.
+///
+/// Neither this nor the rest of the render path touches the filesystem, so
+/// it's the entry point for WebAssembly builds.
+pub fn render_to_buffer(config: &RenderConfig, world: &World, cam: &Camera) -> Vec<u8> {
+    render_rgba(config, world, cam)
+        .iter()
+        .flat_map(|c| [c.0, c.1, c.2, c.3])
+        .collect()
+}
+
+/// Render the averaged linear colors of the pixels in `(x0, y0, x1, y1)`, a row
+/// at a time with rows counting down from the top.
+fn render_tile(
+    config: &RenderConfig,
+    ctx: &RenderContext<'_>,
+    cam: &Camera,
+    (x0, y0, x1, y1): (u32, u32, u32, u32),
+) -> Vec<Color> {
+    let width = x1 - x0;
+    let pixel = |k: u32| {
+        let (i, y) = (x0 + k % width, y0 + k / width);
+        pixel_average(config, ctx, cam, i, config.img_h - 1 - y).0
+    };
+
+    #[cfg(feature = "threads")]
+    {
+        if config.threads {
+            return (0..width * (y1 - y0)).into_par_iter().map(pixel).collect();
+        }
+    }
+
+    (0..width * (y1 - y0)).map(pixel).collect()
+}
+
+/// Reasons a render can't be written into a caller's buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderError {
+    /// The buffer doesn't hold one color for each pixel of the image.
+    BufferSize {
+        /// Number of colors the buffer holds.
+        len: usize,
+        /// Number of pixels in the image.
+        needed: usize,
+    },
+}
+
+impl core::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RenderError::BufferSize { len, needed } => write!(
+                f,
+                "buffer holds {} colors, but the image needs {}",
+                len, needed
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// Render the image a tile at a time in the order of `config.tile_order`,
+/// passing each finished pixel to `sink` as `(x, y, color)` with `y` counting
+/// down from the top.
+///
+/// Colors are gamma corrected and dithered like [`quantize`]. Pixels outside
+/// `config.region` are skipped. Tiles may be rendered on several threads, but
+/// `sink` is only ever called from the calling thread, a tile at a time, so it
+/// doesn't need to be `Send` or `Sync`.
+pub fn render_with<F: FnMut(u32, u32, ColorU8)>(
+    config: &RenderConfig,
+    world: &World,
+    cam: &Camera,
+    mut sink: F,
+) {
+    let (objects, media) = split_media(config.mode, &world.objects);
+    let ctx = RenderContext {
+        world: &*objects,
+        media: &media,
+        ..RenderContext::new(config, world)
+    };
+
+    for tile in config.tile_order.tiles(config.img_w, config.img_h) {
+        let (x0, y0, x1, y1) = tile;
+        let pixels = || (y0..y1).flat_map(move |y| (x0..x1).map(move |x| (x, y)));
+        if !pixels().any(|(x, y)| config.in_region(x, y)) {
+            continue;
+        }
+
+        let colors = render_tile(config, &ctx, cam, tile);
+        for ((x, y), color) in pixels().zip(colors) {
+            if config.in_region(x, y) {
+                let color = color.into_u8_color_tonemapped(
+                    1.0,
+                    config.exposure,
+                    config.tone_map,
+                    x,
+                    y,
+                    config.dither,
+                );
+                sink(x, y, color);
+            }
+        }
+    }
+}
+
+/// Render every pixel as an 8-bit gamma corrected color, top row first.
+pub fn render(config: &RenderConfig, world: &World, cam: &Camera) -> Vec<ColorU8> {
+    let mut colors = vec![ColorU8::default(); (config.img_w * config.img_h) as usize];
+    render_into(&mut colors, config, world, cam).expect("buffer matches the image size");
+    colors
+}
+
+/// Render every pixel as an 8-bit gamma corrected color into `buf`, top row
+/// first, so a frame buffer can be reused between renders without allocating
+/// another.
+///
+/// Fails if `buf` doesn't hold exactly `img_w * img_h` colors. Pixels outside
+/// `config.region` keep what was in `buf`. The colors are the same as
+/// [`render_with`]'s, but rows are written as they finish rather than a tile
+/// at a time.
+pub fn render_into(
+    buf: &mut [ColorU8],
+    config: &RenderConfig,
+    world: &World,
+    cam: &Camera,
+) -> Result<(), RenderError> {
+    let needed = config.img_w as usize * config.img_h as usize;
+    if buf.len() != needed {
+        return Err(RenderError::BufferSize {
+            len: buf.len(),
+            needed,
+        });
+    }
+
+    let (objects, media) = split_media(config.mode, &world.objects);
+    let ctx = RenderContext {
+        world: &*objects,
+        media: &media,
+        ..RenderContext::new(config, world)
+    };
+    let img_w = config.img_w;
+    let render_row = |(y, row): (usize, &mut [ColorU8])| {
+        let y = y as u32;
+        for (x, pix) in (0..img_w).zip(row) {
+            if config.in_region(x, y) {
+                let color = pixel_average(config, &ctx, cam, x, config.img_h - 1 - y).0;
+                *pix = color.into_u8_color_tonemapped(
+                    1.0,
+                    config.exposure,
+                    config.tone_map,
+                    x,
+                    y,
+                    config.dither,
+                );
+            }
+        }
+    };
+
+    #[cfg(feature = "threads")]
+    {
+        if config.threads {
+            buf.par_chunks_mut(img_w as usize)
+                .enumerate()
+                .for_each(render_row);
+            return Ok(());
+        }
+    }
+
+    buf.chunks_mut(img_w as usize)
+        .enumerate()
+        .for_each(render_row);
+    Ok(())
+}
+
+/// Hash 8-bit colors with 64-bit FNV-1a.
+///
+/// The hash only depends on the bytes of the colors, so unlike
+/// `std::collections::hash_map::DefaultHasher` it stays the same across Rust
+/// versions and platforms and can be stored to spot renders which change.
+pub fn hash_colors(colors: &[ColorU8]) -> u64 {
+    colors
+        .iter()
+        .flat_map(|c| [c.0, c.1, c.2])
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+/// Render every pixel as an 8-bit color and return the [`hash_colors`] of the
+/// image.
+///
+/// With a fixed `RenderConfig::seed` the hash only changes when the output
+/// does, which makes it a cheap check that a refactor left renders alone.
+pub fn render_hash(config: &RenderConfig, world: &World, cam: &Camera) -> u64 {
+    hash_colors(&render(config, world, cam))
+}
+
+/// Write 8-bit colors out as a plain text PPM image.
+///
+/// Each `(key, value)` pair of `metadata` is written as a `# key=value`
+/// comment after the magic number.
+pub fn write_ppm<W: Write>(
     mut w: &mut W,
     img_w: u32,
     img_h: u32,
-    samples: u32,
-    max_depth: u32,
-    mut rng: &mut rand::rngs::ThreadRng,
-    world: &dyn Hittable,
-    lights: std::sync::Arc<dyn Hittable + Send + Sync>,
+    colors: &[ColorU8],
+    metadata: &[(String, String)],
+) -> Result<(), std::io::Error> {
+    writeln!(&mut w, "P3")?;
+    for (key, value) in metadata {
+        writeln!(&mut w, "# {}={}", key, value)?;
+    }
+    writeln!(&mut w, "{} {}\n255", img_w, img_h)?;
+    for color in colors {
+        writeln!(&mut w, "{} {} {}", color.0, color.1, color.2)?;
+    }
+
+    Ok(())
+}
+
+/// Render a scene and write it out as a PPM image.
+pub fn run_ppm<W: Write>(
+    w: &mut W,
+    config: &RenderConfig,
+    world: &World,
     cam: &Camera,
-    background: &Color,
+    metadata: &[(String, String)],
 ) -> Result<(), std::io::Error> {
-    writeln!(&mut w, "P3\n{} {}\n255", img_w, img_h)?;
+    let colors = render(config, world, cam);
+    write_ppm(w, config.img_w, config.img_h, &colors, metadata)
+}
+
+/// Encode 8-bit samples as a PNG image with a `tEXt` chunk per metadata pair.
+#[cfg(feature = "images")]
+fn encode_png<P: AsRef<std::path::Path>>(
+    path: P,
+    img_w: u32,
+    img_h: u32,
+    color_type: png::ColorType,
+    buf: &[u8],
+    metadata: &[(String, String)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let w = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(w, img_w, img_h);
+    encoder.set_color(color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+    for (key, value) in metadata {
+        encoder.add_text_chunk(key.clone(), value.clone())?;
+    }
 
-    for j in (0..img_h).rev() {
-        eprint!("\rScanlines remaining: {}   ", j);
-        std::io::stderr().flush()?;
-        for i in 0..img_w {
-            let pixel_color: Color = (0..samples).fold(Color::new_with(0.0), |pix, _| {
-                let u = (f64::from(i) + rng.gen::<f64>()) * f64::from(img_w - 1).recip();
-                let v = (f64::from(j) + rng.gen::<f64>()) * f64::from(img_h - 1).recip();
-                let r = cam.get_ray(&mut rng, u, v);
-                pix + ray_color(&mut rng, &r, background, world, lights.clone(), max_depth)
-            });
-            let color = pixel_color.into_u8_color(f64::from(samples));
-            writeln!(&mut w, "{} {} {}", color.0, color.1, color.2)?;
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(buf)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Write 8-bit colors out as a PNG image, storing `metadata` in `tEXt` chunks.
+#[cfg(feature = "images")]
+pub fn write_png<P: AsRef<std::path::Path>>(
+    path: P,
+    img_w: u32,
+    img_h: u32,
+    colors: &[ColorU8],
+    metadata: &[(String, String)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let buf: Vec<u8> = colors.iter().flat_map(|c| [c.0, c.1, c.2]).collect();
+    encode_png(path, img_w, img_h, png::ColorType::Rgb, &buf, metadata)
+}
+
+/// Write 8-bit colors with alpha out as a PNG image, storing `metadata` in
+/// `tEXt` chunks.
+#[cfg(feature = "images")]
+pub fn write_png_rgba<P: AsRef<std::path::Path>>(
+    path: P,
+    img_w: u32,
+    img_h: u32,
+    colors: &[ColorU8A],
+    metadata: &[(String, String)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let buf: Vec<u8> = colors.iter().flat_map(|c| [c.0, c.1, c.2, c.3]).collect();
+    encode_png(path, img_w, img_h, png::ColorType::Rgba, &buf, metadata)
+}
+
+/// Write linear colors out as an OpenEXR image at full `f32` precision,
+/// without exposure or tone mapping, storing `metadata` as text attributes.
+///
+/// `alpha` adds an `A` channel, which should be the coverage from
+/// [`render_linear_with_coverage`] for colors rendered against a transparent
+/// background, as EXR colors are premultiplied. `aovs`
+/// adds `normal`, `depth` and `albedo` layers, each written as its own part
+/// of the file.
+#[cfg(feature = "exr")]
+pub fn write_exr<P: AsRef<std::path::Path>>(
+    path: P,
+    img_w: u32,
+    img_h: u32,
+    colors: &[Color],
+    alpha: Option<&[f64]>,
+    aovs: Option<&Aovs>,
+    metadata: &[(String, String)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    use exr::prelude::{
+        AnyChannel, AnyChannels, AttributeValue, Encoding, FlatSamples, Image, ImageAttributes,
+        IntegerBounds, Layer, LayerAttributes, Text, WritableImage,
+    };
+
+    let size = (img_w as usize, img_h as usize);
+    let channel = |name: &str, samples: Vec<f32>| AnyChannel::new(name, FlatSamples::F32(samples));
+    let vectors = |names: [&str; 3], values: &[Vec3]| -> Vec<AnyChannel<FlatSamples>> {
+        (0..3)
+            .map(|k| {
+                let samples = values.iter().map(|v| v.to_f32_array()[k]).collect();
+                channel(names[k], samples)
+            })
+            .collect()
+    };
+    let layer = |attributes: LayerAttributes, channels: Vec<AnyChannel<FlatSamples>>| {
+        Layer::new(
+            size,
+            attributes,
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(channels.into()),
+        )
+    };
+
+    let mut attributes = LayerAttributes::named("rgba");
+    for (key, value) in metadata {
+        // EXR text is Latin-1, so skip anything that can't be stored
+        let value = match Text::new_or_none(value) {
+            Some(value) => value,
+            None => continue,
+        };
+        // The software has a standard attribute, which can't be set as a custom one
+        if key == "software" {
+            attributes.software_name = Some(value);
+        } else if let Some(key) = Text::new_or_none(key) {
+            attributes.other.insert(key, AttributeValue::Text(value));
         }
     }
+    let mut rgba = vectors(["R", "G", "B"], colors);
+    if let Some(alpha) = alpha {
+        rgba.push(channel("A", alpha.iter().map(|&a| a as f32).collect()));
+    }
+
+    let mut layers = vec![layer(attributes, rgba)];
+    if let Some(aovs) = aovs {
+        layers.push(layer(
+            LayerAttributes::named("normal"),
+            vectors(["X", "Y", "Z"], &aovs.normal),
+        ));
+        layers.push(layer(
+            LayerAttributes::named("depth"),
+            vec![channel("Z", aovs.depth.iter().map(|&z| z as f32).collect())],
+        ));
+        layers.push(layer(
+            LayerAttributes::named("albedo"),
+            vectors(["R", "G", "B"], &aovs.albedo),
+        ));
+    }
+
+    let attributes = ImageAttributes::new(IntegerBounds::from_dimensions(size));
+    Image::from_layers(attributes, layers)
+        .write()
+        .to_file(path)?;
 
     Ok(())
 }
 
+/// Render a scene and write it out as a PNG image, with an alpha channel for
+/// [`Background::Transparent`].
+#[cfg(feature = "images")]
+pub fn run_png<P: AsRef<std::path::Path>>(
+    path: P,
+    config: &RenderConfig,
+    world: &World,
+    cam: &Camera,
+    metadata: &[(String, String)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    match config.background {
+        Background::Transparent => {
+            let colors = render_rgba(config, world, cam);
+            write_png_rgba(path, config.img_w, config.img_h, &colors, metadata)
+        }
+        _ => {
+            let colors = render(config, world, cam);
+            write_png(path, config.img_w, config.img_h, &colors, metadata)
+        }
+    }
+}
+
+/// Pixel types of the `image` crate that [`render_to_image`] can fill.
+///
+/// Integer pixels are gamma corrected and clamped like the 8-bit output, with
+/// dithering only applied to 8-bit channels. Float pixels are left linear and
+/// unclamped for HDR work, though [`render_to_image`] still applies the
+/// exposure and tone mapping of its config to them first.
+#[cfg(feature = "images")]
+pub trait ImagePixel: image::Pixel + 'static {
+    /// Convert a linear `color` with coverage `alpha` for pixel `(x, y)`,
+    /// counting down from the top row.
+    fn from_linear(color: Color, alpha: f64, x: u32, y: u32, dither: f64) -> Self;
+}
+
+/// Gamma correct a linear channel into a 16-bit value.
+#[cfg(feature = "images")]
+fn into_u16(value: f64) -> u16 {
+    (value.max(0.0).sqrt().min(1.0) * f64::from(u16::MAX)).round() as u16
+}
+
+#[cfg(feature = "images")]
+impl ImagePixel for image::Rgb<u8> {
+    fn from_linear(color: Color, _alpha: f64, x: u32, y: u32, dither: f64) -> Self {
+        let ColorU8(r, g, b) = color.into_u8_color_dithered(1.0, x, y, dither);
+        image::Rgb([r, g, b])
+    }
+}
+
+#[cfg(feature = "images")]
+impl ImagePixel for image::Rgba<u8> {
+    fn from_linear(color: Color, alpha: f64, x: u32, y: u32, dither: f64) -> Self {
+        let ColorU8(r, g, b) = color.into_u8_color_dithered(1.0, x, y, dither);
+        image::Rgba([r, g, b, crate::conversion::IntoU8::into_u8(alpha)])
+    }
+}
+
+#[cfg(feature = "images")]
+impl ImagePixel for image::Rgb<u16> {
+    fn from_linear(color: Color, _alpha: f64, _x: u32, _y: u32, _dither: f64) -> Self {
+        image::Rgb([
+            into_u16(color.x()),
+            into_u16(color.y()),
+            into_u16(color.z()),
+        ])
+    }
+}
+
+#[cfg(feature = "images")]
+impl ImagePixel for image::Rgba<u16> {
+    fn from_linear(color: Color, alpha: f64, _x: u32, _y: u32, _dither: f64) -> Self {
+        let alpha = (alpha.clamp(0.0, 1.0) * f64::from(u16::MAX)).round() as u16;
+        image::Rgba([
+            into_u16(color.x()),
+            into_u16(color.y()),
+            into_u16(color.z()),
+            alpha,
+        ])
+    }
+}
+
+#[cfg(feature = "images")]
+impl ImagePixel for image::Rgb<f32> {
+    fn from_linear(color: Color, _alpha: f64, _x: u32, _y: u32, _dither: f64) -> Self {
+        image::Rgb([color.x() as f32, color.y() as f32, color.z() as f32])
+    }
+}
+
+#[cfg(feature = "images")]
+impl ImagePixel for image::Rgba<f32> {
+    fn from_linear(color: Color, alpha: f64, _x: u32, _y: u32, _dither: f64) -> Self {
+        image::Rgba([
+            color.x() as f32,
+            color.y() as f32,
+            color.z() as f32,
+            alpha as f32,
+        ])
+    }
+}
+
+/// Render a scene into an `image` crate buffer of any [`ImagePixel`] type,
+/// such as `Rgb<u8>`, `Rgba<u16>` or `Rgb<f32>`.
+///
+/// Colors are rendered linear and converted once at the end, with the exposure
+/// and tone mapping of `config` applied for every pixel type, floating point
+/// included. Pixels are only transparent with [`Background::Transparent`], and
+/// colors with alpha are straight rather than premultiplied.
+#[cfg(feature = "images")]
+pub fn render_to_image<P: ImagePixel>(
+    config: &RenderConfig,
+    world: &World,
+    cam: &Camera,
+) -> image::ImageBuffer<P, Vec<P::Subpixel>> {
+    let (colors, coverage) = render_linear_with_coverage(config, world, cam);
+    let opaque = !matches!(config.background, Background::Transparent);
+
+    image::ImageBuffer::from_fn(config.img_w, config.img_h, |x, y| {
+        let i = (y * config.img_w + x) as usize;
+        let alpha = if opaque { 1.0 } else { coverage[i] };
+        // Missed samples added black, so divide them back out
+        let color = if alpha > 0.0 {
+            colors[i] / alpha
+        } else {
+            colors[i]
+        };
+        let color = config.tone_map.apply(color.exposed(config.exposure));
+        P::from_linear(color, alpha, x, y, config.dither)
+    })
+}
+
+/// Print the rows left of a render over the previous count on stderr, for
+/// [`RenderConfig::progress`].
+pub fn print_scanlines_remaining(rows: u32) {
+    eprint!("\rScanlines remaining: {}   ", rows);
+}
+
+/// Run ray tracing in a single thread.
+///
+/// The render's seed is drawn from `rng`, so generators in the same state
+/// give the same image. Kept for the older positional signature, prefer
+/// [`run_ppm`].
+#[allow(clippy::too_many_arguments)]
+pub fn run_single_ppm<W: Write, R: rand::Rng>(
+    w: &mut W,
+    img_w: u32,
+    img_h: u32,
+    samples: u32,
+    max_depth: u32,
+    rng: &mut R,
+    world: &(dyn Hittable + Sync),
+    lights: std::sync::Arc<dyn Hittable + Send + Sync>,
+    cam: &Camera,
+    background: &Color,
+    mode: RenderMode,
+) -> Result<(), std::io::Error> {
+    let config = RenderConfig {
+        img_w,
+        img_h,
+        samples,
+        max_depth,
+        background: Background::Solid(*background),
+        mode,
+        threads: false,
+        region: None,
+        stratified_lens: false,
+        sampler: Sampler::Random,
+        jitter: Jitter::Box,
+        dither: 0.0,
+        seed: rng.gen(),
+        clamp_indirect: None,
+        specular_depth: None,
+        tile_order: TileOrder::Scanline,
+        exposure: 0.0,
+        tone_map: ToneMap::Linear,
+        t_min: 0.001,
+        progress: Some(print_scanlines_remaining),
+    };
+    let ctx = RenderContext {
+        world,
+        lights: Some(&lights),
+        light_weight: 0.5,
+        spot_lights: &[],
+        media: &[],
+        background: &config.background,
+        clamp_indirect: None,
+        max_depth,
+        specular_depth: None,
+        t_min: config.t_min,
+    };
+    let colors: Vec<Color> = render_single(&config, &ctx, cam)
+        .into_iter()
+        .map(|(color, _)| color)
+        .collect();
+    let colors = quantize(&colors, &config);
+    write_ppm(w, img_w, img_h, &colors, &[])
+}
+
 /// Run multi-threaded ray tracing.
+///
+/// The same `seed` gives the same image on any number of threads. Kept for
+/// the older positional signature, prefer [`run_ppm`].
 #[cfg(feature = "threads")]
+#[allow(clippy::too_many_arguments)]
 pub fn run_threaded_ppm<W, H>(
-    mut w: &mut W,
+    w: &mut W,
     img_w: u32,
     img_h: u32,
     samples: u32,
     max_depth: u32,
+    seed: u64,
     world: &H,
     lights: std::sync::Arc<dyn Hittable + Send + Sync>,
     cam: &Camera,
     background: &Color,
+    mode: RenderMode,
 ) -> Result<(), std::io::Error>
 where
     W: Write,
     H: Hittable + Sync,
 {
-    writeln!(&mut w, "P3\n{} {}\n255", img_w, img_h)?;
+    let config = RenderConfig {
+        img_w,
+        img_h,
+        samples,
+        max_depth,
+        background: Background::Solid(*background),
+        mode,
+        threads: true,
+        region: None,
+        stratified_lens: false,
+        sampler: Sampler::Random,
+        jitter: Jitter::Box,
+        dither: 0.0,
+        seed,
+        clamp_indirect: None,
+        specular_depth: None,
+        tile_order: TileOrder::Scanline,
+        exposure: 0.0,
+        tone_map: ToneMap::Linear,
+        t_min: 0.001,
+        progress: None,
+    };
+    let ctx = RenderContext {
+        world,
+        lights: Some(&lights),
+        light_weight: 0.5,
+        spot_lights: &[],
+        media: &[],
+        background: &config.background,
+        clamp_indirect: None,
+        max_depth,
+        specular_depth: None,
+        t_min: config.t_min,
+    };
+    let colors: Vec<Color> = render_threaded(&config, &ctx, cam)
+        .into_iter()
+        .map(|(color, _)| color)
+        .collect();
+    let colors = quantize(&colors, &config);
+    write_ppm(w, img_w, img_h, &colors, &[])
+}
 
-    let colors = (0..img_h * img_w)
-        .into_par_iter()
-        .map(|x| {
-            let pixel_color = (0..samples).fold(Color::new_with(0.0), |pix, _| {
-                let mut rng = rand::thread_rng();
-                let u = (f64::from(x % img_w) + rng.gen::<f64>()) * f64::from(img_w - 1).recip();
-                let v = (f64::from(img_h - 1 - x / img_w) + rng.gen::<f64>())
-                    * f64::from(img_h - 1).recip();
-                let r = cam.get_ray(&mut rng, u, v);
-                pix + ray_color(&mut rng, &r, background, world, lights.clone(), max_depth)
-            });
-            pixel_color.into_u8_color(f64::from(samples))
-        })
-        .collect::<Vec<crate::vec3::ColorU8>>();
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
 
-    for color in colors {
-        writeln!(&mut w, "{} {} {}", color.0, color.1, color.2)?;
+    use super::*;
+    use crate::hittable::Sphere;
+    use crate::material::{DiffuseLight, Material};
+    use crate::texture::SolidColor;
+    use crate::vec3::Point3;
+
+    /// A glowing sphere in the middle of a small image.
+    fn glowing_sphere(background: Background) -> (RenderConfig, World, Camera) {
+        let mut objects = HittableList::new();
+        objects.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Material::DiffLight(DiffuseLight::new(Arc::new(SolidColor::new(0.5, 0.25, 1.0)))),
+        )));
+        let cam = Camera::new(
+            Point3::new(0.0, 0.0, 5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            5.0,
+            0.0,
+            1.0,
+        );
+        let config = RenderConfig {
+            samples: 16,
+            background,
+            threads: false,
+            seed: 1,
+            ..RenderConfig::new(16, 16)
+        };
+
+        (config, World::without_lights(objects), cam)
     }
 
-    Ok(())
+    #[test]
+    fn coverage_counts_the_samples_behind_the_colors() {
+        let (config, world, cam) = glowing_sphere(Background::Transparent);
+        let (colors, coverage) = render_linear_with_coverage(&config, &world, &cam);
+
+        assert_eq!(coverage[0], 0.0);
+        assert_eq!(coverage[8 * 16 + 8], 1.0);
+        assert!(coverage.iter().any(|&alpha| alpha > 0.0 && alpha < 1.0));
+        // Every sample that hits sees the same emission, so dividing it back
+        // out of edge pixels recovers it exactly
+        for (&color, &alpha) in colors.iter().zip(&coverage) {
+            if alpha > 0.0 {
+                let straight = color / alpha;
+                assert!((straight - Color::new(0.5, 0.25, 1.0)).length() < 1e-9);
+            }
+        }
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn float_images_keep_linear_colors() {
+        let (config, world, cam) = glowing_sphere(Background::default());
+        let img = render_to_image::<image::Rgb<f32>>(&config, &world, &cam);
+
+        assert_eq!(img.get_pixel(8, 8).0, [0.5, 0.25, 1.0]);
+        assert_eq!(img.get_pixel(0, 0).0, [0.0, 0.0, 0.0]);
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn sixteen_bit_images_are_gamma_corrected() {
+        let (config, world, cam) = glowing_sphere(Background::Transparent);
+        let img = render_to_image::<image::Rgba<u16>>(&config, &world, &cam);
+
+        let gamma = |value: f64| (value.sqrt() * f64::from(u16::MAX)).round() as u16;
+        assert_eq!(
+            img.get_pixel(8, 8).0,
+            [gamma(0.5), gamma(0.25), u16::MAX, u16::MAX]
+        );
+        assert_eq!(img.get_pixel(0, 0).0[3], 0);
+    }
+
+    #[cfg(feature = "exr")]
+    #[test]
+    fn exr_round_trips_linear_colors() {
+        use exr::prelude::FlatSamples;
+
+        let colors = vec![
+            Color::new(0.0, 0.125, 1.0),
+            Color::new(12.5, 0.001, 3.25),
+            Color::new(0.3, 0.6, 0.9),
+            Color::new(1e4, 2.0, 0.0),
+            Color::new(0.7, 0.2, 0.1),
+            Color::new(5.0, 5.0, 5.0),
+        ];
+        let alpha = [0.0, 0.25, 0.5, 0.75, 1.0, 1.0];
+        let path =
+            std::env::temp_dir().join(format!("rtiow-{}-round-trip.exr", std::process::id()));
+        write_exr(&path, 3, 2, &colors, Some(&alpha), None, &[]).unwrap();
+        let image = exr::prelude::read_all_flat_layers_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let layer = &image.layer_data[0];
+        let channel = |name: &str| match &layer
+            .channel_data
+            .list
+            .iter()
+            .find(|channel| channel.name == *name)
+            .unwrap()
+            .sample_data
+        {
+            FlatSamples::F32(samples) => samples.clone(),
+            _ => panic!("channel {} isn't f32", name),
+        };
+        let (r, g, b, a) = (channel("R"), channel("G"), channel("B"), channel("A"));
+        for (k, color) in colors.iter().enumerate() {
+            let read = Color::new(f64::from(r[k]), f64::from(g[k]), f64::from(b[k]));
+            assert!((read - *color).length() <= 1e-6 * color.length().max(1.0));
+            assert_eq!(f64::from(a[k]), alpha[k]);
+        }
+    }
+
+    #[cfg(feature = "threads")]
+    #[test]
+    fn thread_count_leaves_output_alone() {
+        let mut config = RenderConfig {
+            samples: 4,
+            seed: 7,
+            ..RenderConfig::new(24, 24)
+        };
+        let (cam, objects) = crate::scene::second::cornell_smoke(
+            &mut crate::rng::scene_rng(7),
+            config.img_w,
+            config.img_h,
+        )
+        .unwrap();
+        let world = World::without_lights(objects);
+
+        let hash_on = |threads: usize| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| render_hash(&config, &world, &cam))
+        };
+        let (one, many) = (hash_on(1), hash_on(8));
+        config.threads = false;
+        let single = render_hash(&config, &world, &cam);
+
+        assert_eq!(one, many);
+        assert_eq!(one, single);
+    }
+
+    #[test]
+    fn pixels_outside_the_region_are_left_alone() {
+        let (mut config, world, cam) = glowing_sphere(Background::default());
+        config.region = Some((4, 6, 12, 10));
+        let mut buf = vec![ColorU8(1, 2, 3); 16 * 16];
+        render_into(&mut buf, &config, &world, &cam).unwrap();
+
+        for y in 0..16 {
+            for x in 0..16 {
+                let ColorU8(r, g, b) = buf[(y * 16 + x) as usize];
+                let untouched = (r, g, b) == (1, 2, 3);
+                assert_eq!(untouched, !config.in_region(x, y), "pixel ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn clamping_indirect_light_keeps_caustics_finite() {
+        // Nested glass balls around a light far too bright to add up, so every
+        // path to the light scatters at least twice on the way
+        let (mut config, _, cam) = glowing_sphere(Background::default());
+        let mut objects = HittableList::new();
+        for &radius in &[1.0, 0.75] {
+            objects.add(Arc::new(Sphere::new(
+                Point3::new(0.0, 0.0, 0.0),
+                radius,
+                Material::Dielectric(crate::material::Diel::new(1.5)),
+            )));
+        }
+        objects.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            0.5,
+            Material::DiffLight(DiffuseLight::new(Arc::new(SolidColor::new_with(1e308)))),
+        )));
+        let world = World::without_lights(objects);
+        let all_finite = |colors: &[Color]| {
+            colors
+                .iter()
+                .all(|c| c.x().is_finite() && c.y().is_finite() && c.z().is_finite())
+        };
+
+        assert!(!all_finite(&render_linear(&config, &world, &cam)));
+        config.clamp_indirect = Some(10.0);
+        assert!(all_finite(&render_linear(&config, &world, &cam)));
+    }
+
+    #[test]
+    fn render_into_matches_render_with() {
+        let (config, world, cam) = glowing_sphere(Background::default());
+        let mut tiled = vec![ColorU8::default(); 16 * 16];
+        render_with(&config, &world, &cam, |x, y, color| {
+            tiled[(y * 16 + x) as usize] = color;
+        });
+        let mut buf = vec![ColorU8::default(); 16 * 16];
+        render_into(&mut buf, &config, &world, &cam).unwrap();
+        assert_eq!(hash_colors(&buf), hash_colors(&tiled));
+
+        let mut short = vec![ColorU8::default(); 16 * 15];
+        assert_eq!(
+            render_into(&mut short, &config, &world, &cam),
+            Err(RenderError::BufferSize {
+                len: 240,
+                needed: 256
+            })
+        );
+    }
+
+    #[test]
+    fn debug_pixel_stays_inside_the_image() {
+        let (config, world, cam) = glowing_sphere(Background::default());
+        let samples = debug_pixel(&config, &world, &cam, 15, 15).unwrap();
+        assert_eq!(samples.len(), 16);
+        assert!(debug_pixel(&config, &world, &cam, 16, 0).is_none());
+        assert!(debug_pixel(&config, &world, &cam, 0, 16).is_none());
+    }
+
+    #[test]
+    #[cfg(not(feature = "threads"))]
+    fn renders_without_threads_even_when_asked_for_them() {
+        let (config, world, cam) = glowing_sphere(Background::default());
+        let single = render_hash(&config, &world, &cam);
+        let config = RenderConfig {
+            threads: true,
+            ..config
+        };
+        assert_eq!(render_hash(&config, &world, &cam), single);
+        assert_eq!(render(&config, &world, &cam).len(), 16 * 16);
+    }
 }
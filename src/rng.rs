@@ -0,0 +1,63 @@
+//! Random number generators seeded from the render settings, so an image comes
+//! out the same whichever thread renders each pixel.
+
+use rand::SeedableRng;
+
+/// Random number generator used while rendering.
+pub type RenderRng = rand_chacha::ChaCha8Rng;
+
+/// Random number generator the scene builders place random objects with.
+///
+/// Building a scene twice from generators with the same seed gives the same
+/// scene, and so with the same `RenderConfig::seed` the same image.
+///
+/// ```
+/// use rtiow::render::{render_hash, Background, RenderConfig};
+/// use rtiow::vec3::Color;
+/// use rtiow::world::World;
+///
+/// let config = RenderConfig {
+///     samples: 2,
+///     seed: 7,
+///     background: Background::Solid(Color::new(0.7, 0.8, 1.0)),
+///     ..RenderConfig::new(32, 18)
+/// };
+/// // The book cover, with its small spheres placed at random
+/// let hash = |seed| {
+///     let mut rng = rtiow::rng::scene_rng(seed);
+///     let (cam, objects) = rtiow::scene::first::final_scene(&mut rng, 32, 18);
+///     render_hash(&config, &World::without_lights(objects), &cam)
+/// };
+/// assert_eq!(hash(7), hash(7));
+/// assert_ne!(hash(7), hash(8));
+/// ```
+pub type SceneRng = rand_chacha::ChaCha8Rng;
+
+thread_local! {
+    static HIT_RNG: core::cell::RefCell<RenderRng> =
+        core::cell::RefCell::new(RenderRng::seed_from_u64(0));
+}
+
+/// Generator for building a scene from `seed`.
+pub fn scene_rng(seed: u64) -> SceneRng {
+    SceneRng::seed_from_u64(seed)
+}
+
+/// Generator for pixel `pixel` of a render with `seed`, each pixel drawing
+/// from its own stream.
+pub fn pixel_rng(seed: u64, pixel: u64) -> RenderRng {
+    let mut rng = RenderRng::seed_from_u64(seed);
+    rng.set_stream(pixel);
+    rng
+}
+
+/// Reseed the current thread's generator for `Hittable::hit`, which has no
+/// generator passed in. The renderer calls this before every pixel.
+pub fn seed_hit_rng(seed: u64, pixel: u64) {
+    HIT_RNG.with(|rng| *rng.borrow_mut() = pixel_rng(!seed, pixel));
+}
+
+/// Run `f` with the current thread's generator for `Hittable::hit`.
+pub fn with_hit_rng<T, F: FnOnce(&mut RenderRng) -> T>(f: F) -> T {
+    HIT_RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
@@ -0,0 +1,133 @@
+//! Sample patterns for spreading a pixel's samples over the pixel and lens.
+
+/// How the samples of a pixel are placed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sampler {
+    /// Independent uniform random samples.
+    #[default]
+    Random,
+    /// Correlated multi-jittered samples, stratified over the whole square and
+    /// along each axis, with a different pattern for every pixel.
+    ///
+    /// Both the pixel and lens positions are drawn from the pattern, which
+    /// makes `RenderConfig::stratified_lens` redundant.
+    CorrelatedMultiJittered,
+}
+
+/// Distribution of the sample offsets within a pixel, which acts as the image's
+/// reconstruction filter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Jitter {
+    /// Uniform over the pixel, a box filter.
+    #[default]
+    Box,
+    /// Tent shaped around the pixel center, reaching one pixel out on each side.
+    Tent,
+    /// Gaussian around the pixel center with a standard deviation of half a
+    /// pixel.
+    Gaussian,
+}
+
+impl Jitter {
+    /// Reshape a uniform point in the unit square into an offset from the
+    /// pixel's corner following the distribution.
+    ///
+    /// Stratification of the uniform points carries over to the offsets.
+    pub fn offset(self, (x, y): (f64, f64)) -> (f64, f64) {
+        match self {
+            Jitter::Box => (x, y),
+            Jitter::Tent => {
+                let tent = |u: f64| {
+                    let u = 2.0 * u;
+                    if u < 1.0 {
+                        u.sqrt() - 1.0
+                    } else {
+                        1.0 - (2.0 - u).sqrt()
+                    }
+                };
+                (0.5 + tent(x), 0.5 + tent(y))
+            }
+            Jitter::Gaussian => {
+                // Box-Muller transform
+                let radius = 0.5 * (-2.0 * (1.0 - x).ln()).sqrt();
+                let theta = crate::conversion::TWO_PI * y;
+                (0.5 + radius * theta.cos(), 0.5 + radius * theta.sin())
+            }
+        }
+    }
+}
+
+/// Pseudorandom permutation of `i` within `0..l` for the pattern `p`.
+fn permute(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    // Cycle walk until the hashed index lands inside `0..l`
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+
+    (i.wrapping_add(p)) % l
+}
+
+/// Pseudorandom number in `[0, 1)` for index `i` of the pattern `p`.
+pub(crate) fn randfloat(mut i: u32, p: u32) -> f64 {
+    i ^= p;
+    i ^= i >> 17;
+    i ^= i >> 10;
+    i = i.wrapping_mul(0xb365_34e5);
+    i ^= i >> 12;
+    i ^= i >> 21;
+    i = i.wrapping_mul(0x93fc_4795);
+    i ^= 0xdf6e_307f;
+    i ^= i >> 17;
+    i = i.wrapping_mul(1 | p >> 18);
+    f64::from(i) / 4_294_967_296.0
+}
+
+/// Sample `s` of `n` correlated multi-jittered points in the unit square for
+/// the pattern `p`, following Kensler's 2013 paper.
+///
+/// The `n` samples of a pattern fall one per cell of a near-square grid and
+/// one per row and column of a finer grid. Different patterns are decorrelated
+/// from each other.
+pub fn cmj(s: u32, n: u32, p: u32) -> (f64, f64) {
+    let n = n.max(1);
+    let m = f64::from(n).sqrt().ceil() as u32;
+    let rows = n.div_ceil(m);
+
+    let s = permute(s % n, n, p.wrapping_mul(0x5163_3e2d));
+    let sx = permute(s % m, m, p.wrapping_mul(0xa511_e9b3));
+    let sy = permute(s / m, rows, p.wrapping_mul(0x63d8_3595));
+    let jx = randfloat(s, p.wrapping_mul(0xa399_d265));
+    let jy = randfloat(s, p.wrapping_mul(0x711a_d6a5));
+
+    (
+        (f64::from(s % m) + (f64::from(sy) + jx) / f64::from(rows)) / f64::from(m),
+        (f64::from(s / m) + (f64::from(sx) + jy) / f64::from(m)) / f64::from(rows),
+    )
+}
@@ -3,3 +3,477 @@
 pub mod first;
 pub mod second;
 pub mod third;
+
+use crate::camera::Camera;
+use crate::hittable::HittableList;
+use crate::vec3::{Color, Vec3};
+use crate::world::World;
+
+/// A scene that can be built for rendering, so a caller can pick one from a
+/// table by name.
+pub trait Scene {
+    /// Build the camera and world for an image of `img_w` by `img_h` pixels,
+    /// placing anything random with `rng`.
+    fn build(
+        &self,
+        rng: &mut crate::rng::SceneRng,
+        img_w: u32,
+        img_h: u32,
+    ) -> Result<(Camera, World), Box<dyn std::error::Error>>;
+}
+
+/// Result of a scene builder.
+type Built<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Scene builder functions, by what they return.
+#[derive(Clone, Copy)]
+pub enum Builder {
+    /// Objects along with the objects to sample as lights.
+    Lit(fn(&mut crate::rng::SceneRng, u32, u32) -> Built<third::LitScene>),
+    /// Objects with nothing to sample as lights, lit by the background or
+    /// their own emission alone.
+    Unlit(fn(&mut crate::rng::SceneRng, u32, u32) -> Built<(Camera, HittableList)>),
+    /// A whole world, for scenes with spotlights or their own light sampling.
+    World(fn(&mut crate::rng::SceneRng, u32, u32) -> Built<(Camera, World)>),
+}
+
+/// A scene in [`SCENES`].
+pub struct SceneEntry {
+    /// Name used to select the scene.
+    pub name: &'static str,
+    /// Short description shown in the usage message.
+    pub description: &'static str,
+    /// Function constructing the camera and objects.
+    pub build: Builder,
+    /// Aspect ratio the scene is framed for.
+    pub aspect_ratio: f64,
+    /// Color seen by rays that miss everything, black for scenes lit only by
+    /// their own lights.
+    pub background: Color,
+}
+
+impl Scene for SceneEntry {
+    fn build(
+        &self,
+        rng: &mut crate::rng::SceneRng,
+        img_w: u32,
+        img_h: u32,
+    ) -> Result<(Camera, World), Box<dyn std::error::Error>> {
+        Ok(match self.build {
+            Builder::Lit(build) => {
+                let (cam, objects, lights) = build(rng, img_w, img_h)?;
+                (cam, World::new(objects, lights))
+            }
+            Builder::Unlit(build) => {
+                let (cam, objects) = build(rng, img_w, img_h)?;
+                (cam, World::without_lights(objects))
+            }
+            Builder::World(build) => build(rng, img_w, img_h)?,
+        })
+    }
+}
+
+/// Aspect ratio of the Cornell box scenes, framed like the book's square box.
+const SQUARE: f64 = 1.0;
+
+/// Aspect ratio of the open scenes.
+const WIDE: f64 = 16.0 / 9.0;
+
+/// Background of scenes with no lights of their own.
+const BLACK: Color = Vec3::new_with(0.0);
+
+/// The books' sky color, for scenes lit by the background.
+const BOOK_SKY: Color = Vec3::new(0.7, 0.8, 1.0);
+
+/// Scenes that can be picked by name, see [`find`].
+pub const SCENES: &[SceneEntry] = &[
+    SceneEntry {
+        name: "cornell_box",
+        description: "Refactored Cornell box (book 3, 6.1)",
+        build: Builder::Lit(third::cornell_box),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_box_metal",
+        description: "Cornell box with a metallic block (book 3, 12.2)",
+        build: Builder::Lit(third::cornell_box_metal),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_box_brushed",
+        description: "Cornell box with a brushed aluminum block",
+        build: Builder::Lit(third::cornell_box_brushed),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_box_glossy",
+        description: "Cornell box with a rough aluminum block sampled towards the light",
+        build: Builder::Lit(third::cornell_box_glossy),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_box_softbox",
+        description: "Cornell box lit by a light with a narrowed spread",
+        build: Builder::Lit(third::cornell_box_softbox),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_box_sphere",
+        description: "Cornell box with a glass sphere (book 3, 12.4)",
+        build: Builder::Lit(third::cornell_box_sphere),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_nested_glass",
+        description: "Cornell box with an air bubble inside the glass sphere",
+        build: Builder::Lit(third::cornell_nested_glass),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_glowing_fog",
+        description: "Cornell box with a faintly glowing fog sphere",
+        build: Builder::Lit(third::cornell_glowing_fog),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "checker_corner",
+        description: "World space and UV checkers on rotated walls",
+        build: Builder::Lit(third::checker_corner),
+        aspect_ratio: WIDE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_spinning_box",
+        description: "Cornell box with a block blurred by spinning and sliding",
+        build: Builder::Lit(third::cornell_spinning_box),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_glowing_cube",
+        description: "Cornell box lit by a glowing cube",
+        build: Builder::Lit(third::cornell_glowing_cube),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_light_shafts",
+        description: "Cornell box with patchy fog and shafts of light, see `--volumetric`",
+        build: Builder::Lit(third::cornell_light_shafts),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "phong_spheres",
+        description: "Phong spheres with rising shininess",
+        build: Builder::Lit(third::phong_spheres),
+        aspect_ratio: WIDE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_cutout",
+        description: "Cornell box seen through a card with checkerboard holes",
+        build: Builder::Lit(third::cornell_cutout),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_stained_glass",
+        description: "Cornell box behind a pane of tinted glass",
+        build: Builder::Lit(third::cornell_stained_glass),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "sky_spheres",
+        description: "Spheres on open ground, see `--sky`",
+        build: Builder::Lit(third::sky_spheres),
+        aspect_ratio: WIDE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "environment_spheres",
+        description: "Mirror and rough metal spheres, see `--environment`",
+        build: Builder::Lit(third::environment_spheres),
+        aspect_ratio: WIDE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "ring_light",
+        description: "Spheres lit by a ring light around the camera",
+        build: Builder::Lit(third::ring_light),
+        aspect_ratio: WIDE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_many_lights",
+        description: "Cornell box with one bright and four dim lights",
+        build: Builder::Lit(third::cornell_many_lights),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_diffuser",
+        description: "Cornell box lit through a translucent paper sheet",
+        build: Builder::Lit(third::cornell_diffuser),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_spotlight",
+        description: "Cornell box with a warm spotlight on the floor",
+        build: Builder::World(third::cornell_spotlight),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_small_light",
+        description: "Cornell box lit by a small, bright light",
+        build: Builder::World(third::cornell_small_light),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "base_metal_lambert",
+        description: "Lambertian and metal spheres (book 1, 9.6)",
+        build: Builder::Unlit(|_, img_w, img_h| Ok(first::base_metal_lambert(img_w, img_h))),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "all_refract",
+        description: "Glass that always refracts (book 1, 10.2)",
+        build: Builder::Unlit(|_, img_w, img_h| {
+            Ok((first::origin_camera(img_w, img_h), first::all_refract()))
+        }),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "sometimes_refract",
+        description: "Glass with total internal reflection (book 1, 10.3)",
+        build: Builder::Unlit(|_, img_w, img_h| {
+            Ok((
+                first::origin_camera(img_w, img_h),
+                first::sometimes_refract(),
+            ))
+        }),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "bubble",
+        description: "Hollow glass sphere (book 1, 10.5)",
+        build: Builder::Unlit(|_, img_w, img_h| {
+            Ok((first::origin_camera(img_w, img_h), first::bubble()))
+        }),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "wide_view",
+        description: "Two spheres seen with a wide field of view (book 1, 11.1)",
+        build: Builder::Unlit(|_, img_w, img_h| {
+            Ok((first::origin_camera(img_w, img_h), first::wide_view()))
+        }),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "first_final_scene",
+        description: "Book cover of random spheres (book 1, 13.1)",
+        build: Builder::Unlit(|rng, img_w, img_h| Ok(first::final_scene(rng, img_w, img_h))),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "bouncing_spheres",
+        description: "Book cover with bouncing, motion blurred spheres (book 2, 2.5)",
+        build: Builder::Unlit(second::bouncing_spheres),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "bouncing_spheres_bokeh",
+        description: "Bouncing spheres with star-shaped bokeh",
+        build: Builder::Unlit(second::bouncing_spheres_bokeh),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "checker_world",
+        description: "Bouncing spheres on a checkered ground (book 2, 4.3)",
+        build: Builder::Unlit(second::checker_world),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "two_spheres",
+        description: "Two checkered spheres (book 2, 4.4)",
+        build: Builder::Unlit(second::two_spheres),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "checker_horizon",
+        description: "Antialiased checkered ground reaching the horizon",
+        build: Builder::Unlit(second::checker_horizon),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "two_spheres_uv",
+        description: "World space and texture space checkers",
+        build: Builder::Unlit(second::two_spheres_uv),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "perlin_spheres",
+        description: "Perlin noise spheres (book 2, 5.1)",
+        build: Builder::Unlit(second::perlin_spheres),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "warped_marble",
+        description: "Marble spheres with warped noise",
+        build: Builder::Unlit(second::warped_marble),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    #[cfg(feature = "images")]
+    SceneEntry {
+        name: "earth",
+        description: "Image textured globe (book 2, 6.2)",
+        build: Builder::Unlit(second::earth),
+        aspect_ratio: WIDE,
+        background: BOOK_SKY,
+    },
+    SceneEntry {
+        name: "simple_light",
+        description: "Sphere and rectangle lights (book 2, 7.4)",
+        build: Builder::Unlit(second::simple_light),
+        aspect_ratio: WIDE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "naive_cornell_box",
+        description: "Empty Cornell box before flipping faces (book 2, 7.6)",
+        build: Builder::Unlit(second::naive_cornell_box),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "second_cornell_box",
+        description: "Cornell box with two rotated blocks (book 2, 8.2)",
+        build: Builder::Unlit(second::cornell_box),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    SceneEntry {
+        name: "cornell_smoke",
+        description: "Cornell box with smoke and fog blocks (book 2, 9.2)",
+        build: Builder::Unlit(second::cornell_smoke),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+    #[cfg(feature = "images")]
+    SceneEntry {
+        name: "final_scene",
+        description: "Every feature of the second book (book 2, 10)",
+        build: Builder::Unlit(second::final_scene),
+        aspect_ratio: SQUARE,
+        background: BLACK,
+    },
+];
+
+/// Look up a scene of [`SCENES`] by name.
+pub fn find(name: &str) -> Option<&'static SceneEntry> {
+    SCENES.iter().find(|scene| scene.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{render_hash, RenderConfig};
+    use crate::world::World;
+
+    // Golden hashes of a few scenes. When a change is meant to alter renders,
+    // check the new images by eye, then replace these with the hashes the
+    // failing tests print.
+    const BASE_METAL_LAMBERT: u64 = 0x40af_c6ef_5273_4a25;
+    const CORNELL_SMOKE: u64 = 0x5fc9_b4c2_0ad7_9363;
+    const CORNELL_BOX: u64 = 0xcb1e_3c21_f665_ef77;
+
+    /// Small enough to render in a moment, with a fixed seed.
+    fn config() -> RenderConfig {
+        RenderConfig {
+            samples: 4,
+            threads: false,
+            seed: 1,
+            ..RenderConfig::new(24, 24)
+        }
+    }
+
+    #[test]
+    fn base_metal_lambert_renders_as_before() {
+        let config = config();
+        let (cam, objects) = first::base_metal_lambert(config.img_w, config.img_h);
+        let hash = render_hash(&config, &World::without_lights(objects), &cam);
+        assert_eq!(hash, BASE_METAL_LAMBERT, "got {:#018x}", hash);
+    }
+
+    #[test]
+    fn cornell_smoke_renders_as_before() {
+        let config = config();
+        let (cam, objects) =
+            second::cornell_smoke(&mut crate::rng::scene_rng(1), config.img_w, config.img_h)
+                .unwrap();
+        let hash = render_hash(&config, &World::without_lights(objects), &cam);
+        assert_eq!(hash, CORNELL_SMOKE, "got {:#018x}", hash);
+    }
+
+    #[test]
+    fn cornell_box_renders_as_before() {
+        let config = config();
+        let (cam, objects, lights) =
+            third::cornell_box(&mut crate::rng::scene_rng(1), config.img_w, config.img_h).unwrap();
+        let hash = render_hash(&config, &World::new(objects, lights), &cam);
+        assert_eq!(hash, CORNELL_BOX, "got {:#018x}", hash);
+    }
+
+    #[test]
+    fn cameras_match_the_image_aspect() {
+        let rng = &mut crate::rng::scene_rng(1);
+        let (cam, _, _) = third::cornell_box(rng, 100, 100).unwrap();
+        assert!((cam.aspect_ratio() - 1.0).abs() < 1e-12);
+        let (cam, _, _) = third::phong_spheres(rng, 100, 100).unwrap();
+        assert!((cam.aspect_ratio() - 1.0).abs() < 1e-12);
+        let (cam, _, _) = third::phong_spheres(rng, 160, 90).unwrap();
+        assert!((cam.aspect_ratio() - 16.0 / 9.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn every_registered_scene_builds() {
+        // The globe's image isn't part of the repository, see `asset_path`
+        let have_earth = crate::texture::asset_path("earthmap.jpg").exists();
+        for scene in SCENES {
+            let built = Scene::build(scene, &mut crate::rng::scene_rng(1), 16, 9);
+            if scene.name == "earth" && !have_earth {
+                assert!(built.is_err());
+                continue;
+            }
+            assert!(built.is_ok(), "`{}` failed to build", scene.name);
+            assert_eq!(find(scene.name).map(|found| found.name), Some(scene.name));
+        }
+    }
+}
@@ -2,6 +2,8 @@
 
 use std::sync::Arc;
 
+use rand::Rng;
+
 use crate::camera::Camera;
 use crate::hittable::{HittableList, Sphere};
 use crate::material::Material::{Dielectric, Lambertian, Metallic};
@@ -34,7 +36,13 @@ pub fn base_metal_lambert(img_w: u32, img_h: u32) -> (crate::camera::Camera, Hit
         Metallic(Metal::new(Color::new(0.8, 0.8, 0.8), 0.0)),
     )));
 
-    let cam = Camera::new(
+    (origin_camera(img_w, img_h), world)
+}
+
+/// Camera of the early chapters, at the origin looking down -Z with a 90
+/// degree vertical field of view, for an image of `img_w` by `img_h` pixels.
+pub fn origin_camera(img_w: u32, img_h: u32) -> Camera {
+    Camera::new(
         Point3::new_with(0.0),
         Point3::new(0.0, 0.0, -1.0),
         Vec3::new(0.0, 1.0, 0.0),
@@ -44,8 +52,7 @@ pub fn base_metal_lambert(img_w: u32, img_h: u32) -> (crate::camera::Camera, Hit
         1.0,
         0.0,
         1.0,
-    );
-    (cam, world)
+    )
 }
 
 /// Scene for "all objects refract", includes first parts of dielectrics.
@@ -153,8 +160,8 @@ pub fn wide_view() -> HittableList {
 }
 
 /// Book cover scene.
-pub fn final_scene<R: rand::Rng>(
-    rng: &mut R,
+pub fn final_scene(
+    rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
 ) -> (crate::camera::Camera, HittableList) {
@@ -167,6 +174,8 @@ pub fn final_scene<R: rand::Rng>(
     )));
 
     // Add more balls to the scene and randomize the radius of the smaller ones
+    // Every glass ball shares one material
+    let glass = Arc::new(Dielectric(Diel::new(1.5)));
     let bound = 15;
     for (a, b) in (-bound..bound).flat_map(|x| core::iter::repeat(x).zip(-bound..bound)) {
         let radius = rng.gen_range(0.1, 0.3);
@@ -200,19 +209,15 @@ pub fn final_scene<R: rand::Rng>(
                 )));
             } else {
                 // glass
-                world.add(Arc::new(Sphere::new(
-                    center,
-                    radius,
-                    Dielectric(Diel::new(1.5)),
-                )));
+                world.add(Arc::new(Sphere::new_shared(center, radius, glass.clone())));
             }
         }
     }
 
-    world.add(Arc::new(Sphere::new(
+    world.add(Arc::new(Sphere::new_shared(
         Point3::new(0.0, 1.0, 0.0),
         1.0,
-        Dielectric(Diel::new(1.5)),
+        glass,
     )));
 
     world.add(Arc::new(Sphere::new(
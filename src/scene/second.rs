@@ -2,6 +2,8 @@
 
 use std::sync::Arc;
 
+use rand::Rng;
+
 use crate::aarect::{AaRect, Plane};
 use crate::bvh::BvhNode;
 use crate::camera::Camera;
@@ -11,18 +13,22 @@ use crate::hittable::{
 use crate::material::Material::{Dielectric, DiffLight, Lambertian, Metallic};
 use crate::material::{Diel, DiffuseLight, Lambert, Metal};
 use crate::perlin::NoiseType;
-use crate::texture::{Checker, ImageTexture, Noise, SolidColor};
+#[cfg(feature = "images")]
+use crate::texture::ImageTexture;
+use crate::texture::{Checker, Noise, SolidColor, UvChecker};
 use crate::vec3::{Color, Point3, Vec3};
 
 /// Section 2.5: Book cover scene but with motion blur.
-pub fn bouncing_spheres<R: rand::Rng>(
-    rng: &mut R,
+pub fn bouncing_spheres(
+    rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
 ) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
     let mut world = HittableList::new();
 
     // Add more balls to the scene and randomize the radius of the smaller ones
+    // Every glass ball shares one material
+    let glass = Arc::new(Dielectric(Diel::new(1.5)));
     let bound = 15;
     for (a, b) in (-bound..bound).flat_map(|x| core::iter::repeat(x).zip(-bound..bound)) {
         let radius = rng.gen_range(0.1, 0.3);
@@ -56,16 +62,12 @@ pub fn bouncing_spheres<R: rand::Rng>(
                 )));
             } else {
                 // glass
-                world.add(Arc::new(Sphere::new(
-                    center,
-                    radius,
-                    Dielectric(Diel::new(1.5)),
-                )));
+                world.add(Arc::new(Sphere::new_shared(center, radius, glass.clone())));
             }
         }
     }
 
-    let mut world = HittableList::new_from(Arc::new(BvhNode::bvh_node(rng, &mut world, 0.0, 1.0)));
+    let mut world = HittableList::new_from(Arc::new(BvhNode::from_list(&world, 0.0, 1.0)));
 
     world.add(Arc::new(Sphere::new(
         Point3::new(0.5, -1000.0, 0.0),
@@ -73,10 +75,10 @@ pub fn bouncing_spheres<R: rand::Rng>(
         Lambertian(Lambert::new(Arc::new(SolidColor::new_with(0.5)))),
     )));
 
-    world.add(Arc::new(Sphere::new(
+    world.add(Arc::new(Sphere::new_shared(
         Point3::new(0.0, 1.0, 0.0),
         1.0,
-        Dielectric(Diel::new(1.5)),
+        glass,
     )));
 
     world.add(Arc::new(Sphere::new(
@@ -113,15 +115,18 @@ pub fn bouncing_spheres<R: rand::Rng>(
     Ok((cam, world))
 }
 
-/// Section 4.3: Checkerboard world with BVH.
-pub fn checker_world<R: rand::Rng>(
-    rng: &mut R,
+/// Bouncing spheres focused on the nearest large sphere, with a star-shaped
+/// aperture turning the blurred highlights behind it into stars.
+pub fn bouncing_spheres_bokeh(
+    rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
 ) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
     let mut world = HittableList::new();
 
     // Add more balls to the scene and randomize the radius of the smaller ones
+    // Every glass ball shares one material
+    let glass = Arc::new(Dielectric(Diel::new(1.5)));
     let bound = 15;
     for (a, b) in (-bound..bound).flat_map(|x| core::iter::repeat(x).zip(-bound..bound)) {
         let radius = rng.gen_range(0.1, 0.3);
@@ -155,16 +160,142 @@ pub fn checker_world<R: rand::Rng>(
                 )));
             } else {
                 // glass
+                world.add(Arc::new(Sphere::new_shared(center, radius, glass.clone())));
+            }
+        }
+    }
+
+    let mut world = HittableList::new_from(Arc::new(BvhNode::from_list(&world, 0.0, 1.0)));
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.5, -1000.0, 0.0),
+        1000.0,
+        Lambertian(Lambert::new(Arc::new(SolidColor::new_with(0.5)))),
+    )));
+
+    world.add(Arc::new(Sphere::new_shared(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        glass,
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Lambertian(Lambert::new(Arc::new(SolidColor::new(0.4, 0.2, 0.1)))),
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Metallic(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0)),
+    )));
+
+    // Small bright lights far out of focus show the shape of the aperture
+    let fairy_light = Arc::new(DiffLight(DiffuseLight::new(Arc::new(SolidColor::new(
+        40.0, 30.0, 15.0,
+    )))));
+    for i in 0..7 {
+        world.add(Arc::new(Sphere::new_shared(
+            Point3::new(-10.0, 1.2 + 0.3 * f64::from(i % 2), -3.0 + f64::from(i)),
+            0.05,
+            fairy_light.clone(),
+        )));
+    }
+
+    let lookfrom = Point3::new(13.0, 2.0, 3.0);
+    let lookat = Point3::new(0.0, 0.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let vfov = 20.0;
+    let aspect_ratio = f64::from(img_w) * f64::from(img_h).recip();
+    let focus_dist = (lookfrom - Point3::new(4.0, 1.0, 0.0)).length();
+    let aperture = 0.8;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        vfov,
+        aspect_ratio,
+        aperture,
+        focus_dist,
+        0.0,
+        1.0,
+    )
+    .with_aperture_mask(Arc::new(StarMask { points: 5 }));
+    Ok((cam, world))
+}
+
+/// Aperture mask of a star with `points` tips, open inside and closed outside.
+struct StarMask {
+    points: u32,
+}
+
+impl crate::texture::Texture for StarMask {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        let (x, y) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+        // Edge of the star moves in and out linearly between tips and valleys
+        let sector = crate::conversion::TWO_PI / f64::from(self.points);
+        let phase = (y.atan2(x).rem_euclid(sector) / sector - 0.5).abs() * 2.0;
+        let edge = 0.4 + 0.6 * phase;
+        if x.hypot(y) <= edge {
+            Color::new_with(1.0)
+        } else {
+            Color::new_with(0.0)
+        }
+    }
+}
+
+/// Section 4.3: Checkerboard world with BVH.
+pub fn checker_world(
+    rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    // Add more balls to the scene and randomize the radius of the smaller ones
+    // Every glass ball shares one material
+    let glass = Arc::new(Dielectric(Diel::new(1.5)));
+    let bound = 15;
+    for (a, b) in (-bound..bound).flat_map(|x| core::iter::repeat(x).zip(-bound..bound)) {
+        let radius = rng.gen_range(0.1, 0.3);
+
+        let choose_mat = rng.gen::<f64>();
+        let center = Point3::new(
+            f64::from(a) + 0.9 * rng.gen::<f64>(),
+            radius,
+            f64::from(b) + 0.9 * rng.gen::<f64>(),
+        );
+
+        if (center - Point3::new(4.0, radius, 0.0)).length() > 0.9 {
+            if choose_mat < 0.8 {
+                // diffuse
+                world.add(Arc::new(MovingSphere::new(
+                    center,
+                    center + Vec3::new(0.0, rng.gen_range(0.0, 0.5), 0.0),
+                    0.0,
+                    1.0,
+                    radius,
+                    Arc::new(Lambertian(Lambert::new(Arc::new(SolidColor::from_color(
+                        Color::random(rng) * Color::random(rng),
+                    ))))),
+                )));
+            } else if choose_mat < 0.95 {
+                // metal
                 world.add(Arc::new(Sphere::new(
                     center,
                     radius,
-                    Dielectric(Diel::new(1.5)),
+                    Metallic(Metal::new(Color::random_range(rng, 0.3, 1.0), 0.0)),
                 )));
+            } else {
+                // glass
+                world.add(Arc::new(Sphere::new_shared(center, radius, glass.clone())));
             }
         }
     }
 
-    let mut world = HittableList::new_from(Arc::new(BvhNode::bvh_node(rng, &mut world, 0.0, 1.0)));
+    let mut world = HittableList::new_from(Arc::new(BvhNode::from_list(&world, 0.0, 1.0)));
 
     world.add(Arc::new(Sphere::new(
         Point3::new(0.5, -1000.0, 0.0),
@@ -175,10 +306,10 @@ pub fn checker_world<R: rand::Rng>(
         )))),
     )));
 
-    world.add(Arc::new(Sphere::new(
+    world.add(Arc::new(Sphere::new_shared(
         Point3::new(0.0, 1.0, 0.0),
         1.0,
-        Dielectric(Diel::new(1.5)),
+        glass,
     )));
 
     world.add(Arc::new(Sphere::new(
@@ -216,8 +347,8 @@ pub fn checker_world<R: rand::Rng>(
 }
 
 /// Section 4.4: Rendering a scene with two checker spheres.
-pub fn two_spheres<R: rand::Rng>(
-    _rng: &mut R,
+pub fn two_spheres(
+    _rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
 ) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
@@ -265,9 +396,118 @@ pub fn two_spheres<R: rand::Rng>(
     Ok((cam, world))
 }
 
+/// Checkered ground receding to the horizon, antialiased so the distant
+/// squares blend together instead of shimmering.
+pub fn checker_horizon(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    // Keep the ground within a half period of the checker's sines in Y, so
+    // only X and Z change the pattern
+    world.add(Arc::new(AaRect::new(
+        -1000.0,
+        1000.0,
+        -1000.0,
+        1000.0,
+        -0.15,
+        Arc::new(Lambertian(Lambert::new(Arc::new(
+            Checker::new(
+                Arc::new(SolidColor::new(0.2, 0.1, 0.7)),
+                Arc::new(SolidColor::new(0.9, 0.9, 0.9)),
+            )
+            .with_antialias(true),
+        )))),
+        Plane::Xz,
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 0.85, -4.0),
+        1.0,
+        Metallic(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0)),
+    )));
+
+    let lookfrom = Point3::new(0.0, 1.0, 2.0);
+    let lookat = Point3::new(0.0, 0.6, -10.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let vfov = 40.0;
+    let aspect_ratio = f64::from(img_w) * f64::from(img_h).recip();
+    let focus_dist = 10.0;
+    let aperture = 0.0;
+    let time0 = 0.0;
+    let time1 = 1.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        vfov,
+        aspect_ratio,
+        aperture,
+        focus_dist,
+        time0,
+        time1,
+    );
+    Ok((cam, world))
+}
+
+/// Two spheres contrasting the world space `Checker`, on the bottom, with the
+/// `UvChecker` following the sphere's texture coordinates, on the top.
+pub fn two_spheres_uv(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -10.0, 0.0),
+        10.0,
+        Lambertian(Lambert::new(Arc::new(Checker::new(
+            Arc::new(SolidColor::new(0.2, 0.1, 0.7)),
+            Arc::new(SolidColor::new(0.9, 0.3, 0.2)),
+        )))),
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 10.0, 0.0),
+        10.0,
+        Lambertian(Lambert::new(Arc::new(UvChecker::new(
+            Arc::new(SolidColor::new(0.2, 0.1, 0.7)),
+            Arc::new(SolidColor::new(0.9, 0.3, 0.2)),
+            10.0,
+        )))),
+    )));
+
+    let lookfrom = Point3::new(13.0, 2.0, 3.0);
+    let lookat = Point3::new(0.0, 0.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let vfov = 20.0;
+    let aspect_ratio = f64::from(img_w) * f64::from(img_h).recip();
+    let focus_dist = 10.0;
+    let aperture = 0.0;
+    let time0 = 0.0;
+    let time1 = 1.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        vfov,
+        aspect_ratio,
+        aperture,
+        focus_dist,
+        time0,
+        time1,
+    );
+    Ok((cam, world))
+}
+
 /// Section 5.1: Scene with two Perlin spheres.
-pub fn perlin_spheres<R: rand::Rng>(
-    _rng: &mut R,
+pub fn perlin_spheres(
+    _rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
 ) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
@@ -309,14 +549,58 @@ pub fn perlin_spheres<R: rand::Rng>(
     Ok((cam, world))
 }
 
+/// Marble spheres with the sphere's noise domain warped into swirls.
+pub fn warped_marble(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
+    let marble = Noise::new_with(1.0, NoiseType::Marble, 4.0, 7, 10.0);
+
+    let mut world = HittableList::with_capacity(2);
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Lambertian(Lambert::new(Arc::new(marble.clone()))),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 2.0, 0.0),
+        2.0,
+        Lambertian(Lambert::new(Arc::new(marble.with_warp(1.5)))),
+    )));
+
+    let lookfrom = Point3::new(13.0, 2.0, 3.0);
+    let lookat = Point3::new(0.0, 0.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let vfov = 40.0;
+    let aspect_ratio = f64::from(img_w) * f64::from(img_h).recip();
+    let focus_dist = 10.0;
+    let aperture = 0.0;
+    let time0 = 0.0;
+    let time1 = 1.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        vfov,
+        aspect_ratio,
+        aperture,
+        focus_dist,
+        time0,
+        time1,
+    );
+    Ok((cam, world))
+}
+
 /// Section 6.2: Load an image texture. In `ray_color`, only return attenuation.
 #[cfg(feature = "images")]
-pub fn earth<R: rand::Rng>(
-    _rng: &mut R,
+pub fn earth(
+    _rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
 ) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
-    let earth_texture = ImageTexture::new("earthmap.jpg")?;
+    let earth_texture = ImageTexture::new(crate::texture::asset_path("earthmap.jpg"))?;
     let globe = Arc::new(Sphere::new(
         Point3::default(),
         2.0,
@@ -352,8 +636,8 @@ pub fn earth<R: rand::Rng>(
 
 /// Section 7.4: Turning objects into lights. Scene with a sphere and rectangle
 /// light.
-pub fn simple_light<R: rand::Rng>(
-    _rng: &mut R,
+pub fn simple_light(
+    _rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
 ) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
@@ -414,8 +698,8 @@ pub fn simple_light<R: rand::Rng>(
 }
 
 /// Section 7.6: Empty Cornell Box scene.
-pub fn naive_cornell_box<R: rand::Rng>(
-    _rng: &mut R,
+pub fn naive_cornell_box(
+    _rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
 ) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
@@ -516,8 +800,8 @@ pub fn naive_cornell_box<R: rand::Rng>(
 }
 
 /// Section 7.7: Empty Cornell Box scene with adjusted normals.
-pub fn cornell_box<R: rand::Rng>(
-    _rng: &mut R,
+pub fn cornell_box(
+    _rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
 ) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
@@ -641,8 +925,8 @@ pub fn cornell_box<R: rand::Rng>(
 }
 
 /// Section 9.2: Cornell box scene with smoke and fog volumes.
-pub fn cornell_smoke<R: rand::Rng>(
-    _rng: &mut R,
+pub fn cornell_smoke(
+    _rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
 ) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
@@ -776,8 +1060,8 @@ pub fn cornell_smoke<R: rand::Rng>(
 
 /// Chapter 10: A scene testing all features.
 #[cfg(feature = "images")]
-pub fn final_scene<R: rand::Rng>(
-    rng: &mut R,
+pub fn final_scene(
+    rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
 ) -> Result<(Camera, HittableList), Box<dyn std::error::Error>> {
@@ -809,7 +1093,7 @@ pub fn final_scene<R: rand::Rng>(
 
     let mut objects = HittableList::new();
 
-    objects.add(Arc::new(BvhNode::bvh_node(rng, &mut boxes1, 0.0, 1.0)));
+    objects.add(Arc::new(BvhNode::from_list(&boxes1, 0.0, 1.0)));
 
     // Light
     let light = DiffuseLight::new(Arc::new(SolidColor::new_with(7.0)));
@@ -871,7 +1155,7 @@ pub fn final_scene<R: rand::Rng>(
     )));
 
     // Earth sphere
-    // let earth_texture = ImageTexture::new("earthmap.jpg")?;
+    // let earth_texture = ImageTexture::new(crate::texture::asset_path("earthmap.jpg"))?;
     // objects.add(Arc::new(Sphere::new(
     //     Point3::new(400.0, 200.0, 400.0),
     //     100.0,
@@ -900,7 +1184,7 @@ pub fn final_scene<R: rand::Rng>(
 
     objects.add(Arc::new(Translate::new(
         Arc::new(RotateY::new(
-            Arc::new(BvhNode::bvh_node(rng, &mut boxes2, 0.0, 1.0)),
+            Arc::new(BvhNode::from_list(&boxes2, 0.0, 1.0)),
             15.0,
             0.0,
             1.0,
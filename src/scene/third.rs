@@ -6,25 +6,72 @@ use crate::aarect::{AaRect, Plane};
 use crate::camera::Camera;
 use crate::hittable::{BoxPrim, FlipFace, HittableList, RotateY, Translate};
 use crate::material::Material::{DiffLight, Lambertian, Metallic};
-use crate::material::{DiffuseLight, Lambert, Metal};
+use crate::material::{DiffuseLight, Lambert, Material, Metal, MetalSampling};
 use crate::texture::SolidColor;
 use crate::vec3::{Color, Point3, Vec3};
 
+/// Camera, objects, and the objects to sample as lights of a scene.
+pub type LitScene = (
+    Camera,
+    HittableList,
+    Arc<dyn crate::hittable::Hittable + Send + Sync>,
+);
+
 /// Section 6.1: Refactored Cornell box.
-pub fn cornell_box<R: rand::Rng>(
-    _rng: &mut R,
+pub fn cornell_box(
+    _rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
-) -> Result<
-    (
-        Camera,
-        HittableList,
-        Arc<dyn crate::hittable::Hittable + Send + Sync>,
-    ),
-    Box<dyn std::error::Error>,
-> {
+) -> Result<LitScene, Box<dyn std::error::Error>> {
     let mut world = HittableList::new();
 
+    let lights = ceiling_light(
+        &mut world,
+        CORNELL_LIGHT,
+        DiffuseLight::new(Arc::new(SolidColor::new_with(15.0))),
+    );
+    let white = cornell_walls(&mut world);
+    world.add(Arc::new(tall_block(white.clone())));
+    world.add(Arc::new(short_block(white)));
+
+    Ok((cornell_camera(img_w, img_h), world, lights))
+}
+
+/// Shape of the Cornell box's usual light, `x0..x1` by `z0..z1`, see
+/// [`ceiling_light`].
+const CORNELL_LIGHT: (f64, f64, f64, f64) = (213.0, 343.0, 227.0, 332.0);
+
+/// Add a light just under the ceiling of the Cornell box over `x0..x1` by
+/// `z0..z1`, shining down, and return its shape for sampling.
+fn ceiling_light(
+    world: &mut HittableList,
+    (x0, x1, z0, z1): (f64, f64, f64, f64),
+    light: DiffuseLight,
+) -> Arc<AaRect> {
+    world.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
+        x0,
+        x1,
+        z0,
+        z1,
+        554.0,
+        Arc::new(DiffLight(light)),
+        Plane::Xz,
+    )))));
+
+    Arc::new(AaRect::new(
+        x0,
+        x1,
+        z0,
+        z1,
+        554.0,
+        Arc::new(Material::default()),
+        Plane::Xz,
+    ))
+}
+
+/// Add the green, red, and white walls of the Cornell box, open towards the
+/// camera, and return the white material for the objects inside.
+fn cornell_walls(world: &mut HittableList) -> Arc<Material> {
     let red = Arc::new(Lambertian(Lambert::new(Arc::new(SolidColor::new(
         0.65, 0.05, 0.05,
     )))));
@@ -34,29 +81,7 @@ pub fn cornell_box<R: rand::Rng>(
     let green = Arc::new(Lambertian(Lambert::new(Arc::new(SolidColor::new(
         0.12, 0.45, 0.15,
     )))));
-    let difflight = DiffuseLight::new(Arc::new(SolidColor::new_with(15.0)));
 
-    // Light
-    world.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
-        213.0,
-        343.0,
-        227.0,
-        332.0,
-        554.0,
-        Arc::new(DiffLight(difflight)),
-        Plane::Xz,
-    )))));
-    let lights = std::sync::Arc::new(crate::aarect::AaRect::new(
-        213.0,
-        343.0,
-        227.0,
-        332.0,
-        554.0,
-        std::sync::Arc::new(crate::material::Material::default()),
-        crate::aarect::Plane::Xz,
-    ));
-
-    // Planes
     world.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
         0.0,
         555.0,
@@ -103,29 +128,39 @@ pub fn cornell_box<R: rand::Rng>(
         Plane::Xy,
     )))));
 
-    // Boxes
-    let box1 = Arc::new(BoxPrim::new(
+    white
+}
+
+/// The tall block at the back of the Cornell box, turned towards the right
+/// wall.
+fn tall_block(material: Arc<Material>) -> Translate {
+    let block = Arc::new(BoxPrim::new(
         &Point3::new_with(0.0),
         &Point3::new(165.0, 330.0, 165.0),
-        white.clone(),
+        material,
     ));
-    let box1 = Translate::new(
-        Arc::new(RotateY::new(box1, 15.0, 0.0, 1.0)),
+    Translate::new(
+        Arc::new(RotateY::new(block, 15.0, 0.0, 1.0)),
         Vec3::new(265.0, 0.0, 295.0),
-    );
-    world.add(Arc::new(box1));
+    )
+}
 
-    let box2 = Arc::new(BoxPrim::new(
+/// The short block at the front of the Cornell box, turned towards the left
+/// wall.
+fn short_block(material: Arc<Material>) -> Translate {
+    let block = Arc::new(BoxPrim::new(
         &Point3::new_with(0.0),
         &Point3::new_with(165.0),
-        white,
+        material,
     ));
-    let box2 = Translate::new(
-        Arc::new(RotateY::new(box2, -18.0, 0.0, 1.0)),
+    Translate::new(
+        Arc::new(RotateY::new(block, -18.0, 0.0, 1.0)),
         Vec3::new(130.0, 0.0, 65.0),
-    );
-    world.add(Arc::new(box2));
+    )
+}
 
+/// Camera looking into the Cornell box through its open side.
+fn cornell_camera(img_w: u32, img_h: u32) -> Camera {
     let lookfrom = Point3::new(278.0, 278.0, -800.0);
     let lookat = Point3::new(278.0, 278.0, 0.0);
     let vup = Vec3::new(0.0, 1.0, 0.0);
@@ -136,7 +171,7 @@ pub fn cornell_box<R: rand::Rng>(
     let time0 = 0.0;
     let time1 = 1.0;
 
-    let cam = Camera::new(
+    Camera::new(
         lookfrom,
         lookat,
         vup,
@@ -146,130 +181,503 @@ pub fn cornell_box<R: rand::Rng>(
         focus_dist,
         time0,
         time1,
+    )
+}
+
+/// Section 12.2: Cornell box with metallic block.
+pub fn cornell_box_metal(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    let lights = ceiling_light(
+        &mut world,
+        CORNELL_LIGHT,
+        DiffuseLight::new(Arc::new(SolidColor::new_with(15.0))),
     );
+    let white = cornell_walls(&mut world);
 
-    Ok((cam, world, lights))
+    let aluminum = Arc::new(Metallic(Metal::new(Color::new(0.8, 0.85, 0.88), 0.0)));
+    world.add(Arc::new(tall_block(aluminum)));
+    world.add(Arc::new(short_block(white)));
+
+    Ok((cornell_camera(img_w, img_h), world, lights))
 }
 
-/// Section 12.2: Cornell box with metallic block.
-pub fn cornell_box_metal<R: rand::Rng>(
-    _rng: &mut R,
+/// Section 12.4: Cornell box with glass sphere.
+pub fn cornell_box_sphere(
+    _rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
-) -> Result<
-    (
-        Camera,
-        HittableList,
-        Arc<dyn crate::hittable::Hittable + Send + Sync>,
-    ),
-    Box<dyn std::error::Error>,
-> {
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    let lights = ceiling_light(
+        &mut world,
+        CORNELL_LIGHT,
+        DiffuseLight::new(Arc::new(SolidColor::new_with(15.0))),
+    );
+    let white = cornell_walls(&mut world);
+    world.add(Arc::new(tall_block(white)));
+
+    let glass_sphere = crate::hittable::Sphere::new(
+        Point3::new(190.0, 90.0, 190.0),
+        90.0,
+        crate::material::Material::Dielectric(crate::material::Diel::new(1.5)),
+    );
+    world.add(Arc::new(glass_sphere));
+
+    Ok((cornell_camera(img_w, img_h), world, lights))
+}
+
+/// Cornell box with a faintly glowing fog sphere.
+pub fn cornell_glowing_fog(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    let lights = ceiling_light(
+        &mut world,
+        CORNELL_LIGHT,
+        DiffuseLight::new(Arc::new(SolidColor::new_with(15.0))),
+    );
+    let white = cornell_walls(&mut world);
+    world.add(Arc::new(tall_block(white)));
+
+    // Glowing fog
+    let boundary = Arc::new(crate::hittable::Sphere::new(
+        Point3::new(190.0, 120.0, 190.0),
+        110.0,
+        crate::material::Material::default(),
+    ));
+    world.add(Arc::new(crate::hittable::ConstantMedium::new_emissive(
+        boundary,
+        Arc::new(SolidColor::new_with(0.8)),
+        0.01,
+        Arc::new(SolidColor::new(0.25, 0.1, 0.03)),
+    )));
+
+    Ok((cornell_camera(img_w, img_h), world, lights))
+}
+
+/// Corner of two rotated walls contrasting the world space `Checker`, on the
+/// right, with the `UvChecker`, on the left and on the sphere.
+pub fn checker_corner(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
     let mut world = HittableList::new();
 
-    let red = Arc::new(Lambertian(Lambert::new(Arc::new(SolidColor::new(
-        0.65, 0.05, 0.05,
-    )))));
     let white = Arc::new(Lambertian(Lambert::new(Arc::new(SolidColor::new(
         0.73, 0.73, 0.73,
     )))));
-    let green = Arc::new(Lambertian(Lambert::new(Arc::new(SolidColor::new(
-        0.12, 0.45, 0.15,
-    )))));
-    let difflight = DiffuseLight::new(Arc::new(SolidColor::new_with(15.0)));
+    let odd: Arc<dyn crate::texture::Texture + Send + Sync> =
+        Arc::new(SolidColor::new(0.2, 0.3, 0.1));
+    let even: Arc<dyn crate::texture::Texture + Send + Sync> = Arc::new(SolidColor::new_with(0.9));
+    let world_checker = Arc::new(Lambertian(Lambert::new(Arc::new(
+        crate::texture::Checker::new(odd.clone(), even.clone()),
+    ))));
+    let uv_checker = Arc::new(Lambertian(Lambert::new(Arc::new(
+        crate::texture::UvChecker::new(odd, even, 8.0),
+    ))));
+    let difflight = DiffuseLight::new(Arc::new(SolidColor::new_with(4.0)));
 
     // Light
     world.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
-        213.0,
-        343.0,
-        227.0,
-        332.0,
-        554.0,
+        0.5,
+        2.5,
+        0.5,
+        2.5,
+        4.0,
         Arc::new(DiffLight(difflight)),
         Plane::Xz,
     )))));
     let lights = std::sync::Arc::new(crate::aarect::AaRect::new(
-        213.0,
-        343.0,
-        227.0,
-        332.0,
-        554.0,
+        0.5,
+        2.5,
+        0.5,
+        2.5,
+        4.0,
         std::sync::Arc::new(crate::material::Material::default()),
         crate::aarect::Plane::Xz,
     ));
 
-    // Planes
-    world.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
-        0.0,
-        555.0,
-        0.0,
-        555.0,
-        555.0,
-        green,
-        Plane::Yz,
-    )))));
+    // Floor
     world.add(Arc::new(AaRect::new(
+        -1.0,
+        4.0,
+        -1.0,
+        4.0,
         0.0,
-        555.0,
-        0.0,
-        555.0,
-        0.0,
-        red,
-        Plane::Yz,
+        white,
+        Plane::Xz,
     )));
-    world.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
-        0.0,
-        555.0,
+
+    // Walls, rotated so neither lines up with the world axes
+    let right_wall = Arc::new(AaRect::new(
         0.0,
-        555.0,
-        555.0,
-        white.clone(),
-        Plane::Xz,
-    )))));
-    world.add(Arc::new(AaRect::new(
+        3.0,
         0.0,
-        555.0,
+        3.0,
         0.0,
-        555.0,
+        world_checker,
+        Plane::Xy,
+    ));
+    world.add(Arc::new(RotateY::new(right_wall, -20.0, 0.0, 1.0)));
+    let left_wall = Arc::new(AaRect::new(
         0.0,
-        white.clone(),
-        Plane::Xz,
-    )));
-    world.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
+        3.0,
         0.0,
-        555.0,
+        3.0,
         0.0,
-        555.0,
-        555.0,
-        white.clone(),
-        Plane::Xy,
-    )))));
-
-    // Boxes
-    let aluminum = Arc::new(Metallic(Metal::new(Color::new(0.8, 0.85, 0.88), 0.0)));
-    let box1 = Arc::new(BoxPrim::new(
-        &Point3::new_with(0.0),
-        &Point3::new(165.0, 330.0, 165.0),
-        aluminum,
+        uv_checker.clone(),
+        Plane::Yz,
     ));
-    let box1 = Translate::new(
-        Arc::new(RotateY::new(box1, 15.0, 0.0, 1.0)),
-        Vec3::new(265.0, 0.0, 295.0),
+    world.add(Arc::new(RotateY::new(left_wall, -20.0, 0.0, 1.0)));
+
+    world.add(Arc::new(crate::hittable::Sphere::new_shared(
+        Point3::new(1.5, 0.6, 1.5),
+        0.6,
+        uv_checker,
+    )));
+
+    let lookfrom = Point3::new(6.0, 2.5, 6.0);
+    let lookat = Point3::new(0.8, 1.2, 0.8);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let vfov = 40.0;
+    let aspect_ratio = f64::from(img_w) * f64::from(img_h).recip();
+    let focus_dist = 10.0;
+    let aperture = 0.0;
+    let time0 = 0.0;
+    let time1 = 1.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        vfov,
+        aspect_ratio,
+        aperture,
+        focus_dist,
+        time0,
+        time1,
+    );
+
+    Ok((cam, world, lights))
+}
+
+/// Cornell box with the short block spinning and sliding while the shutter is
+/// open.
+pub fn cornell_spinning_box(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    let lights = ceiling_light(
+        &mut world,
+        CORNELL_LIGHT,
+        DiffuseLight::new(Arc::new(SolidColor::new_with(15.0))),
     );
-    world.add(Arc::new(box1));
+    let white = cornell_walls(&mut world);
+    world.add(Arc::new(tall_block(white.clone())));
 
     let box2 = Arc::new(BoxPrim::new(
         &Point3::new_with(0.0),
         &Point3::new_with(165.0),
         white,
     ));
-    let box2 = Translate::new(
-        Arc::new(RotateY::new(box2, -18.0, 0.0, 1.0)),
-        Vec3::new(130.0, 0.0, 65.0),
+    let box2 = crate::hittable::Animated::new(
+        box2,
+        vec![
+            crate::hittable::Keyframe::new(0.0, Vec3::new(130.0, 0.0, 65.0), -18.0),
+            crate::hittable::Keyframe::new(1.0, Vec3::new(190.0, 0.0, 65.0), 27.0),
+        ],
     );
     world.add(Arc::new(box2));
 
-    let lookfrom = Point3::new(278.0, 278.0, -800.0);
-    let lookat = Point3::new(278.0, 278.0, 0.0);
+    Ok((cornell_camera(img_w, img_h), world, lights))
+}
+
+/// Cornell box filled with patchy fog, lit through the gaps of a slatted panel
+/// under the light so the fog shows shafts of light. Best rendered with
+/// `RenderMode::Volumetric`.
+pub fn cornell_light_shafts(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    let lights = ceiling_light(
+        &mut world,
+        CORNELL_LIGHT,
+        DiffuseLight::new(Arc::new(SolidColor::new_with(15.0))),
+    );
+    let white = cornell_walls(&mut world);
+
+    // Slats under the light, running along Z
+    for k in 0..6 {
+        let x = 183.0 + 35.0 * f64::from(k);
+        world.add(Arc::new(BoxPrim::new(
+            &Point3::new(x, 480.0, 190.0),
+            &Point3::new(x + 20.0, 490.0, 370.0),
+            white.clone(),
+        )));
+    }
+    world.add(Arc::new(tall_block(white)));
+
+    // Fog
+    let boundary = Arc::new(BoxPrim::new(
+        &Point3::new_with(0.0),
+        &Point3::new_with(555.0),
+        Arc::new(crate::material::Material::default()),
+    ));
+    world.add(Arc::new(crate::hittable::ConstantMedium::new_textured(
+        boundary,
+        Arc::new(SolidColor::new_with(1.0)),
+        Arc::new(
+            crate::texture::Noise::builder()
+                .kind(crate::perlin::NoiseType::Smooth)
+                .scale(0.01)
+                .phase(0.0)
+                .build(),
+        ),
+        0.003,
+    )));
+
+    Ok((cornell_camera(img_w, img_h), world, lights))
+}
+
+/// Cornell box lit only by a glowing cube hanging in the middle, which is
+/// importance sampled as a light.
+pub fn cornell_glowing_cube(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    // Light
+    let difflight = DiffuseLight::new(Arc::new(SolidColor::new_with(4.0)));
+    let light_min = Point3::new(318.0, 360.0, 140.0);
+    let light_max = Point3::new(408.0, 450.0, 230.0);
+    world.add(Arc::new(BoxPrim::new(
+        &light_min,
+        &light_max,
+        Arc::new(DiffLight(difflight)),
+    )));
+    let lights = std::sync::Arc::new(BoxPrim::new(
+        &light_min,
+        &light_max,
+        std::sync::Arc::new(crate::material::Material::default()),
+    ));
+
+    let white = cornell_walls(&mut world);
+    world.add(Arc::new(tall_block(white)));
+
+    Ok((cornell_camera(img_w, img_h), world, lights))
+}
+
+/// Cornell box with a block of brushed aluminum, streaking highlights
+/// vertically.
+pub fn cornell_box_brushed(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    let lights = ceiling_light(
+        &mut world,
+        CORNELL_LIGHT,
+        DiffuseLight::new(Arc::new(SolidColor::new_with(15.0))),
+    );
+    let white = cornell_walls(&mut world);
+
+    let aluminum = Arc::new(Metallic(Metal::new_anisotropic(
+        Color::new(0.8, 0.85, 0.88),
+        0.02,
+        0.3,
+    )));
+    world.add(Arc::new(tall_block(aluminum)));
+    world.add(Arc::new(short_block(white)));
+
+    Ok((cornell_camera(img_w, img_h), world, lights))
+}
+
+/// Cornell box with a block of rough aluminum, whose reflections are sampled
+/// towards the light.
+pub fn cornell_box_glossy(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    let lights = ceiling_light(
+        &mut world,
+        CORNELL_LIGHT,
+        DiffuseLight::new(Arc::new(SolidColor::new_with(15.0))),
+    );
+    cornell_walls(&mut world);
+
+    let aluminum = Arc::new(Metallic(
+        Metal::new(Color::new(0.8, 0.85, 0.88), 0.3).with_sampling(MetalSampling::Glossy),
+    ));
+    world.add(Arc::new(tall_block(aluminum.clone())));
+    world.add(Arc::new(short_block(aluminum)));
+
+    Ok((cornell_camera(img_w, img_h), world, lights))
+}
+
+/// Cornell box lit by a softbox, whose light is narrowed towards the floor.
+pub fn cornell_box_softbox(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    // Brighter to make up for the power lost by narrowing the spread
+    let spread_power = 8.0;
+    let lights = ceiling_light(
+        &mut world,
+        CORNELL_LIGHT,
+        DiffuseLight::new_directional(
+            Arc::new(SolidColor::new_with(15.0)),
+            (spread_power + 2.0) / 2.0,
+            spread_power,
+        ),
+    );
+    let white = cornell_walls(&mut world);
+    world.add(Arc::new(tall_block(white.clone())));
+    world.add(Arc::new(short_block(white)));
+
+    Ok((cornell_camera(img_w, img_h), world, lights))
+}
+
+/// Cornell box with a warm spotlight shining onto the floor from the upper
+/// right, alongside the ceiling light.
+pub fn cornell_spotlight(
+    rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<(Camera, crate::world::World), Box<dyn std::error::Error>> {
+    let (cam, objects, lights) = cornell_box(rng, img_w, img_h)?;
+
+    let spot = crate::light::SpotLight::new(
+        Point3::new(450.0, 500.0, 150.0),
+        Point3::new(278.0, 0.0, 278.0) - Point3::new(450.0, 500.0, 150.0),
+        Color::new(1.0, 0.85, 0.6),
+        12.0,
+        18.0,
+        1_000_000.0,
+    );
+    let world = crate::world::World::new(objects, lights).with_spot_lights(vec![spot]);
+
+    Ok((cam, world))
+}
+
+/// Cornell box lit by a small, bright light, with most diffuse bounces
+/// sampled towards it.
+pub fn cornell_small_light(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<(Camera, crate::world::World), Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    let lights = ceiling_light(
+        &mut world,
+        (268.0, 288.0, 270.0, 290.0),
+        DiffuseLight::new(Arc::new(SolidColor::new_with(500.0))),
+    );
+    let white = cornell_walls(&mut world);
+    world.add(Arc::new(tall_block(white.clone())));
+    world.add(Arc::new(short_block(white)));
+
+    let world = crate::world::World::new(world, lights).with_light_weight(0.9);
+
+    Ok((cornell_camera(img_w, img_h), world))
+}
+
+/// Cornell box with an air bubble trapped inside the glass sphere.
+pub fn cornell_nested_glass(
+    rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let (cam, mut world, lights) = cornell_box_sphere(rng, img_w, img_h)?;
+
+    // Air bubble inside the glass, off center so it refracts unevenly
+    let bubble = crate::hittable::Sphere::new(
+        Point3::new(170.0, 110.0, 175.0),
+        40.0,
+        crate::material::Material::Dielectric(crate::material::Diel::new(1.0)),
+    );
+    world.add(Arc::new(bubble));
+
+    Ok((cam, world, lights))
+}
+
+/// Row of Phong spheres under an area light, with the shininess rising from
+/// left to right.
+pub fn phong_spheres(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    // Light
+    world.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
+        -6.0,
+        6.0,
+        -3.0,
+        3.0,
+        8.0,
+        Arc::new(DiffLight(DiffuseLight::new(Arc::new(
+            SolidColor::new_with(4.0),
+        )))),
+        Plane::Xz,
+    )))));
+    let lights = Arc::new(AaRect::new(
+        -6.0,
+        6.0,
+        -3.0,
+        3.0,
+        8.0,
+        Arc::new(crate::material::Material::default()),
+        Plane::Xz,
+    ));
+
+    world.add(Arc::new(crate::hittable::Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Lambertian(Lambert::new(Arc::new(SolidColor::new_with(0.5)))),
+    )));
+
+    for (i, &shininess) in [1.0, 10.0, 50.0, 200.0, 1000.0].iter().enumerate() {
+        world.add(Arc::new(crate::hittable::Sphere::new(
+            Point3::new(2.2 * (i as f64 - 2.0), 1.0, 0.0),
+            1.0,
+            crate::material::Material::Phong(crate::material::Phong::new(
+                Arc::new(SolidColor::new(0.6, 0.1, 0.1)),
+                Color::new_with(0.35),
+                shininess,
+            )),
+        )));
+    }
+
+    let lookfrom = Point3::new(0.0, 3.0, 12.0);
+    let lookat = Point3::new(0.0, 1.0, 0.0);
     let vup = Vec3::new(0.0, 1.0, 0.0);
     let vfov = 40.0;
     let aspect_ratio = f64::from(img_w) * f64::from(img_h).recip();
@@ -293,122 +701,209 @@ pub fn cornell_box_metal<R: rand::Rng>(
     Ok((cam, world, lights))
 }
 
-/// Section 12.4: Cornell box with glass sphere.
-pub fn cornell_box_sphere<R: rand::Rng>(
-    _rng: &mut R,
+/// Spheres on open ground, meant to be lit by the sun and sky background.
+pub fn sky_spheres(
+    _rng: &mut crate::rng::SceneRng,
     img_w: u32,
     img_h: u32,
-) -> Result<
-    (
-        Camera,
-        HittableList,
-        Arc<dyn crate::hittable::Hittable + Send + Sync>,
-    ),
-    Box<dyn std::error::Error>,
-> {
+) -> Result<LitScene, Box<dyn std::error::Error>> {
     let mut world = HittableList::new();
 
-    let red = Arc::new(Lambertian(Lambert::new(Arc::new(SolidColor::new(
-        0.65, 0.05, 0.05,
-    )))));
-    let white = Arc::new(Lambertian(Lambert::new(Arc::new(SolidColor::new(
-        0.73, 0.73, 0.73,
-    )))));
-    let green = Arc::new(Lambertian(Lambert::new(Arc::new(SolidColor::new(
-        0.12, 0.45, 0.15,
-    )))));
-    let difflight = DiffuseLight::new(Arc::new(SolidColor::new_with(15.0)));
+    // Nothing in the scene glows, so sample the upper sky where most of the
+    // background's light comes from. The sphere isn't part of the world.
+    let lights = Arc::new(crate::hittable::Sphere::new(
+        Point3::new(0.0, 100.0, 0.0),
+        70.0,
+        crate::material::Material::default(),
+    ));
 
-    // Light
-    world.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
-        213.0,
-        343.0,
-        227.0,
-        332.0,
-        554.0,
-        Arc::new(DiffLight(difflight)),
-        Plane::Xz,
-    )))));
-    let lights = std::sync::Arc::new(crate::aarect::AaRect::new(
-        213.0,
-        343.0,
-        227.0,
-        332.0,
-        554.0,
-        std::sync::Arc::new(crate::material::Material::default()),
-        crate::aarect::Plane::Xz,
+    world.add(Arc::new(crate::hittable::Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Lambertian(Lambert::new(Arc::new(crate::texture::Checker::new(
+            Arc::new(SolidColor::new(0.2, 0.3, 0.1)),
+            Arc::new(SolidColor::new_with(0.7)),
+        )))),
+    )));
+    world.add(Arc::new(crate::hittable::Sphere::new(
+        Point3::new(-2.2, 1.0, 0.0),
+        1.0,
+        Lambertian(Lambert::new(Arc::new(SolidColor::new(0.7, 0.2, 0.1)))),
+    )));
+    world.add(Arc::new(crate::hittable::Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        crate::material::Material::Dielectric(crate::material::Diel::new(1.5)),
+    )));
+    world.add(Arc::new(crate::hittable::Sphere::new(
+        Point3::new(2.2, 1.0, 0.0),
+        1.0,
+        Metallic(Metal::new(Color::new(0.8, 0.85, 0.88), 0.05)),
+    )));
+
+    let lookfrom = Point3::new(0.0, 2.0, 9.0);
+    let lookat = Point3::new(0.0, 1.2, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let vfov = 40.0;
+    let aspect_ratio = f64::from(img_w) * f64::from(img_h).recip();
+    let focus_dist = 10.0;
+    let aperture = 0.0;
+    let time0 = 0.0;
+    let time1 = 1.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        vfov,
+        aspect_ratio,
+        aperture,
+        focus_dist,
+        time0,
+        time1,
+    );
+
+    Ok((cam, world, lights))
+}
+
+/// A mirror and two rough metal spheres, to be surrounded by an environment
+/// map with `--environment`. The rough spheres show the map's blurred levels.
+pub fn environment_spheres(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    // Metal reflections are traced without sampling lights, so this sphere
+    // only stands in for the sky. It isn't part of the world.
+    let lights = Arc::new(crate::hittable::Sphere::new(
+        Point3::new(0.0, 100.0, 0.0),
+        70.0,
+        crate::material::Material::default(),
     ));
 
-    // Planes
-    world.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
-        0.0,
-        555.0,
-        0.0,
-        555.0,
-        555.0,
-        green,
-        Plane::Yz,
-    )))));
+    // Real metals reflect more towards their rims
+    world.add(Arc::new(crate::hittable::Sphere::new(
+        Point3::new(-2.2, 0.0, 0.0),
+        1.0,
+        Metallic(Metal::new(Color::new(0.9, 0.9, 0.9), 0.0).with_fresnel(true)),
+    )));
+    world.add(Arc::new(crate::hittable::Sphere::new(
+        Point3::new(0.0, 0.0, 0.0),
+        1.0,
+        Metallic(Metal::new(Color::new(0.9, 0.9, 0.9), 0.2).with_fresnel(true)),
+    )));
+    world.add(Arc::new(crate::hittable::Sphere::new(
+        Point3::new(2.2, 0.0, 0.0),
+        1.0,
+        Metallic(Metal::new(Color::new(0.9, 0.9, 0.9), 0.6).with_fresnel(true)),
+    )));
+
+    let lookfrom = Point3::new(0.0, 0.5, 9.0);
+    let lookat = Point3::new(0.0, 0.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let vfov = 35.0;
+    let aspect_ratio = f64::from(img_w) * f64::from(img_h).recip();
+    let focus_dist = 10.0;
+    let aperture = 0.0;
+    let time0 = 0.0;
+    let time1 = 1.0;
+
+    let cam = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        vfov,
+        aspect_ratio,
+        aperture,
+        focus_dist,
+        time0,
+        time1,
+    );
+
+    Ok((cam, world, lights))
+}
+
+/// Portrait lighting from a ring light around the camera, which leaves ring
+/// shaped highlights in shiny surfaces and barely any shadows.
+pub fn ring_light(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    let lookfrom = Point3::new(0.0, 1.0, 10.0);
+    let lookat = Point3::new(0.0, 1.0, 0.0);
+
+    // Light, facing the subject from just in front of the camera, which looks
+    // through the hole in the middle
+    let ring_center = Point3::new(0.0, 1.0, 9.5);
+    let ring_normal = lookat - lookfrom;
+    world.add(Arc::new(crate::hittable::Disk::new_annulus(
+        ring_center,
+        ring_normal,
+        1.2,
+        1.8,
+        Arc::new(DiffLight(DiffuseLight::new(Arc::new(
+            SolidColor::new_with(30.0),
+        )))),
+    )));
+    let lights = Arc::new(crate::hittable::Disk::new_annulus(
+        ring_center,
+        ring_normal,
+        1.2,
+        1.8,
+        Arc::new(crate::material::Material::default()),
+    ));
+
+    // Backdrop and floor
     world.add(Arc::new(AaRect::new(
-        0.0,
-        555.0,
-        0.0,
-        555.0,
-        0.0,
-        red,
-        Plane::Yz,
+        -20.0,
+        20.0,
+        -1.0,
+        20.0,
+        -4.0,
+        Arc::new(Lambertian(Lambert::new(Arc::new(SolidColor::new(
+            0.35, 0.4, 0.45,
+        ))))),
+        Plane::Xy,
     )));
-    world.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
-        0.0,
-        555.0,
-        0.0,
-        555.0,
-        555.0,
-        white.clone(),
-        Plane::Xz,
-    )))));
     world.add(Arc::new(AaRect::new(
+        -20.0,
+        20.0,
+        -4.0,
+        20.0,
         0.0,
-        555.0,
-        0.0,
-        555.0,
-        0.0,
-        white.clone(),
+        Arc::new(Lambertian(Lambert::new(Arc::new(SolidColor::new_with(
+            0.3,
+        ))))),
         Plane::Xz,
     )));
-    world.add(Arc::new(FlipFace::new(Arc::new(AaRect::new(
-        0.0,
-        555.0,
-        0.0,
-        555.0,
-        555.0,
-        white.clone(),
-        Plane::Xy,
-    )))));
-
-    // Boxes
-    let box1 = Arc::new(BoxPrim::new(
-        &Point3::new_with(0.0),
-        &Point3::new(165.0, 330.0, 165.0),
-        white,
-    ));
-    let box1 = Translate::new(
-        Arc::new(RotateY::new(box1, 15.0, 0.0, 1.0)),
-        Vec3::new(265.0, 0.0, 295.0),
-    );
-    world.add(Arc::new(box1));
 
-    let glass_sphere = crate::hittable::Sphere::new(
-        Point3::new(190.0, 90.0, 190.0),
-        90.0,
-        crate::material::Material::Dielectric(crate::material::Diel::new(1.5)),
-    );
-    world.add(Arc::new(glass_sphere));
+    world.add(Arc::new(crate::hittable::Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Lambertian(Lambert::new(Arc::new(SolidColor::new(0.8, 0.55, 0.45)))),
+    )));
+    world.add(Arc::new(crate::hittable::Sphere::new(
+        Point3::new(-2.1, 0.7, 0.5),
+        0.7,
+        crate::material::Material::Phong(crate::material::Phong::new(
+            Arc::new(SolidColor::new(0.1, 0.15, 0.5)),
+            Color::new_with(0.3),
+            200.0,
+        )),
+    )));
+    world.add(Arc::new(crate::hittable::Sphere::new(
+        Point3::new(2.1, 0.7, 0.5),
+        0.7,
+        Metallic(Metal::new(Color::new(0.9, 0.9, 0.9), 0.0)),
+    )));
 
-    let lookfrom = Point3::new(278.0, 278.0, -800.0);
-    let lookat = Point3::new(278.0, 278.0, 0.0);
     let vup = Vec3::new(0.0, 1.0, 0.0);
-    let vfov = 40.0;
+    let vfov = 30.0;
     let aspect_ratio = f64::from(img_w) * f64::from(img_h).recip();
     let focus_dist = 10.0;
     let aperture = 0.0;
@@ -429,3 +924,151 @@ pub fn cornell_box_sphere<R: rand::Rng>(
 
     Ok((cam, world, lights))
 }
+
+/// Cornell box seen through a card with checkerboard holes cut in it.
+pub fn cornell_cutout(
+    rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let (cam, mut world, lights) = cornell_box(rng, img_w, img_h)?;
+
+    // Card between the boxes and the back wall, with every other square cut
+    // out
+    let holes: Arc<dyn crate::texture::Texture + Send + Sync> =
+        Arc::new(crate::texture::UvChecker::new(
+            Arc::new(SolidColor::new_with(0.0)),
+            Arc::new(SolidColor::new_with(1.0)),
+            6.0,
+        ));
+    world.add(Arc::new(AaRect::new(
+        60.0,
+        495.0,
+        0.0,
+        480.0,
+        420.0,
+        Arc::new(Lambertian(
+            Lambert::new(Arc::new(SolidColor::new(0.8, 0.6, 0.2))).with_alpha(holes),
+        )),
+        Plane::Xy,
+    )));
+
+    Ok((cam, world, lights))
+}
+
+/// Cornell box seen through a pane of stained glass tinted from red to blue.
+pub fn cornell_stained_glass(
+    rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let (cam, mut world, lights) = cornell_box(rng, img_w, img_h)?;
+
+    // Pane in front of the boxes, tinted where light refracts into it
+    let tint = Arc::new(crate::texture::UvGradient::new(
+        Color::new(0.9, 0.2, 0.2),
+        Color::new(0.2, 0.3, 0.9),
+    ));
+    world.add(Arc::new(BoxPrim::new(
+        &Point3::new(80.0, 0.0, 20.0),
+        &Point3::new(475.0, 420.0, 30.0),
+        Arc::new(crate::material::Material::Dielectric(
+            crate::material::Diel::new_textured(1.5, tint),
+        )),
+    )));
+
+    Ok((cam, world, lights))
+}
+
+/// Cornell box lit by its usual light and four dim ones in the corners of the
+/// ceiling, sampled in proportion to their power.
+pub fn cornell_many_lights(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    let bright = Color::new_with(15.0);
+    let dim = Color::new(2.0, 1.6, 1.0);
+
+    // Lights, the usual one in the middle and a small dim one in each corner.
+    // They're picked for sampling in proportion to their power, so the dim
+    // ones only take a small share of the samples.
+    let mut lights = crate::hittable::LightList::new();
+    let rects = [
+        (213.0, 343.0, 227.0, 332.0, bright),
+        (40.0, 100.0, 40.0, 100.0, dim),
+        (455.0, 515.0, 40.0, 100.0, dim),
+        (40.0, 100.0, 455.0, 515.0, dim),
+        (455.0, 515.0, 455.0, 515.0, dim),
+    ];
+    for &(x0, x1, z0, z1, emit) in rects.iter() {
+        let rect = Arc::new(AaRect::new(
+            x0,
+            x1,
+            z0,
+            z1,
+            554.0,
+            Arc::new(DiffLight(DiffuseLight::new(Arc::new(
+                SolidColor::from_color(emit),
+            )))),
+            Plane::Xz,
+        ));
+        world.add(Arc::new(FlipFace::new(rect.clone())));
+        lights.add(rect)?;
+    }
+    let lights = Arc::new(lights);
+
+    let white = cornell_walls(&mut world);
+    world.add(Arc::new(tall_block(white.clone())));
+    world.add(Arc::new(short_block(white)));
+
+    Ok((cornell_camera(img_w, img_h), world, lights))
+}
+
+/// Cornell box lit through a sheet of paper hung under the light, which glows
+/// where the light shines through it.
+pub fn cornell_diffuser(
+    _rng: &mut crate::rng::SceneRng,
+    img_w: u32,
+    img_h: u32,
+) -> Result<LitScene, Box<dyn std::error::Error>> {
+    let mut world = HittableList::new();
+
+    ceiling_light(
+        &mut world,
+        CORNELL_LIGHT,
+        DiffuseLight::new(Arc::new(SolidColor::new_with(30.0))),
+    );
+
+    // Paper under the light, which the camera only sees lit from behind. It
+    // hides the light from the rest of the box, so it's sampled instead.
+    let paper = Arc::new(crate::material::Material::Translucent(
+        crate::material::Translucent::new(Arc::new(SolidColor::new(0.9, 0.85, 0.75)), 0.6),
+    ));
+    world.add(Arc::new(AaRect::new(
+        113.0,
+        443.0,
+        127.0,
+        432.0,
+        480.0,
+        paper,
+        Plane::Xz,
+    )));
+    let lights = std::sync::Arc::new(crate::aarect::AaRect::new(
+        113.0,
+        443.0,
+        127.0,
+        432.0,
+        480.0,
+        std::sync::Arc::new(crate::material::Material::default()),
+        crate::aarect::Plane::Xz,
+    ));
+
+    let white = cornell_walls(&mut world);
+    world.add(Arc::new(tall_block(white.clone())));
+    world.add(Arc::new(short_block(white)));
+
+    Ok((cornell_camera(img_w, img_h), world, lights))
+}
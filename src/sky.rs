@@ -0,0 +1,274 @@
+//! Analytic daylight sky, following Preetham, Shirley and Smits, *A Practical
+//! Analytic Model for Daylight* (1999).
+
+use crate::vec3::{Color, Vec3};
+
+/// Clear sky lit by the sun, seen by rays that miss everything.
+///
+/// The sky's brightness and color across the dome come from the Preetham
+/// model. The sun is a disk around `sun_direction` whose light is reddened
+/// by the air it passes through, so low suns turn orange.
+///
+/// Rays rarely find such a small disk by chance, so `ray_color` lights diffuse
+/// surfaces by sending a shadow ray straight to the sun instead.
+#[derive(Clone, Copy, Debug)]
+pub struct Sky {
+    /// Direction towards the sun, with +Y up.
+    pub sun_direction: Vec3,
+    /// Haziness of the air, from `2.0` for a very clear day to around `10.0`
+    /// for a hazy one.
+    pub turbidity: f64,
+    /// Angular radius of the sun disk in degrees.
+    pub sun_angle: f64,
+    /// Light falling on a surface facing the sun, before passing through the
+    /// air.
+    pub sun_irradiance: f64,
+    /// Scale from the model's luminance in kcd/m² to the renderer's radiance.
+    pub scale: f64,
+    /// Fraction of the horizon's light seen below it.
+    pub ground_albedo: f64,
+}
+
+impl Sky {
+    /// Create a sky with the sun in `sun_direction`, and the defaults for
+    /// everything else.
+    pub fn new(sun_direction: Vec3, turbidity: f64) -> Self {
+        Self {
+            sun_direction,
+            turbidity,
+            ..Self::default()
+        }
+    }
+
+    /// Create a sky with the sun `elevation` degrees above the horizon and
+    /// `azimuth` degrees around from -Z towards +X.
+    pub fn from_angles(elevation: f64, azimuth: f64, turbidity: f64) -> Self {
+        Self::new(direction_from_angles(elevation, azimuth), turbidity)
+    }
+
+    /// Set the angular radius of the sun disk in degrees.
+    pub fn with_sun_angle(mut self, sun_angle: f64) -> Self {
+        self.sun_angle = sun_angle;
+        self
+    }
+
+    /// Set the light falling on a surface facing the sun.
+    pub fn with_sun_irradiance(mut self, sun_irradiance: f64) -> Self {
+        self.sun_irradiance = sun_irradiance;
+        self
+    }
+
+    /// Set the scale from the model's luminance to radiance.
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the fraction of the horizon's light seen below it.
+    pub fn with_ground_albedo(mut self, ground_albedo: f64) -> Self {
+        self.ground_albedo = ground_albedo;
+        self
+    }
+
+    /// Radiance arriving from `direction`, including the sun disk.
+    pub fn color(&self, direction: &Vec3) -> Color {
+        let sky = self.sky_color(direction);
+        let cos_sun_angle = self.sun_angle.to_radians().cos();
+        match self.sun() {
+            Some((sun, irradiance)) if direction.unit_vector().dot(&sun) >= cos_sun_angle => {
+                // Spread the sun's light evenly over its disk
+                sky + irradiance * (crate::conversion::TWO_PI * (1.0 - cos_sun_angle)).recip()
+            }
+            _ => sky,
+        }
+    }
+
+    /// Direction towards the sun and the light it shines on a surface facing
+    /// it, or `None` once it has set.
+    pub fn sun(&self) -> Option<(Vec3, Color)> {
+        let sun = self.sun_direction.unit_vector();
+        if sun.y() < 0.0 {
+            return None;
+        }
+
+        Some((sun, self.sun_transmittance(sun.y()) * self.sun_irradiance))
+    }
+
+    /// Radiance arriving from `direction`, leaving out the sun disk.
+    pub fn sky_color(&self, direction: &Vec3) -> Color {
+        let sun = self.sun_direction.unit_vector();
+        // The model only covers suns at or above the horizon
+        let sun_theta = sun.y().clamp(0.0, 1.0).acos();
+
+        // Below the horizon, see a dimmed copy of the horizon. Looking straight
+        // down, every point of the horizon is as near, so pick one
+        let d = direction.unit_vector();
+        let (d, ground) = if d.y() < 0.0 {
+            let horizontal = Vec3::new(d.x(), 0.0, d.z());
+            let horizon = if horizontal.length_squared() > 1e-12 {
+                horizontal.unit_vector()
+            } else {
+                Vec3::new(1.0, 0.0, 0.0)
+            };
+            (horizon, self.ground_albedo)
+        } else {
+            (d, 1.0)
+        };
+        // Perez's formula blows up at the horizon itself
+        let cos_theta = d.y().max(0.001);
+        let gamma = d.dot(&sun).clamp(-1.0, 1.0).acos();
+
+        let t = self.turbidity;
+        let luminance =
+            zenith_luminance(t, sun_theta) * perez_ratio(&PEREZ_Y, t, cos_theta, gamma, sun_theta);
+        let x = zenith_chromaticity(&ZENITH_X, t, sun_theta)
+            * perez_ratio(&PEREZ_X, t, cos_theta, gamma, sun_theta);
+        let y = zenith_chromaticity(&ZENITH_Y, t, sun_theta)
+            * perez_ratio(&PEREZ_CHROMA_Y, t, cos_theta, gamma, sun_theta);
+
+        xyy_to_rgb(x, y, luminance * self.scale) * ground
+    }
+
+    /// Fraction of each color of sunlight reaching the ground, for a sun whose
+    /// direction has height `cos_theta`.
+    fn sun_transmittance(&self, cos_theta: f64) -> Color {
+        // Kasten and Young's relative air mass
+        let cos_theta = cos_theta.clamp(0.0, 1.0);
+        let elevation = 90.0 - cos_theta.acos().to_degrees();
+        let air_mass = (cos_theta + 0.50572 * (elevation + 6.07995).powf(-1.6364)).recip();
+
+        // Rayleigh scattering at roughly 680, 550 and 440 nm, plus haze which
+        // dims every color alike
+        let haze = 0.012 * self.turbidity;
+        let depth = |rayleigh: f64| (-air_mass * (rayleigh + haze)).exp();
+        Color::new(depth(0.04), depth(0.09), depth(0.21))
+    }
+}
+
+impl Default for Sky {
+    /// Clear midday sky.
+    fn default() -> Self {
+        Self {
+            sun_direction: direction_from_angles(60.0, 30.0),
+            turbidity: 3.0,
+            sun_angle: 0.5,
+            sun_irradiance: 4.0,
+            scale: 0.05,
+            ground_albedo: 0.3,
+        }
+    }
+}
+
+/// Unit vector `elevation` degrees above the horizon and `azimuth` degrees
+/// around from -Z towards +X.
+fn direction_from_angles(elevation: f64, azimuth: f64) -> Vec3 {
+    let (sin_elevation, cos_elevation) = elevation.to_radians().sin_cos();
+    let (sin_azimuth, cos_azimuth) = azimuth.to_radians().sin_cos();
+    Vec3::new(
+        cos_elevation * sin_azimuth,
+        sin_elevation,
+        -cos_elevation * cos_azimuth,
+    )
+}
+
+/// Perez coefficients `A` to `E` as `(slope, intercept)` in the turbidity for
+/// luminance and the two chromaticity coordinates.
+const PEREZ_Y: [(f64, f64); 5] = [
+    (0.1787, -1.4630),
+    (-0.3554, 0.4275),
+    (-0.0227, 5.3251),
+    (0.1206, -2.5771),
+    (-0.0670, 0.3703),
+];
+const PEREZ_X: [(f64, f64); 5] = [
+    (-0.0193, -0.2592),
+    (-0.0665, 0.0008),
+    (-0.0004, 0.2125),
+    (-0.0641, -0.8989),
+    (-0.0033, 0.0452),
+];
+const PEREZ_CHROMA_Y: [(f64, f64); 5] = [
+    (-0.0167, -0.2608),
+    (-0.0950, 0.0092),
+    (-0.0079, 0.2102),
+    (-0.0441, -1.6537),
+    (-0.0109, 0.0529),
+];
+
+/// Zenith chromaticity as cubics in the sun's zenith angle, for the squared,
+/// linear and constant turbidity terms.
+const ZENITH_X: [[f64; 4]; 3] = [
+    [0.00166, -0.00375, 0.00209, 0.0],
+    [-0.02903, 0.06377, -0.03202, 0.00394],
+    [0.11693, -0.21196, 0.06052, 0.25886],
+];
+const ZENITH_Y: [[f64; 4]; 3] = [
+    [0.00275, -0.00610, 0.00317, 0.0],
+    [-0.04214, 0.08970, -0.04153, 0.00516],
+    [0.15346, -0.26756, 0.06670, 0.26688],
+];
+
+/// Luminance at the zenith in kcd/m².
+fn zenith_luminance(turbidity: f64, sun_theta: f64) -> f64 {
+    let chi = (4.0 / 9.0 - turbidity / 120.0) * (core::f64::consts::PI - 2.0 * sun_theta);
+    (4.0453 * turbidity - 4.9710) * chi.tan() - 0.2155 * turbidity + 2.4192
+}
+
+/// Chromaticity coordinate at the zenith.
+fn zenith_chromaticity(table: &[[f64; 4]; 3], turbidity: f64, sun_theta: f64) -> f64 {
+    let cubic = |c: &[f64; 4]| ((c[0] * sun_theta + c[1]) * sun_theta + c[2]) * sun_theta + c[3];
+    turbidity * turbidity * cubic(&table[0]) + turbidity * cubic(&table[1]) + cubic(&table[2])
+}
+
+/// Perez's distribution for a view at `cos_theta` from the zenith and `gamma`
+/// from the sun, relative to its value at the zenith.
+fn perez_ratio(
+    table: &[(f64, f64); 5],
+    turbidity: f64,
+    cos_theta: f64,
+    gamma: f64,
+    sun_theta: f64,
+) -> f64 {
+    let [a, b, c, d, e] = [0, 1, 2, 3, 4].map(|i| table[i].0 * turbidity + table[i].1);
+    let perez = |cos_theta: f64, gamma: f64| {
+        let cos_gamma = gamma.cos();
+        (1.0 + a * (b / cos_theta).exp())
+            * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+    };
+
+    perez(cos_theta, gamma) / perez(1.0, sun_theta)
+}
+
+/// Convert CIE xyY to linear sRGB, clipping colors outside its gamut.
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0.0 {
+        return Color::new_with(0.0);
+    }
+    let cx = x / y * luminance;
+    let cz = (1.0 - x - y) / y * luminance;
+
+    Color::new(
+        (3.2406 * cx - 1.5372 * luminance - 0.4986 * cz).max(0.0),
+        (-0.9689 * cx + 1.8758 * luminance + 0.0415 * cz).max(0.0),
+        (0.0557 * cx - 0.2040 * luminance + 1.0570 * cz).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looking_straight_down_sees_the_ground() {
+        let sky = Sky::default();
+        for direction in &[Vec3::new(0.0, -1.0, 0.0), Vec3::new(1e-200, -1.0, 0.0)] {
+            let ground = sky.color(direction);
+            assert!(
+                ground.x().is_finite() && ground.y().is_finite() && ground.z().is_finite(),
+                "{:?}",
+                ground
+            );
+            assert!(ground.x() > 0.0 && ground.y() > 0.0 && ground.z() > 0.0);
+        }
+    }
+}
@@ -3,7 +3,7 @@
 use std::sync::Arc;
 
 use crate::perlin::NoiseType;
-use crate::vec3::{Color, Point3};
+use crate::vec3::{Color, Point3, Vec3};
 
 /// A trait for procedural or lookup textures.
 pub trait Texture {
@@ -45,6 +45,38 @@ impl Texture for SolidColor {
     }
 }
 
+thread_local! {
+    /// Angle covered by a pixel in radians, and how far the current path has
+    /// travelled to the point being shaded.
+    static FOOTPRINT: core::cell::Cell<(f64, f64)> = const { core::cell::Cell::new((0.0, 0.0)) };
+}
+
+/// Set the angle covered by a pixel of the current render in radians, for
+/// filtering textures. The renderer calls this before every pixel.
+pub fn set_pixel_spread(spread: f64) {
+    FOOTPRINT.with(|footprint| footprint.set((spread, footprint.get().1)));
+}
+
+/// Set how far the current path has travelled from the camera to the point
+/// being shaded, divided by the cosine of the angle it arrives at since
+/// pixels seen at a grazing angle stretch over more of a surface. `ray_color`
+/// calls this at every hit.
+pub fn set_path_length(length: f64) {
+    FOOTPRINT.with(|footprint| footprint.set((footprint.get().0, length)));
+}
+
+/// Approximate width of a pixel at the point being shaded, or `0.0` outside
+/// of a render.
+pub fn footprint() -> f64 {
+    FOOTPRINT.with(|footprint| {
+        let (spread, length) = footprint.get();
+        spread * length
+    })
+}
+
+/// Number of points of the pattern averaged by an antialiased [`Checker`].
+const CHECKER_SUBSAMPLES: u32 = 8;
+
 /// Texture with one color.
 #[derive(Clone)]
 pub struct Checker {
@@ -52,19 +84,99 @@ pub struct Checker {
     pub odd: Arc<dyn Texture + Send + Sync>,
     /// Even pattern.
     pub even: Arc<dyn Texture + Send + Sync>,
+    /// Whether to average the pattern over each pixel's [`footprint`] so it
+    /// doesn't shimmer in the distance, fading to an even mix of `odd` and
+    /// `even` where squares are much smaller than a pixel.
+    pub antialias: bool,
 }
 
 impl Checker {
     /// Create new checker texture.
     pub fn new(odd: Arc<dyn Texture + Send + Sync>, even: Arc<dyn Texture + Send + Sync>) -> Self {
-        Self { odd, even }
+        Self {
+            odd,
+            even,
+            antialias: false,
+        }
+    }
+
+    /// Set whether to average the pattern over each pixel.
+    pub fn with_antialias(self, antialias: bool) -> Self {
+        Self { antialias, ..self }
+    }
+
+    /// Whether `p` falls on an odd square.
+    fn is_odd(p: &Point3) -> bool {
+        (10.0 * p.x()).sin() * (10.0 * p.y()).sin() * (10.0 * p.z()).sin() < 0.0
     }
 }
 
 impl Texture for Checker {
     fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
-        let sines = (10.0 * p.x()).sin() * (10.0 * p.y()).sin() * (10.0 * p.z()).sin();
-        if sines < 0.0 {
+        // Squares are a half period of the sines across
+        let square = 0.1 * crate::conversion::PI;
+        let width = if self.antialias { footprint() } else { 0.0 };
+        if width < 0.05 * square {
+            return if Self::is_odd(p) {
+                self.odd.value(u, v, p)
+            } else {
+                self.even.value(u, v, p)
+            };
+        }
+
+        // Average jittered points over the footprint, then fade towards an
+        // even mix as more squares fit in it than the points can resolve.
+        // The jitter is hashed from `p` rather than drawn from the hit
+        // generator, which a medium sampling this texture already holds.
+        let pattern = [p.x(), p.y(), p.z()].iter().fold(0u32, |h, c| {
+            let bits = c.to_bits();
+            (h ^ bits as u32 ^ (bits >> 32) as u32).wrapping_mul(0x9e37_79b9)
+        });
+        let odd = (0..CHECKER_SUBSAMPLES)
+            .filter(|&k| {
+                let jitter = |axis| crate::sampler::randfloat(3 * k + axis, pattern) - 0.5;
+                Self::is_odd(&(*p + width * Vec3::new(jitter(0), jitter(1), jitter(2))))
+            })
+            .count();
+        let fraction = odd as f64 / f64::from(CHECKER_SUBSAMPLES);
+        let fade = ((width - 2.0 * square) / (2.0 * square)).clamp(0.0, 1.0);
+        let fade = fade * fade * (3.0 - 2.0 * fade);
+        let fraction = fraction + (0.5 - fraction) * fade;
+
+        fraction * self.odd.value(u, v, p) + (1.0 - fraction) * self.even.value(u, v, p)
+    }
+}
+
+/// Checker texture alternating in texture coordinates rather than space.
+///
+/// Squares follow a surface's `u` and `v`, so they line up with the edges of
+/// rectangles and triangles however those are rotated. Prefer [`Checker`] for
+/// volumes, which have no meaningful texture coordinates.
+#[derive(Clone)]
+pub struct UvChecker {
+    /// Odd pattern.
+    pub odd: Arc<dyn Texture + Send + Sync>,
+    /// Even pattern.
+    pub even: Arc<dyn Texture + Send + Sync>,
+    /// Number of squares along each texture coordinate.
+    pub scale: f64,
+}
+
+impl UvChecker {
+    /// Create new UV checker texture.
+    pub fn new(
+        odd: Arc<dyn Texture + Send + Sync>,
+        even: Arc<dyn Texture + Send + Sync>,
+        scale: f64,
+    ) -> Self {
+        Self { odd, even, scale }
+    }
+}
+
+impl Texture for UvChecker {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let parity = ((self.scale * u).floor() + (self.scale * v).floor()) as i64;
+        if parity.rem_euclid(2) == 1 {
             self.odd.value(u, v, p)
         } else {
             self.even.value(u, v, p)
@@ -72,6 +184,29 @@ impl Texture for Checker {
     }
 }
 
+/// Blend between two colors along a surface's `u` coordinate.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UvGradient {
+    /// Color where `u` is `0.0`.
+    pub start: Color,
+    /// Color where `u` is `1.0`.
+    pub end: Color,
+}
+
+impl UvGradient {
+    /// Create a new UV gradient texture.
+    pub fn new(start: Color, end: Color) -> Self {
+        Self { start, end }
+    }
+}
+
+impl Texture for UvGradient {
+    fn value(&self, u: f64, _v: f64, _p: &Point3) -> Color {
+        let t = u.clamp(0.0, 1.0);
+        (1.0 - t) * self.start + t * self.end
+    }
+}
+
 #[derive(Clone, Debug)]
 /// Perlin noise texture.
 pub struct Noise {
@@ -87,6 +222,9 @@ pub struct Noise {
     pub turb_depth: u32,
     /// Phase of `Marble` texture.
     pub phase: f64,
+    /// How far points are pushed around by another noise lookup before the
+    /// main one, which swirls the pattern. `0.0` disables the warping.
+    pub warp_strength: f64,
 }
 
 impl Noise {
@@ -107,6 +245,7 @@ impl Noise {
             scale,
             turb_depth,
             phase,
+            warp_strength: 0.0,
         }
     }
 
@@ -126,6 +265,7 @@ impl Noise {
             scale,
             turb_depth,
             phase,
+            warp_strength: 0.0,
         }
     }
 
@@ -144,8 +284,39 @@ impl Noise {
             scale,
             turb_depth,
             phase,
+            warp_strength: 0.0,
         }
     }
+
+    /// Start building a noise texture from named parameters, beginning with
+    /// the defaults.
+    pub fn builder() -> NoiseBuilder {
+        NoiseBuilder::default()
+    }
+
+    /// Warp the domain of the noise by `warp_strength` for swirling, organic
+    /// patterns.
+    pub fn with_warp(self, warp_strength: f64) -> Self {
+        Self {
+            warp_strength,
+            ..self
+        }
+    }
+
+    /// Offset `p` by smooth noise, with a different lookup for each axis.
+    fn warp(&self, p: &Point3) -> Point3 {
+        if self.warp_strength == 0.0 {
+            return *p;
+        }
+
+        let offset = |shift: Vec3| self.noise.noise(&(*p + shift), NoiseType::Smooth);
+        *p + self.warp_strength
+            * Vec3::new(
+                offset(Vec3::new(0.0, 0.0, 0.0)),
+                offset(Vec3::new(5.2, 1.3, 2.8)),
+                offset(Vec3::new(1.7, 9.2, 3.4)),
+            )
+    }
 }
 
 impl core::default::Default for Noise {
@@ -157,12 +328,94 @@ impl core::default::Default for Noise {
             scale: 4.0,
             turb_depth: 7,
             phase: 10.0,
+            warp_strength: 0.0,
+        }
+    }
+}
+
+/// Builder for [`Noise`] textures, so that parameters are set by name rather
+/// than by their position in [`Noise::new_with`].
+#[derive(Clone, Copy, Debug)]
+pub struct NoiseBuilder {
+    albedo: Color,
+    noise_type: NoiseType,
+    scale: f64,
+    turb_depth: u32,
+    phase: f64,
+    warp_strength: f64,
+}
+
+impl NoiseBuilder {
+    /// Color the noise is multiplied by. Defaults to white.
+    pub fn albedo(self, albedo: Color) -> Self {
+        Self { albedo, ..self }
+    }
+
+    /// Pattern of the noise. Defaults to [`NoiseType::Square`].
+    pub fn kind(self, noise_type: NoiseType) -> Self {
+        Self { noise_type, ..self }
+    }
+
+    /// Frequency of the noise, with larger values giving a finer pattern. For
+    /// `Marble`, the frequency of the stripes along Z instead. Defaults to
+    /// `4.0`.
+    pub fn scale(self, scale: f64) -> Self {
+        Self { scale, ..self }
+    }
+
+    /// Number of octaves summed for turbulence by `Marble` and `Net`, each at
+    /// twice the frequency and half the weight of the last. Defaults to `7`.
+    pub fn octaves(self, octaves: u32) -> Self {
+        Self {
+            turb_depth: octaves,
+            ..self
+        }
+    }
+
+    /// How strongly turbulence bends the `Marble` stripes. Defaults to `10.0`.
+    pub fn phase(self, phase: f64) -> Self {
+        Self { phase, ..self }
+    }
+
+    /// Distance points are pushed around before the lookup, see
+    /// [`Noise::with_warp`]. Defaults to `0.0`.
+    pub fn warp(self, warp_strength: f64) -> Self {
+        Self {
+            warp_strength,
+            ..self
+        }
+    }
+
+    /// Create the texture.
+    pub fn build(self) -> Noise {
+        Noise::from_color(
+            self.albedo,
+            self.noise_type,
+            self.scale,
+            self.turb_depth,
+            self.phase,
+        )
+        .with_warp(self.warp_strength)
+    }
+}
+
+impl core::default::Default for NoiseBuilder {
+    fn default() -> Self {
+        let noise = Noise::default();
+        Self {
+            albedo: noise.albedo,
+            noise_type: noise.noise_type,
+            scale: noise.scale,
+            turb_depth: noise.turb_depth,
+            phase: noise.phase,
+            warp_strength: noise.warp_strength,
         }
     }
 }
 
 impl Texture for Noise {
     fn value(&self, _u: f64, _v: f64, p: &Point3) -> crate::vec3::Color {
+        let p = &self.warp(p);
         match self.noise_type {
             NoiseType::Square | NoiseType::Trilinear => {
                 self.albedo * self.noise.noise(&(self.scale * *p), self.noise_type)
@@ -183,7 +436,37 @@ impl Texture for Noise {
     }
 }
 
+/// Environment variable naming the directory the built-in scenes load their
+/// textures from.
+pub const ASSET_DIR_VAR: &str = "RTIOW_ASSETS";
+
+/// Resolve a relative `path` against `base`, such as the directory of a scene
+/// file, instead of the current directory.
+///
+/// Absolute paths, and any path without a `base`, are returned unchanged.
+pub fn resolve_path<P: AsRef<std::path::Path>>(
+    base: Option<&std::path::Path>,
+    path: P,
+) -> std::path::PathBuf {
+    match base {
+        Some(base) => base.join(path),
+        None => path.as_ref().to_path_buf(),
+    }
+}
+
+/// Resolve a relative texture `path` against the directory in the
+/// [`ASSET_DIR_VAR`] environment variable, so scenes load their textures
+/// wherever the binary is run from. Without the variable, paths are relative
+/// to the current directory.
+pub fn asset_path<P: AsRef<std::path::Path>>(path: P) -> std::path::PathBuf {
+    let base = std::env::var_os(ASSET_DIR_VAR).map(std::path::PathBuf::from);
+    resolve_path(base.as_deref(), path)
+}
+
 /// Texture that holds image data.
+///
+/// Loading images requires the `images` feature. Without an image, the
+/// texture is magenta.
 #[derive(Clone, Debug, Default)]
 pub struct ImageTexture {
     /// Buffer of pixels.
@@ -199,20 +482,49 @@ pub struct ImageTexture {
 }
 
 impl ImageTexture {
+    #[cfg(feature = "images")]
     /// Create new `ImageTexture` from file.
+    ///
+    /// The format is detected from the file's contents, see
+    /// [`ImageTexture::from_memory`].
     pub fn new<P: AsRef<std::path::Path>>(filename: P) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_memory(&std::fs::read(filename)?)
+    }
+
+    #[cfg(feature = "images")]
+    /// Create new `ImageTexture` from an encoded image in memory, such as one
+    /// embedded with `include_bytes!`, detecting its format from its contents.
+    pub fn from_memory(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::from_image(image::load_from_memory(bytes)?))
+    }
+
+    #[cfg(feature = "images")]
+    /// Create new `ImageTexture` by decoding an image in `format` from
+    /// `reader`.
+    pub fn from_reader<R: std::io::Read + std::io::Seek>(
+        reader: R,
+        format: image::ImageFormat,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::from_image(image::load(
+            std::io::BufReader::new(reader),
+            format,
+        )?))
+    }
+
+    #[cfg(feature = "images")]
+    fn from_image(img: image::DynamicImage) -> Self {
         let bytes_per_pixel = 3;
-        let img = image::open(filename)?.to_rgb();
+        let img = img.to_rgb8();
         let (width, height) = img.dimensions();
         let data = img.into_raw();
 
-        Ok(Self {
+        Self {
             data,
             width,
             height,
             bytes_per_pixel,
             bytes_per_scanline: bytes_per_pixel * width,
-        })
+        }
     }
 }
 
@@ -222,8 +534,8 @@ impl Texture for ImageTexture {
             return Color::new(1.0, 0.0, 1.0);
         }
 
-        let u = u.max(0.0).min(1.0);
-        let v = 1.0 - v.max(0.0).min(1.0);
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
 
         let mut i = (u * f64::from(self.width)) as u32;
         let mut j = (v * f64::from(self.height)) as u32;
@@ -244,3 +556,24 @@ impl Texture for ImageTexture {
         )
     }
 }
+
+#[cfg(all(test, feature = "images"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn textures_load_relative_to_a_scene_directory() {
+        let scene_dir = std::env::temp_dir().join(format!("rtiow-{}-scene", std::process::id()));
+        std::fs::create_dir_all(scene_dir.join("textures")).unwrap();
+        image::RgbImage::from_pixel(2, 2, image::Rgb([255, 0, 0]))
+            .save(scene_dir.join("textures").join("red.png"))
+            .unwrap();
+
+        let path = resolve_path(Some(&scene_dir), "textures/red.png");
+        let texture = ImageTexture::new(&path);
+        std::fs::remove_dir_all(&scene_dir).unwrap();
+
+        let color = texture.unwrap().value(0.5, 0.5, &Point3::default());
+        assert_eq!((color.x(), color.y(), color.z()), (1.0, 0.0, 0.0));
+    }
+}
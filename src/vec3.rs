@@ -5,44 +5,119 @@ use rand::Rng;
 use crate::conversion::TWO_PI;
 
 /// General purpose Vector3 struct, basis for `Color` and `Point3` struct types.
+#[cfg(not(feature = "simd"))]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct Vec3(pub f64, pub f64, pub f64);
 
+/// General purpose Vector3 struct, basis for `Color` and `Point3` struct types.
+///
+/// With the `simd` feature the elements fill three of four `f64` lanes, so
+/// they're read with [`x`](Self::x), [`y`](Self::y) and [`z`](Self::z)
+/// rather than as tuple fields.
+#[cfg(feature = "simd")]
+#[derive(Clone, Copy, Default)]
+pub struct Vec3(wide::f64x4);
+
 /// Struct for 8-bit color used in image output.
 #[derive(Clone, Copy, Debug, Default)]
 pub struct ColorU8(pub u8, pub u8, pub u8);
 
+/// Struct for 8-bit color with straight alpha used in image output.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ColorU8A(pub u8, pub u8, pub u8, pub u8);
+
 /// Color struct holding (R, G, B).
 pub type Color = crate::vec3::Vec3;
 /// Point struct holding (x, y, z).
 pub type Point3 = crate::vec3::Vec3;
 
+#[cfg(not(feature = "simd"))]
 impl Vec3 {
     /// Create a new `Vec3`.
-    pub fn new(a: f64, b: f64, c: f64) -> Self {
+    #[inline]
+    pub const fn new(a: f64, b: f64, c: f64) -> Self {
         Self(a, b, c)
     }
 
-    /// Create a new `Vec3` with the same value for all fields.
-    pub fn new_with(a: f64) -> Self {
-        Self(a, a, a)
-    }
-
     /// Return the first element of the tuple.
+    #[inline]
     pub fn x(&self) -> f64 {
         self.0
     }
 
     /// Return the second element of the tuple.
+    #[inline]
     pub fn y(&self) -> f64 {
         self.1
     }
 
     /// Return the third element of the tuple.
+    #[inline]
     pub fn z(&self) -> f64 {
         self.2
     }
 
+    /// Return the dot product of the vector and another `v`.
+    #[inline]
+    pub fn dot(&self, v: &Self) -> f64 {
+        self.0 * v.0 + self.1 * v.1 + self.2 * v.2
+    }
+}
+
+#[cfg(feature = "simd")]
+impl Vec3 {
+    /// Create a new `Vec3`.
+    #[inline]
+    pub const fn new(a: f64, b: f64, c: f64) -> Self {
+        Self(wide::f64x4::new([a, b, c, 0.0]))
+    }
+
+    /// Return the first element.
+    #[inline]
+    pub fn x(&self) -> f64 {
+        self.0.as_array_ref()[0]
+    }
+
+    /// Return the second element.
+    #[inline]
+    pub fn y(&self) -> f64 {
+        self.0.as_array_ref()[1]
+    }
+
+    /// Return the third element.
+    #[inline]
+    pub fn z(&self) -> f64 {
+        self.0.as_array_ref()[2]
+    }
+
+    /// Return the dot product of the vector and another `v`.
+    #[inline]
+    pub fn dot(&self, v: &Self) -> f64 {
+        // Summed in the scalar order, leaving out the padding lane, which
+        // isn't kept at zero by every operation
+        let [a, b, c, _] = (self.0 * v.0).to_array();
+        a + b + c
+    }
+}
+
+#[cfg(feature = "simd")]
+impl core::fmt::Debug for Vec3 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("Vec3")
+            .field(&self.x())
+            .field(&self.y())
+            .field(&self.z())
+            .finish()
+    }
+}
+
+impl Vec3 {
+    /// Create a new `Vec3` with the same value for all fields.
+    #[inline]
+    pub const fn new_with(a: f64) -> Self {
+        Self::new(a, a, a)
+    }
+
     /// Return the length of the vector.
     pub fn length(&self) -> f64 {
         self.length_squared().sqrt()
@@ -50,20 +125,15 @@ impl Vec3 {
 
     /// Return the squared length of the vector.
     pub fn length_squared(&self) -> f64 {
-        self.0 * self.0 + self.1 * self.1 + self.2 * self.2
-    }
-
-    /// Return the dot product of the vector and another `v`.
-    pub fn dot(&self, v: &Self) -> f64 {
-        self.0 * v.0 + self.1 * v.1 + self.2 * v.2
+        self.dot(self)
     }
 
     /// Return the cross product of the vector and another `v`.
     pub fn cross(&self, v: &Self) -> Self {
-        Vec3(
-            self.1 * v.2 - self.2 * v.1,
-            self.2 * v.0 - self.0 * v.2,
-            self.0 * v.1 - self.1 * v.0,
+        Vec3::new(
+            self.y() * v.z() - self.z() * v.y(),
+            self.z() * v.x() - self.x() * v.z(),
+            self.x() * v.y() - self.y() * v.x(),
         )
     }
 
@@ -72,16 +142,46 @@ impl Vec3 {
         *self / self.length()
     }
 
+    /// Return the square root of each element.
+    pub fn sqrt(&self) -> Self {
+        Vec3::new(self.x().sqrt(), self.y().sqrt(), self.z().sqrt())
+    }
+
+    /// Return `e` raised to the power of each element.
+    pub fn exp(&self) -> Self {
+        Vec3::new(self.x().exp(), self.y().exp(), self.z().exp())
+    }
+
+    /// Return each element raised to the power `n`.
+    pub fn powf(&self, n: f64) -> Self {
+        Vec3::new(self.x().powf(n), self.y().powf(n), self.z().powf(n))
+    }
+
+    /// Return the reciprocal of each element.
+    pub fn recip(&self) -> Self {
+        Vec3::new(self.x().recip(), self.y().recip(), self.z().recip())
+    }
+
+    /// Return the elements as an array.
+    pub fn as_array(&self) -> [f64; 3] {
+        [self.x(), self.y(), self.z()]
+    }
+
+    /// Return the elements as an array of `f32`, as graphics libraries expect.
+    pub fn to_f32_array(&self) -> [f32; 3] {
+        [self.x() as f32, self.y() as f32, self.z() as f32]
+    }
+
     /// Generate a Vec3 with range `[0.0, 1.0)` for each element.
     #[inline]
     pub fn random<R: Rng>(rng: &mut R) -> Self {
-        Vec3(rng.gen(), rng.gen(), rng.gen())
+        Vec3::new(rng.gen(), rng.gen(), rng.gen())
     }
 
     /// Generate a Vec3 with range `[min, max)` for each element.
     #[inline]
     pub fn random_range<R: Rng>(rng: &mut R, min: f64, max: f64) -> Self {
-        Vec3(
+        Vec3::new(
             rng.gen_range(min, max),
             rng.gen_range(min, max),
             rng.gen_range(min, max),
@@ -94,17 +194,30 @@ impl Vec3 {
         let a = rng.gen_range(0.0, TWO_PI);
         let z = rng.gen_range(-1.0, 1.0);
         let r = f64::sqrt(1.0 - z * z);
-        Vec3(r * a.cos(), r * a.sin(), z)
+        Vec3::new(r * a.cos(), r * a.sin(), z)
     }
 
-    /// Sample a circle uniformly.
+    /// Sample a point uniformly by area over the disk of radius `1.0` in the
+    /// XY plane, as used for points on a camera lens.
+    ///
+    /// The radius is the square root of a uniform number, since the area
+    /// within a radius grows with its square. Taking it directly would bunch
+    /// points up near the center.
     #[inline]
-    pub fn random_in_unit_circle<R: Rng>(rng: &mut R) -> Self {
+    pub fn random_in_unit_disk<R: Rng>(rng: &mut R) -> Self {
         let r = rng.gen::<f64>().sqrt();
         let theta = rng.gen::<f64>() * TWO_PI;
         Self::new(r * theta.cos(), r * theta.sin(), 0.0)
     }
 
+    /// Sample the unit disk uniformly.
+    ///
+    /// Kept for the older name, prefer [`Vec3::random_in_unit_disk`].
+    #[inline]
+    pub fn random_in_unit_circle<R: Rng>(rng: &mut R) -> Self {
+        Self::random_in_unit_disk(rng)
+    }
+
     /// Create a random vector in a unit sphere.
     #[inline]
     pub fn random_in_unit_sphere<R: Rng>(rng: &mut R) -> Self {
@@ -121,7 +234,7 @@ impl Vec3 {
     #[inline]
     pub fn random_in_hemisphere<R: Rng>(rng: &mut R, normal: &Self) -> Self {
         let in_unit_sphere = Self::random_in_unit_sphere(rng);
-        if in_unit_sphere.dot(&normal) > 0.0 {
+        if in_unit_sphere.dot(normal) > 0.0 {
             in_unit_sphere
         } else {
             -in_unit_sphere
@@ -131,7 +244,7 @@ impl Vec3 {
     /// Calculate the reflection of a vector and normal `n`.
     #[inline]
     pub fn reflect(v: &Self, n: &Self) -> Self {
-        *v - (v.dot(&n) * 2.0) * *n
+        *v - (v.dot(n) * 2.0) * *n
     }
 
     /// Calculate the reflection of a vector and normal `n` and etas
@@ -144,135 +257,202 @@ impl Vec3 {
     }
 }
 
+#[cfg(not(feature = "simd"))]
 impl core::ops::Neg for Vec3 {
     type Output = Self;
 
+    #[inline]
     fn neg(self) -> Self::Output {
         Vec3(-self.0, -self.1, -self.2)
     }
 }
 
+#[cfg(not(feature = "simd"))]
 impl core::ops::Add for Vec3 {
     type Output = Self;
 
+    #[inline]
     fn add(self, other: Self) -> Self {
         Vec3(self.0 + other.0, self.1 + other.1, self.2 + other.2)
     }
 }
 
-impl core::ops::Add<Vec3> for f64 {
-    type Output = Vec3;
+#[cfg(not(feature = "simd"))]
+impl core::ops::Sub for Vec3 {
+    type Output = Self;
 
-    fn add(self, rhs: Vec3) -> Self::Output {
-        Vec3(self + rhs.0, self + rhs.1, self + rhs.2)
+    #[inline]
+    fn sub(self, other: Self) -> Self {
+        Vec3(self.0 - other.0, self.1 - other.1, self.2 - other.2)
     }
 }
 
-impl core::ops::AddAssign for Vec3 {
-    fn add_assign(&mut self, other: Vec3) {
-        self.0 += other.0;
-        self.1 += other.1;
-        self.2 += other.2;
+#[cfg(not(feature = "simd"))]
+impl core::ops::Mul for Vec3 {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, other: Self) -> Self {
+        Vec3(self.0 * other.0, self.1 * other.1, self.2 * other.2)
     }
 }
 
-impl core::ops::Sub for Vec3 {
+#[cfg(feature = "simd")]
+impl core::ops::Neg for Vec3 {
     type Output = Self;
 
-    fn sub(self, other: Self) -> Self {
-        Vec3(self.0 - other.0, self.1 - other.1, self.2 - other.2)
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Vec3(-self.0)
     }
 }
 
-impl core::ops::SubAssign for Vec3 {
-    fn sub_assign(&mut self, other: Vec3) {
-        self.0 -= other.0;
-        self.1 -= other.1;
-        self.2 -= other.2;
+#[cfg(feature = "simd")]
+impl core::ops::Add for Vec3 {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, other: Self) -> Self {
+        Vec3(self.0 + other.0)
     }
 }
 
+#[cfg(feature = "simd")]
+impl core::ops::Sub for Vec3 {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, other: Self) -> Self {
+        Vec3(self.0 - other.0)
+    }
+}
+
+#[cfg(feature = "simd")]
 impl core::ops::Mul for Vec3 {
     type Output = Self;
 
+    #[inline]
     fn mul(self, other: Self) -> Self {
-        Vec3(self.0 * other.0, self.1 * other.1, self.2 * other.2)
+        Vec3(self.0 * other.0)
+    }
+}
+
+// The rest are built on the element-wise operators above, so they do the same
+// arithmetic in either layout
+
+impl core::ops::Add<Vec3> for f64 {
+    type Output = Vec3;
+
+    #[inline]
+    fn add(self, rhs: Vec3) -> Self::Output {
+        Vec3::new_with(self) + rhs
+    }
+}
+
+impl core::ops::AddAssign for Vec3 {
+    #[inline]
+    fn add_assign(&mut self, other: Vec3) {
+        *self = *self + other;
+    }
+}
+
+impl core::ops::SubAssign for Vec3 {
+    #[inline]
+    fn sub_assign(&mut self, other: Vec3) {
+        *self = *self - other;
     }
 }
 
 impl core::ops::MulAssign for Vec3 {
+    #[inline]
     fn mul_assign(&mut self, other: Vec3) {
-        self.0 *= other.0;
-        self.1 *= other.1;
-        self.2 *= other.2;
+        *self = *self * other;
     }
 }
 
 impl core::ops::Mul<Vec3> for f64 {
     type Output = Vec3;
 
+    #[inline]
     fn mul(self, rhs: Vec3) -> Self::Output {
-        Vec3(self * rhs.0, self * rhs.1, self * rhs.2)
+        Vec3::new_with(self) * rhs
     }
 }
 
 impl core::ops::Mul<f64> for Vec3 {
     type Output = Self;
 
+    #[inline]
     fn mul(self, rhs: f64) -> Self {
-        Vec3(self.0 * rhs, self.1 * rhs, self.2 * rhs)
+        self * Vec3::new_with(rhs)
     }
 }
 
 impl core::ops::MulAssign<f64> for Vec3 {
+    #[inline]
     fn mul_assign(&mut self, rhs: f64) {
-        self.0 *= rhs;
-        self.1 *= rhs;
-        self.2 *= rhs;
+        *self = *self * rhs;
     }
 }
 
 impl core::ops::Div<Vec3> for f64 {
     type Output = Vec3;
 
+    #[inline]
     fn div(self, rhs: Vec3) -> Self::Output {
-        Vec3(
-            self.recip() * rhs.0,
-            self.recip() * rhs.1,
-            self.recip() * rhs.2,
-        )
+        self.recip() * rhs
     }
 }
 
 impl core::ops::Div<f64> for Vec3 {
     type Output = Self;
 
+    #[inline]
     fn div(self, rhs: f64) -> Self {
-        Vec3(
-            self.0 * rhs.recip(),
-            self.1 * rhs.recip(),
-            self.2 * rhs.recip(),
-        )
+        self * rhs.recip()
     }
 }
 
 impl core::ops::DivAssign<f64> for Vec3 {
+    #[inline]
     fn div_assign(&mut self, rhs: f64) {
-        self.0 *= rhs.recip();
-        self.1 *= rhs.recip();
-        self.2 *= rhs.recip();
+        *self = *self / rhs;
     }
 }
 
 impl core::fmt::Display for Vec3 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{} {} {}", self.0, self.1, self.2)
+        write!(f, "{} {} {}", self.x(), self.y(), self.z())
     }
 }
 
 impl core::iter::Sum for Vec3 {
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
-        iter.fold(Self(0.0, 0.0, 0.0), |a, b| a + b)
+        iter.fold(Self::new_with(0.0), |a, b| a + b)
+    }
+}
+
+impl From<[f64; 3]> for Vec3 {
+    fn from([a, b, c]: [f64; 3]) -> Self {
+        Self::new(a, b, c)
+    }
+}
+
+impl From<Vec3> for [f64; 3] {
+    fn from(other: Vec3) -> Self {
+        other.as_array()
+    }
+}
+
+impl From<[f32; 3]> for Vec3 {
+    fn from([a, b, c]: [f32; 3]) -> Self {
+        Self::new(a.into(), b.into(), c.into())
+    }
+}
+
+impl From<Vec3> for [f32; 3] {
+    fn from(other: Vec3) -> Self {
+        other.to_f32_array()
     }
 }
 
@@ -281,3 +461,93 @@ impl From<ColorU8> for std::vec::Vec<u8> {
         [other.0, other.1, other.2].to_vec()
     }
 }
+
+impl From<ColorU8A> for std::vec::Vec<u8> {
+    fn from(other: ColorU8A) -> Self {
+        [other.0, other.1, other.2, other.3].to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_disk_samples_are_uniform_in_area() {
+        // Rings of equal area hold equal shares of the points when sampling is
+        // uniform, so check the counts with a chi-squared test
+        const RINGS: usize = 10;
+        const SAMPLES: usize = 100_000;
+        let mut rng = crate::rng::pixel_rng(1, 0);
+        let mut counts = [0usize; RINGS];
+        for _ in 0..SAMPLES {
+            let p = Vec3::random_in_unit_disk(&mut rng);
+            assert_eq!(p.z(), 0.0);
+            let r_squared = p.length_squared();
+            assert!(r_squared < 1.0, "{:?} is outside the disk", p);
+            counts[(r_squared * RINGS as f64) as usize] += 1;
+        }
+
+        let expected = (SAMPLES / RINGS) as f64;
+        let chi_squared: f64 = counts
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum();
+        // 99.9th percentile with 9 degrees of freedom
+        assert!(chi_squared < 27.88, "rings {:?}", counts);
+    }
+
+    #[test]
+    fn arrays_round_trip() {
+        let v = Vec3::from([0.1, -2.5, 1e10]);
+        assert_eq!(v.as_array(), [0.1, -2.5, 1e10]);
+        assert_eq!(<[f64; 3]>::from(v), [0.1, -2.5, 1e10]);
+
+        // Any f32 is exactly representable as an f64
+        let single = [0.1f32, -2.5, 3.4e38];
+        let v = Vec3::from(single);
+        assert_eq!(v.to_f32_array(), single);
+        assert_eq!(<[f32; 3]>::from(v), single);
+    }
+
+    #[test]
+    fn elementwise_math_matches_each_component() {
+        let v = Vec3::new(0.25, 4.0, 9.0);
+        assert_eq!(v.sqrt().as_array(), [0.5, 2.0, 3.0]);
+        assert_eq!(
+            v.exp().as_array(),
+            [0.25f64.exp(), 4.0f64.exp(), 9.0f64.exp()]
+        );
+        assert_eq!(v.powf(1.5).as_array(), [0.125, 8.0, 27.0]);
+        assert_eq!(v.recip().as_array(), [4.0, 0.25, 1.0 / 9.0]);
+    }
+
+    #[test]
+    fn operators_match_scalar_arithmetic() {
+        // Whichever layout is built, each operator gives the same bits as
+        // doing it a component at a time
+        let mut rng = crate::rng::pixel_rng(1, 0);
+        for _ in 0..1000 {
+            let a = Vec3::random_range(&mut rng, -1e3, 1e3);
+            let b = Vec3::random_range(&mut rng, -1e3, 1e3);
+            let k: f64 = rng.gen_range(-10.0, 10.0);
+            let [ax, ay, az] = a.as_array();
+            let [bx, by, bz] = b.as_array();
+
+            assert_eq!((a + b).as_array(), [ax + bx, ay + by, az + bz]);
+            assert_eq!((a - b).as_array(), [ax - bx, ay - by, az - bz]);
+            assert_eq!((a * b).as_array(), [ax * bx, ay * by, az * bz]);
+            assert_eq!((-a).as_array(), [-ax, -ay, -az]);
+            assert_eq!((k * a).as_array(), [k * ax, k * ay, k * az]);
+            assert_eq!(
+                (a / k).as_array(),
+                [ax * k.recip(), ay * k.recip(), az * k.recip()]
+            );
+            assert_eq!(a.dot(&b), ax * bx + ay * by + az * bz);
+            assert_eq!(
+                a.cross(&b).as_array(),
+                [ay * bz - az * by, az * bx - ax * bz, ax * by - ay * bx]
+            );
+        }
+    }
+}
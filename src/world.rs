@@ -0,0 +1,108 @@
+//! The collection of objects handed to the renderer.
+
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::bvh::BvhNode;
+use crate::hittable::{Hittable, HittableList};
+use crate::light::SpotLight;
+
+/// Objects in a scene along with the objects to sample as lights.
+#[derive(Clone)]
+pub struct World {
+    /// Every object that rays can strike.
+    pub objects: HittableList,
+    /// Objects that scattered rays are importance sampled towards. Without
+    /// them, diffuse bounces only sample their materials, which suits scenes
+    /// lit by the background alone.
+    pub lights: Option<Arc<dyn Hittable + Send + Sync>>,
+    /// Probability that a diffuse bounce is sampled towards `lights` instead
+    /// of from its material, see [`ray_color`](crate::ray::ray_color).
+    pub light_weight: f64,
+    /// Spotlights sampled directly at every diffuse bounce.
+    pub spot_lights: Vec<SpotLight>,
+}
+
+impl World {
+    /// Create a new `World`.
+    ///
+    /// `lights` with nothing in them to sample, like an empty list, are left
+    /// out as in [`World::without_lights`].
+    pub fn new(objects: HittableList, lights: Arc<dyn Hittable + Send + Sync>) -> Self {
+        Self {
+            objects,
+            lights: Some(lights).filter(can_sample),
+            light_weight: 0.5,
+            spot_lights: Vec::new(),
+        }
+    }
+
+    /// Create a new `World` with no objects to sample as lights.
+    pub fn without_lights(objects: HittableList) -> Self {
+        Self {
+            objects,
+            lights: None,
+            light_weight: 0.5,
+            spot_lights: Vec::new(),
+        }
+    }
+
+    /// `lights`, unless there's nothing in them to sample.
+    pub fn sampled_lights(&self) -> Option<&Arc<dyn Hittable + Send + Sync>> {
+        self.lights.as_ref().filter(|lights| can_sample(lights))
+    }
+
+    /// Sample `lights` with probability `light_weight` at diffuse bounces.
+    pub fn with_light_weight(self, light_weight: f64) -> Self {
+        Self {
+            light_weight,
+            ..self
+        }
+    }
+
+    /// Light the scene with spotlights as well as its emissive objects.
+    pub fn with_spot_lights(self, spot_lights: Vec<SpotLight>) -> Self {
+        Self {
+            spot_lights,
+            ..self
+        }
+    }
+
+    /// Gather the bounded objects into a bounding volume hierarchy.
+    ///
+    /// Objects without a bounding box, like infinite planes, are kept outside
+    /// the hierarchy next to it. Nothing changes if there are no bounded
+    /// objects.
+    pub fn with_bvh(self, time0: f64, time1: f64) -> Self {
+        let mut bounded = HittableList::default();
+        let mut unbounded = HittableList::default();
+        let mut bbox = Aabb::default();
+        for object in self.objects.iter() {
+            if object.bounding_box(time0, time1, &mut bbox) {
+                bounded.add(object.clone());
+            } else {
+                unbounded.add(object.clone());
+            }
+        }
+
+        match BvhNode::try_from_list(&bounded, time0, time1) {
+            Ok(bvh) => {
+                unbounded.add(Arc::new(bvh));
+                Self {
+                    objects: unbounded,
+                    ..self
+                }
+            }
+            Err(_) => self,
+        }
+    }
+}
+
+/// Whether `lights` has anything in it for scattered rays to be sampled
+/// towards.
+///
+/// Lights without a bounding box, like an empty list, have a pdf of zero in
+/// every direction, and sampling them would only waste the bounce or give NaNs.
+fn can_sample(lights: &Arc<dyn Hittable + Send + Sync>) -> bool {
+    lights.bounding_box(0.0, 1.0, &mut Aabb::default())
+}